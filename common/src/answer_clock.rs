@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::*;
+
+/// Time the player at the head of the buzzer queue has to answer. Runs on the
+/// server, separately from the question countdown.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AnswerClock {
+    pub player_id: Uuid,
+    /// Server time the answer is due, in milliseconds since the epoch
+    pub deadline_ms: u64,
+    /// Set once the time ran out without the host judging the answer
+    #[serde(default)]
+    pub expired: bool,
+}
+
+impl AnswerClock {
+    pub fn remaining_ms(&self, now_ms: u64) -> u64 {
+        if self.expired {
+            0
+        } else {
+            self.deadline_ms.saturating_sub(now_ms)
+        }
+    }
+}
+
+impl GameState {
+    pub(crate) fn start_answer_clock(&mut self, player_id: Uuid, time_limit: u32, now_ms: u64) {
+        self.answer_clock = Some(AnswerClock {
+            player_id,
+            deadline_ms: now_ms + u64::from(time_limit) * 1000,
+            expired: false,
+        });
+    }
+
+    /// Starts the clock for a player who just got to the head of the queue,
+    /// or stops it when the queue emptied or there is no time limit.
+    pub(crate) fn sync_answer_clock(&mut self, now_ms: u64) -> Vec<Outbound> {
+        let time_limit = self.rules.answer_time_limit_secs;
+        let head = self
+            .buzzer_order
+            .front()
+            .map(|(id, _)| *id)
+            .filter(|_| time_limit > 0);
+        if head == self.answer_clock.as_ref().map(|clock| clock.player_id) {
+            return Vec::new();
+        }
+
+        let mut out = Vec::new();
+        match head {
+            Some(player_id) => {
+                self.record(
+                    now_ms,
+                    GameEvent::AnswerClockStarted {
+                        player_id,
+                        time_limit,
+                    },
+                );
+                out.push(Outbound::ScheduleAnswerExpiry(
+                    now_ms + u64::from(time_limit) * 1000,
+                ));
+            }
+            None => {
+                self.record(now_ms, GameEvent::AnswerClockStopped);
+                out.push(Outbound::CancelAnswerExpiry);
+            }
+        }
+        out.push(self.state_update());
+        out
+    }
+
+    /// The answer clock that was due at `deadline_ms` ran out. Depending on
+    /// the rules the answer counts as wrong right away, or the host decides.
+    pub fn answer_clock_elapsed(&mut self, deadline_ms: u64) -> Vec<Outbound> {
+        let Some(clock) = self
+            .answer_clock
+            .as_ref()
+            .filter(|clock| !clock.expired && clock.deadline_ms == deadline_ms)
+        else {
+            return Vec::new();
+        };
+        let player_id = clock.player_id;
+        self.record(deadline_ms, GameEvent::AnswerClockExpired { player_id });
+        let mut out = vec![self.state_update()];
+        if self.rules.wrong_when_answer_time_up
+            && let Ok(judged) = self.judge_head(deadline_ms, false)
+        {
+            out.extend(judged);
+            out.extend(self.sync_answer_clock(deadline_ms));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    /// A game where the head of the queue has 5 seconds to answer
+    fn timed_game(wrong_when_time_up: bool) -> (GameState, Uuid) {
+        let (mut game, host_id) = new_game();
+        let rules = GameRules {
+            answer_time_limit_secs: 5,
+            wrong_when_answer_time_up: wrong_when_time_up,
+            penalty_wrong: 3,
+            ..GameRules::default()
+        };
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::SetRules {
+                game_code: CODE,
+                rules,
+            },
+        )
+        .unwrap();
+        (game, host_id)
+    }
+
+    fn judge(game: &mut GameState, host_id: Uuid, correct: bool, now_ms: u64) -> Vec<Outbound> {
+        let judge = ClientToServer::Judge {
+            game_code: CODE,
+            correct,
+        };
+        game.apply(host_id, judge, &ctx(now_ms)).unwrap()
+    }
+
+    fn scheduled(out: &[Outbound]) -> Option<u64> {
+        out.iter().find_map(|o| match o {
+            Outbound::ScheduleAnswerExpiry(deadline_ms) => Some(*deadline_ms),
+            _ => None,
+        })
+    }
+
+    fn cancelled(out: &[Outbound]) -> bool {
+        out.iter()
+            .any(|o| matches!(o, Outbound::CancelAnswerExpiry))
+    }
+
+    fn clock_player(game: &GameState) -> Option<Uuid> {
+        game.answer_clock.as_ref().map(|clock| clock.player_id)
+    }
+
+    #[test]
+    fn the_clock_starts_for_the_first_in_the_queue() {
+        let (mut game, _) = timed_game(false);
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+
+        let out = buzz(&mut game, alice, 1_000).unwrap();
+        assert_eq!(scheduled(&out), Some(6_000));
+        let clock = game.answer_clock.clone().unwrap();
+        assert_eq!(clock.player_id, alice);
+        assert_eq!(clock.remaining_ms(2_500), 3_500);
+
+        // Buzzing behind Alice leaves her clock alone
+        let out = buzz(&mut game, bob, 1_100).unwrap();
+        assert_eq!(scheduled(&out), None);
+        assert_eq!(game.answer_clock, Some(clock));
+    }
+
+    #[test]
+    fn the_clock_moves_on_with_the_queue() {
+        let (mut game, host_id) = timed_game(false);
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        buzz(&mut game, alice, 1_000).unwrap();
+        buzz(&mut game, bob, 1_100).unwrap();
+
+        let out = judge(&mut game, host_id, false, 2_000);
+        assert_eq!(scheduled(&out), Some(7_000));
+        assert_eq!(clock_player(&game), Some(bob));
+
+        let out = judge(&mut game, host_id, true, 3_000);
+        assert!(cancelled(&out));
+        assert_eq!(game.answer_clock, None);
+    }
+
+    #[test]
+    fn clearing_the_queue_stops_the_clock() {
+        let (mut game, host_id) = timed_game(false);
+        let (alice, _) = join(&mut game, "Alice");
+        buzz(&mut game, alice, 1_000).unwrap();
+
+        let clear = ClientToServer::Clear { game_code: CODE };
+        let out = game.apply(host_id, clear, &ctx(2_000)).unwrap();
+        assert!(cancelled(&out));
+        assert_eq!(game.answer_clock, None);
+        // A stopped clock running out changes nothing
+        assert!(game.answer_clock_elapsed(6_000).is_empty());
+    }
+
+    #[test]
+    fn running_out_leaves_the_answer_to_the_host() {
+        let (mut game, _) = timed_game(false);
+        let (alice, _) = join(&mut game, "Alice");
+        buzz(&mut game, alice, 1_000).unwrap();
+
+        // A deadline from an earlier clock is ignored
+        assert!(game.answer_clock_elapsed(5_000).is_empty());
+        assert!(!game.answer_clock_elapsed(6_000).is_empty());
+        let clock = game.answer_clock.as_ref().unwrap();
+        assert!(clock.expired);
+        assert_eq!(clock.remaining_ms(5_500), 0);
+        assert_eq!(order(&game), [alice]);
+        assert_eq!(game.scores[&alice], 0);
+        // It only runs out once
+        assert!(game.answer_clock_elapsed(6_000).is_empty());
+    }
+
+    #[test]
+    fn running_out_can_count_as_wrong() {
+        let (mut game, _) = timed_game(true);
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        buzz(&mut game, alice, 1_000).unwrap();
+        buzz(&mut game, bob, 1_100).unwrap();
+
+        let out = game.answer_clock_elapsed(6_000);
+        assert_eq!(game.scores[&alice], -3);
+        assert_eq!(order(&game), [bob]);
+        assert_eq!(scheduled(&out), Some(11_000));
+        assert_eq!(clock_player(&game), Some(bob));
+
+        let out = game.answer_clock_elapsed(11_000);
+        assert_eq!(game.scores[&bob], -3);
+        assert!(cancelled(&out));
+        assert_eq!(game.answer_clock, None);
+    }
+}
@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// One cell of the board
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BoardClue {
+    /// Position of the clue's question in the question set
+    pub index: usize,
+    pub value: i32,
+    /// Whether the host already picked the clue
+    #[serde(default)]
+    pub used: bool,
+}
+
+/// A column of the board, cheapest clue first
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BoardCategory {
+    pub name: String,
+    pub clues: Vec<BoardClue>,
+}
+
+/// A grid of categories and point values built from the question set.
+/// Only its layout is public; the clues themselves stay in the question set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Board {
+    /// Categories in the order they first appear in the question set
+    pub categories: Vec<BoardCategory>,
+    /// Question set position of the clue being played
+    pub active: Option<usize>,
+}
+
+impl Board {
+    /// Lays out every question that has both a category and points
+    pub fn from_questions(questions: &[Question]) -> Result<Self, &'static str> {
+        let mut categories: Vec<BoardCategory> = Vec::new();
+        for (index, question) in questions.iter().enumerate() {
+            let (Some(name), Some(value)) = (&question.category, question.points) else {
+                continue;
+            };
+            let clue = BoardClue {
+                index,
+                value,
+                used: false,
+            };
+            match categories.iter_mut().find(|c| c.name == *name) {
+                Some(category) => category.clues.push(clue),
+                None => categories.push(BoardCategory {
+                    name: name.clone(),
+                    clues: vec![clue],
+                }),
+            }
+        }
+        if categories.is_empty() {
+            return Err("No question in the set has both a category and points.");
+        }
+        for category in &mut categories {
+            category.clues.sort_by_key(|clue| clue.value);
+        }
+        Ok(Self {
+            categories,
+            active: None,
+        })
+    }
+
+    pub fn clue(&self, index: usize) -> Option<&BoardClue> {
+        self.categories
+            .iter()
+            .flat_map(|c| c.clues.iter())
+            .find(|clue| clue.index == index)
+    }
+
+    pub fn active_clue(&self) -> Option<&BoardClue> {
+        self.clue(self.active?)
+    }
+
+    /// Whether every clue has been played
+    pub fn finished(&self) -> bool {
+        self.categories
+            .iter()
+            .all(|c| c.clues.iter().all(|clue| clue.used))
+    }
+}
+
+impl GameState {
+    /// The value of the clue being played, which judging uses instead of the
+    /// usual points
+    pub fn active_clue_value(&self) -> Option<i32> {
+        Some(self.board.as_ref()?.active_clue()?.value)
+    }
+
+    pub(crate) fn play_clue(&mut self, at_ms: u64, index: usize) {
+        let Some(board) = self.board.as_mut() else {
+            return;
+        };
+        for clue in board.categories.iter_mut().flat_map(|c| c.clues.iter_mut()) {
+            if clue.index == index {
+                clue.used = true;
+            }
+        }
+        board.active = Some(index);
+        self.current_question = Some(index);
+        self.revealed = None;
+        self.clear_buzzes();
+        self.unlock_buzzers(at_ms);
+    }
+
+    pub(crate) fn open_board(&mut self, now_ms: u64) -> Result<Vec<Outbound>, GameError> {
+        let board = Board::from_questions(&self.question_bank).map_err(GameError::InvalidBoard)?;
+        self.record(now_ms, GameEvent::BoardOpened { board });
+        Ok(vec![self.state_update()])
+    }
+
+    /// Shows the clue to everyone and opens the buzzers for it. A clue that
+    /// was being played is archived as a question of its own first.
+    pub(crate) fn select_clue(
+        &mut self,
+        now_ms: u64,
+        index: usize,
+    ) -> Result<Vec<Outbound>, GameError> {
+        let board = self
+            .board
+            .as_ref()
+            .ok_or(GameError::InvalidBoard("There is no board to pick from."))?;
+        let clue = board.clue(index).ok_or(GameError::NoQuestion)?;
+        if clue.used {
+            return Err(GameError::InvalidBoard(
+                "That clue has already been played.",
+            ));
+        }
+        let question = self
+            .revealed_question(index, false)
+            .ok_or(GameError::NoQuestion)?;
+
+        let mut out = Vec::new();
+        if board.active.is_some() {
+            out.push(self.archive_question(now_ms, false));
+            out.push(Outbound::CancelCountdownExpiry);
+        }
+        self.record(now_ms, GameEvent::ClueSelected { index });
+        self.record(now_ms, GameEvent::QuestionRevealed { question });
+        out.push(self.state_update());
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    fn clue(category: Option<&str>, points: Option<i32>) -> Question {
+        Question {
+            question: format!("{:?} for {:?}", category, points),
+            answer: "Answer".to_string(),
+            category: category.map(str::to_string),
+            points,
+            media: None,
+        }
+    }
+
+    /// Imports a question set and puts up its board
+    fn board_game() -> (GameState, Uuid) {
+        let (mut game, host_id) = new_game();
+        let questions = vec![
+            clue(Some("Rivers"), Some(200)),
+            clue(Some("Capitals"), Some(100)),
+            clue(Some("Rivers"), Some(100)),
+            clue(None, Some(100)),
+            clue(Some("Capitals"), None),
+        ];
+        let import = ClientToServer::ImportQuestions {
+            game_code: CODE,
+            questions,
+        };
+        host(&mut game, host_id, import).unwrap();
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::OpenBoard { game_code: CODE },
+        )
+        .unwrap();
+        (game, host_id)
+    }
+
+    fn select(
+        game: &mut GameState,
+        host_id: Uuid,
+        index: usize,
+    ) -> Result<Vec<Outbound>, GameError> {
+        host(
+            game,
+            host_id,
+            ClientToServer::SelectClue {
+                game_code: CODE,
+                index,
+            },
+        )
+    }
+
+    #[test]
+    fn the_board_is_laid_out_by_category_and_value() {
+        let (game, _) = board_game();
+        let board = game.board.as_ref().unwrap();
+        let layout: Vec<(&str, Vec<(usize, i32)>)> = board
+            .categories
+            .iter()
+            .map(|c| {
+                let clues = c
+                    .clues
+                    .iter()
+                    .map(|clue| (clue.index, clue.value))
+                    .collect();
+                (c.name.as_str(), clues)
+            })
+            .collect();
+        assert_eq!(
+            layout,
+            [
+                ("Rivers", vec![(2, 100), (0, 200)]),
+                ("Capitals", vec![(1, 100)]),
+            ]
+        );
+
+        let unplayable = [clue(None, Some(100)), clue(Some("Rivers"), None)];
+        assert!(Board::from_questions(&unplayable).is_err());
+    }
+
+    #[test]
+    fn selecting_a_clue_plays_it() {
+        let (mut game, host_id) = board_game();
+        let (alice, _) = join(&mut game, "Alice");
+        select(&mut game, host_id, 0).unwrap();
+
+        let board = game.board.as_ref().unwrap();
+        assert_eq!(board.active, Some(0));
+        assert!(board.clue(0).unwrap().used);
+        assert!(!board.finished());
+        assert_eq!(game.current_question, Some(0));
+        let revealed = game.revealed.as_ref().unwrap();
+        assert_eq!(revealed.index, 0);
+        assert_eq!(revealed.answer, None);
+        assert!(!game.globally_locked);
+
+        // Judging is worth the clue's value
+        buzz(&mut game, alice, 200).unwrap();
+        let judge = ClientToServer::Judge {
+            game_code: CODE,
+            correct: true,
+        };
+        host(&mut game, host_id, judge).unwrap();
+        assert_eq!(game.scores[&alice], 200);
+
+        // The next clue archives this one
+        let out = select(&mut game, host_id, 2).unwrap();
+        assert!(out.iter().any(|o| matches!(
+            o,
+            Outbound::Send(_, ServerToClient::QuestionArchived { .. })
+        )));
+        assert_eq!(game.progress.history.len(), 1);
+        assert_eq!(game.active_clue_value(), Some(100));
+    }
+
+    #[test]
+    fn a_played_clue_cannot_be_picked_again() {
+        let (mut game, host_id) = board_game();
+        select(&mut game, host_id, 1).unwrap();
+        let before = game.events.len();
+
+        let again = select(&mut game, host_id, 1);
+        assert!(matches!(again, Err(GameError::InvalidBoard(_))));
+        assert_eq!(game.events.len(), before);
+
+        select(&mut game, host_id, 0).unwrap();
+        select(&mut game, host_id, 2).unwrap();
+        assert!(game.board.as_ref().unwrap().finished());
+    }
+
+    #[test]
+    fn only_clues_on_the_board_can_be_picked() {
+        let (mut game, host_id) = board_game();
+        // Out of range, and in the set but without a category
+        for index in [99, 3] {
+            assert!(matches!(
+                select(&mut game, host_id, index),
+                Err(GameError::NoQuestion)
+            ));
+        }
+        assert_eq!(game.board.as_ref().unwrap().active, None);
+
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::CloseBoard { game_code: CODE },
+        )
+        .unwrap();
+        assert!(matches!(
+            select(&mut game, host_id, 0),
+            Err(GameError::InvalidBoard(_))
+        ));
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::*;
+
+pub const CHOICE_LABELS: [char; 4] = ['A', 'B', 'C', 'D'];
+
+/// One player's answer to a multiple-choice question
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChoiceAnswer {
+    pub player_id: Uuid,
+    /// Index into the options. Kept in `GameState::choice_picks` and hidden
+    /// from everyone until the question is closed.
+    #[serde(default)]
+    pub choice: Option<usize>,
+    /// When the server received the answer
+    pub at_ms: u64,
+}
+
+/// A multiple-choice question the host opened. Players pick one of the
+/// options instead of buzzing.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChoiceQuestion {
+    pub options: Vec<String>,
+    pub opened_ms: u64,
+    /// Seconds over which correct answers lose value; only set if time
+    /// weighting was on when the question opened. Taken from the countdown
+    /// then, or from one started while the question is open; 0 until there
+    /// is one.
+    #[serde(default)]
+    pub weighting_secs: Option<u32>,
+    pub closed: bool,
+    // Only known once the question is closed
+    #[serde(default)]
+    pub correct: Option<usize>,
+    #[serde(default)]
+    pub answers: Vec<ChoiceAnswer>,
+}
+
+impl ChoiceQuestion {
+    /// The letter shown for the option at `index`
+    pub fn label(index: usize) -> char {
+        CHOICE_LABELS.get(index).copied().unwrap_or('?')
+    }
+
+    pub fn answer_of(&self, player_id: &Uuid) -> Option<&ChoiceAnswer> {
+        self.answers.iter().find(|a| a.player_id == *player_id)
+    }
+
+    /// Points for a correct answer given at `at_ms`, out of `points`. With
+    /// time weighting they drop linearly to half by the end of the countdown.
+    pub fn points_at(&self, points: i32, at_ms: u64) -> i32 {
+        let Some(limit_ms) = self
+            .weighting_secs
+            .filter(|secs| *secs > 0)
+            .map(|secs| u64::from(secs) * 1000)
+        else {
+            return points;
+        };
+        let elapsed_ms = at_ms.saturating_sub(self.opened_ms).min(limit_ms);
+        points - (i64::from(points) * elapsed_ms as i64 / (2 * limit_ms as i64)) as i32
+    }
+
+    /// How many players picked each option
+    pub fn distribution(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.options.len()];
+        for choice in self.answers.iter().filter_map(|a| a.choice) {
+            if let Some(count) = counts.get_mut(choice) {
+                *count += 1;
+            }
+        }
+        counts
+    }
+}
+
+/// Checks the options and answer key the host sent
+pub(crate) fn validate_choices(options: &[String], correct: usize) -> Result<(), &'static str> {
+    if options.len() < 2 || options.len() > CHOICE_LABELS.len() {
+        return Err("A multiple-choice question needs two to four options.");
+    }
+    if options.iter().any(|option| option.trim().is_empty()) {
+        return Err("Options cannot be empty.");
+    }
+    if correct >= options.len() {
+        return Err("The correct answer must be one of the options.");
+    }
+    Ok(())
+}
+
+impl GameState {
+    pub fn choices_open(&self) -> bool {
+        self.choice.as_ref().is_some_and(|c| !c.closed)
+    }
+
+    pub(crate) fn start_choices(
+        &mut self,
+        at_ms: u64,
+        options: &[String],
+        weighting_secs: Option<u32>,
+    ) {
+        self.clear_buzzes();
+        self.unlock_buzzers(at_ms);
+        self.choice = Some(ChoiceQuestion {
+            options: options.to_vec(),
+            opened_ms: at_ms,
+            weighting_secs,
+            closed: false,
+            correct: None,
+            answers: Vec::new(),
+        });
+    }
+
+    /// A countdown started while a weighted question is open is the one its
+    /// answers are weighted by
+    pub(crate) fn time_choices(&mut self, time_limit: u32) {
+        if let Some(question) = self.choice.as_mut().filter(|c| !c.closed)
+            && question.weighting_secs.is_some()
+        {
+            question.weighting_secs = Some(time_limit);
+        }
+    }
+
+    pub(crate) fn reveal_choices(&mut self, correct: usize, picks: &HashMap<Uuid, usize>) {
+        self.globally_locked = true;
+        if let Some(question) = self.choice.as_mut() {
+            question.closed = true;
+            question.correct = Some(correct);
+            for answer in &mut question.answers {
+                answer.choice = picks.get(&answer.player_id).copied();
+            }
+        }
+    }
+
+    /// Opens a new question. One that is still open is closed and scored
+    /// first, so its answers aren't lost.
+    pub(crate) fn open_choices(
+        &mut self,
+        now_ms: u64,
+        options: Vec<String>,
+        correct: usize,
+    ) -> Result<Vec<Outbound>, GameError> {
+        validate_choices(&options, correct).map_err(GameError::InvalidChoices)?;
+        let mut out = self.close_choices(now_ms);
+        let options = options.iter().map(|o| o.trim().to_string()).collect();
+        let weighting_secs = self
+            .rules
+            .time_weighted_choices
+            .then(|| self.time_limit.unwrap_or(0));
+        self.choice_key = Some(correct);
+        self.choice_picks.clear();
+        self.record(
+            now_ms,
+            GameEvent::ChoicesOpened {
+                options,
+                weighting_secs,
+            },
+        );
+        out.push(self.state_update());
+        Ok(out)
+    }
+
+    /// Notes that the player picked an option, without saying which
+    pub(crate) fn note_choice(&mut self, at_ms: u64, player_id: Uuid) {
+        if let Some(question) = self.choice.as_mut()
+            && question.answer_of(&player_id).is_none()
+        {
+            question.answers.push(ChoiceAnswer {
+                player_id,
+                choice: None,
+                at_ms,
+            });
+        }
+    }
+
+    pub(crate) fn submit_choice(
+        &mut self,
+        now_ms: u64,
+        player_id: Uuid,
+        choice: usize,
+    ) -> Result<Vec<Outbound>, GameError> {
+        let Some(question) = self.choice.as_ref().filter(|c| !c.closed) else {
+            return Err(GameError::NoChoices);
+        };
+        if self.globally_locked {
+            return Err(GameError::Locked);
+        }
+        if question.answer_of(&player_id).is_some() {
+            return Err(GameError::AlreadyAnswered);
+        }
+        if choice >= question.options.len() {
+            return Err(GameError::InvalidChoices("There is no such option."));
+        }
+        self.choice_picks.insert(player_id, choice);
+        self.record(now_ms, GameEvent::ChoiceSubmitted { player_id });
+        Ok(vec![self.delta(GameDelta::ChoiceSubmitted {
+            player_id,
+            at_ms: now_ms,
+        })])
+    }
+
+    /// Reveals the answer and scores everyone who answered. Does nothing if
+    /// no question is open.
+    pub(crate) fn close_choices(&mut self, now_ms: u64) -> Vec<Outbound> {
+        let mut out = Vec::new();
+        if !self.choices_open() {
+            return out;
+        }
+        let correct = self.choice_key.take().unwrap_or_default();
+        let picks = std::mem::take(&mut self.choice_picks);
+        self.record(now_ms, GameEvent::ChoicesClosed { correct, picks });
+        let Some(question) = self.choice.clone() else {
+            return out;
+        };
+        for answer in &question.answers {
+            let (amount, reason) = if answer.choice == Some(correct) {
+                let points = question.points_at(self.rules.points_correct, answer.at_ms);
+                (points, "Correct choice")
+            } else {
+                (-self.rules.penalty_wrong, "Wrong choice")
+            };
+            if amount != 0 {
+                out.extend(self.change_score(
+                    now_ms,
+                    answer.player_id,
+                    amount,
+                    None,
+                    Some(reason.to_string()),
+                ));
+            }
+        }
+        out.push(self.state_update());
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    fn open(
+        game: &mut GameState,
+        host_id: Uuid,
+        correct: usize,
+    ) -> Result<Vec<Outbound>, GameError> {
+        host(
+            game,
+            host_id,
+            ClientToServer::OpenChoices {
+                game_code: CODE,
+                options: vec!["Red".to_string(), "Blue".to_string()],
+                correct,
+            },
+        )
+    }
+
+    fn pick(game: &mut GameState, player_id: Uuid, choice: usize, now_ms: u64) {
+        game.apply(
+            player_id,
+            ClientToServer::SubmitChoice {
+                game_code: CODE,
+                choice,
+            },
+            &ctx(now_ms),
+        )
+        .unwrap();
+    }
+
+    fn close(game: &mut GameState, host_id: Uuid) {
+        host(
+            game,
+            host_id,
+            ClientToServer::CloseChoices { game_code: CODE },
+        )
+        .unwrap();
+    }
+
+    fn set_rules(game: &mut GameState, host_id: Uuid, rules: GameRules) {
+        host(
+            game,
+            host_id,
+            ClientToServer::SetRules {
+                game_code: CODE,
+                rules,
+            },
+        )
+        .unwrap();
+    }
+
+    /// Sets 100 points per correct answer, weighted over a 10 second countdown
+    fn weighted_game() -> (GameState, Uuid) {
+        let (mut game, host_id) = new_game();
+        let rules = GameRules {
+            points_correct: 100,
+            time_weighted_choices: true,
+            ..GameRules::default()
+        };
+        set_rules(&mut game, host_id, rules);
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::StartCountdown {
+                game_code: CODE,
+                time_limit: 10,
+            },
+        )
+        .unwrap();
+        (game, host_id)
+    }
+
+    #[test]
+    fn faster_answers_score_more_down_to_half() {
+        let (mut game, host_id) = weighted_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        let (carol, _) = join(&mut game, "Carol");
+        let (dave, _) = join(&mut game, "Dave");
+        // Opened at 100
+        open(&mut game, host_id, 1).unwrap();
+        pick(&mut game, alice, 1, 100);
+        pick(&mut game, bob, 1, 5_100);
+        pick(&mut game, carol, 1, 60_000);
+        pick(&mut game, dave, 0, 200);
+        close(&mut game, host_id);
+
+        assert_eq!(game.scores[&alice], 100);
+        assert_eq!(game.scores[&bob], 75);
+        assert_eq!(game.scores[&carol], 50);
+        assert_eq!(game.scores[&dave], 0);
+    }
+
+    #[test]
+    fn the_weighting_is_settled_when_the_question_opens() {
+        let (mut game, host_id) = weighted_game();
+        let (alice, _) = join(&mut game, "Alice");
+        open(&mut game, host_id, 0).unwrap();
+        assert_eq!(game.choice.as_ref().unwrap().weighting_secs, Some(10));
+
+        // Turning weighting off doesn't change an open question
+        let rules = GameRules {
+            time_weighted_choices: false,
+            ..game.rules
+        };
+        set_rules(&mut game, host_id, rules);
+        pick(&mut game, alice, 0, 5_100);
+        close(&mut game, host_id);
+        assert_eq!(game.scores[&alice], 75);
+
+        // Opened without weighting, turning it on later doesn't count
+        open(&mut game, host_id, 0).unwrap();
+        let rules = GameRules {
+            time_weighted_choices: true,
+            ..game.rules
+        };
+        set_rules(&mut game, host_id, rules);
+        pick(&mut game, alice, 0, 5_100);
+        close(&mut game, host_id);
+        assert_eq!(game.scores[&alice], 175);
+    }
+
+    #[test]
+    fn a_countdown_started_for_the_question_sets_the_weighting() {
+        let (mut game, host_id) = weighted_game();
+        let (alice, _) = join(&mut game, "Alice");
+        open(&mut game, host_id, 0).unwrap();
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::StartCountdown {
+                game_code: CODE,
+                time_limit: 20,
+            },
+        )
+        .unwrap();
+        pick(&mut game, alice, 0, 5_100);
+        close(&mut game, host_id);
+        // A quarter of the way through a 20 second countdown
+        assert_eq!(game.scores[&alice], 88);
+
+        // Closed questions keep the countdown they had
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::StartCountdown {
+                game_code: CODE,
+                time_limit: 5,
+            },
+        )
+        .unwrap();
+        assert_eq!(game.choice.as_ref().unwrap().weighting_secs, Some(20));
+    }
+
+    #[test]
+    fn opening_a_question_scores_the_open_one_first() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        open(&mut game, host_id, 1).unwrap();
+        pick(&mut game, alice, 1, 200);
+
+        // Bad options leave the open question alone
+        let bad = host(
+            &mut game,
+            host_id,
+            ClientToServer::OpenChoices {
+                game_code: CODE,
+                options: vec!["Only".to_string()],
+                correct: 0,
+            },
+        );
+        assert!(matches!(bad, Err(GameError::InvalidChoices(_))));
+        assert!(game.choices_open());
+        assert_eq!(game.choice_picks.len(), 1);
+
+        open(&mut game, host_id, 0).unwrap();
+        assert_eq!(game.scores[&alice], game.rules.points_correct);
+        let question = game.choice.as_ref().unwrap();
+        assert!(!question.closed);
+        assert!(question.answers.is_empty());
+        assert_eq!(game.choice_key, Some(0));
+        assert!(
+            game.events
+                .iter()
+                .any(|e| matches!(e.event, GameEvent::ChoicesClosed { correct: 1, .. }))
+        );
+    }
+}
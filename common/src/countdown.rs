@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+
+use super::GameState;
+
+/// Server-owned countdown. Deadlines are absolute unix timestamps in
+/// milliseconds so every client renders the same remaining time.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct CountdownState {
+    pub running: bool,
+    pub deadline_ms: Option<u64>,
+    // Time left when the countdown was paused
+    pub remaining_ms: u64,
+    // Lock all buzzers once the countdown runs out
+    pub auto_lock: bool,
+    // Pause the countdown as soon as the first player buzzes
+    pub pause_on_buzz: bool,
+}
+
+impl GameState {
+    /// Starts a fresh countdown of `time_limit` seconds.
+    pub(crate) fn start_countdown(&mut self, time_limit: u32, now_ms: u64) {
+        self.time_limit = Some(time_limit);
+        self.countdown.running = true;
+        self.countdown.deadline_ms = Some(now_ms + u64::from(time_limit) * 1000);
+        self.countdown.remaining_ms = 0;
+    }
+
+    /// Pauses a running countdown, keeping the time that was left.
+    pub(crate) fn pause_countdown(&mut self, now_ms: u64) {
+        if !self.countdown.running {
+            return;
+        }
+        self.countdown.remaining_ms = self.countdown_remaining_ms(now_ms);
+        self.countdown.running = false;
+        self.countdown.deadline_ms = None;
+    }
+
+    /// Resumes a paused countdown with the time that was left.
+    pub(crate) fn resume_countdown(&mut self, now_ms: u64) {
+        if self.countdown.running || self.countdown.remaining_ms == 0 {
+            return;
+        }
+        self.countdown.running = true;
+        self.countdown.deadline_ms = Some(now_ms + self.countdown.remaining_ms);
+        self.countdown.remaining_ms = 0;
+    }
+
+    /// Ends the countdown if it is still the one that was due at `deadline_ms`.
+    pub(crate) fn expire_countdown(&mut self, deadline_ms: u64) {
+        if !self.countdown.running || self.countdown.deadline_ms != Some(deadline_ms) {
+            return;
+        }
+        self.countdown.running = false;
+        self.countdown.deadline_ms = None;
+        self.countdown.remaining_ms = 0;
+        if self.countdown.auto_lock {
+            self.globally_locked = true;
+        }
+    }
+
+    pub fn countdown_remaining_ms(&self, now_ms: u64) -> u64 {
+        match self.countdown.deadline_ms {
+            Some(deadline_ms) if self.countdown.running => deadline_ms.saturating_sub(now_ms),
+            _ => self.countdown.remaining_ms,
+        }
+    }
+}
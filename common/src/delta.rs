@@ -0,0 +1,304 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::*;
+
+/// A single, small change to a game, broadcast instead of a full snapshot.
+/// Applying a delta twice has the same effect as applying it once, so a
+/// client that already applied its own action optimistically stays correct.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind")]
+pub enum GameDelta {
+    PlayerJoined {
+        player_id: Uuid,
+        player_name: String,
+    },
+    TeamCreated {
+        team_id: Uuid,
+        name: String,
+    },
+    TeamAssigned {
+        player_id: Uuid,
+        team_id: Option<Uuid>,
+    },
+    ScoreChanged {
+        player_id: Uuid,
+        score: i32,
+        // The player's points per round after the change
+        #[serde(default)]
+        round_scores: Vec<i32>,
+    },
+    /// The host took back a ledger entry
+    ScoreUndone {
+        entry_id: u64,
+        player_id: Uuid,
+        score: i32,
+        round_scores: Vec<i32>,
+    },
+    /// The host restored an undone ledger entry
+    ScoreRedone {
+        entry_id: u64,
+        player_id: Uuid,
+        score: i32,
+        round_scores: Vec<i32>,
+    },
+    Buzzed {
+        player_id: Uuid,
+        player_name: String,
+        position: usize,
+        timing: BuzzTiming,
+    },
+    QueueCleared,
+    AnswerJudged {
+        player_id: Uuid,
+        correct: bool,
+    },
+    LockChanged {
+        locked: bool,
+    },
+    /// A player picked an option; which one stays secret until the close
+    ChoiceSubmitted {
+        player_id: Uuid,
+        at_ms: u64,
+    },
+    /// A player handed in a written answer; only the host sees its text
+    AnswerSubmitted {
+        player_id: Uuid,
+        at_ms: u64,
+    },
+    /// A player placed or changed their bet; only the host sees the amount
+    WagerPlaced {
+        player_id: Uuid,
+        at_ms: u64,
+    },
+}
+
+/// What a client should do with a sequenced message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceCheck {
+    /// The message directly follows the last one that was applied
+    Next,
+    /// Already applied, e.g. re-sent after a snapshot
+    Stale,
+    /// Something was missed in between; ask for a snapshot
+    Gap,
+}
+
+impl GameState {
+    pub fn check_sequence(&self, seq: u64) -> SequenceCheck {
+        if seq <= self.seq {
+            SequenceCheck::Stale
+        } else if seq == self.seq + 1 {
+            SequenceCheck::Next
+        } else {
+            SequenceCheck::Gap
+        }
+    }
+
+    /// Applies a delta received as message number `seq`.
+    pub fn apply_delta(&mut self, seq: u64, delta: GameDelta) {
+        match delta {
+            GameDelta::PlayerJoined {
+                player_id,
+                player_name,
+            } => {
+                self.players.insert(
+                    player_id,
+                    Actor::Player {
+                        id: player_id,
+                        name: player_name,
+                    },
+                );
+                self.scores.entry(player_id).or_insert(0);
+                if !self.player_join_order.contains(&player_id) {
+                    self.player_join_order.push(player_id);
+                }
+            }
+            GameDelta::TeamCreated { team_id, name } => self.add_team(team_id, &name),
+            GameDelta::TeamAssigned { player_id, team_id } => self.set_team(player_id, team_id),
+            GameDelta::ScoreChanged {
+                player_id,
+                score,
+                round_scores,
+            } => {
+                self.scores.insert(player_id, score);
+                self.round_totals.insert(player_id, round_scores);
+            }
+            GameDelta::ScoreUndone {
+                entry_id,
+                player_id,
+                score,
+                round_scores,
+            } => {
+                self.scores.insert(player_id, score);
+                self.round_totals.insert(player_id, round_scores);
+                self.score_ledger.mark_undone(entry_id);
+            }
+            GameDelta::ScoreRedone {
+                entry_id,
+                player_id,
+                score,
+                round_scores,
+            } => {
+                self.scores.insert(player_id, score);
+                self.round_totals.insert(player_id, round_scores);
+                self.score_ledger.mark_redone(entry_id);
+            }
+            GameDelta::Buzzed {
+                player_id,
+                player_name,
+                position,
+                timing,
+            } => {
+                self.buzzer_order.retain(|(id, _)| *id != player_id);
+                let position = position.min(self.buzzer_order.len());
+                self.note_buzz(player_id, &player_name, timing);
+                self.buzzer_order.insert(position, (player_id, player_name));
+                self.buzz_timings.insert(player_id, timing);
+            }
+            GameDelta::QueueCleared => self.clear_buzzes(),
+            GameDelta::AnswerJudged { player_id, correct } => {
+                self.judge_answer(player_id, correct);
+            }
+            GameDelta::LockChanged { locked } => self.globally_locked = locked,
+            GameDelta::ChoiceSubmitted { player_id, at_ms } => self.note_choice(at_ms, player_id),
+            GameDelta::AnswerSubmitted { player_id, at_ms } => {
+                self.add_written_answer(at_ms, player_id)
+            }
+            GameDelta::WagerPlaced { player_id, at_ms } => self.note_wager(at_ms, player_id),
+        }
+        self.seq = seq;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    /// The deltas among a message's broadcasts
+    fn deltas(out: Vec<Outbound>) -> Vec<(u64, GameDelta)> {
+        out.into_iter()
+            .filter_map(|o| match o {
+                Outbound::Broadcast(ServerToClient::Delta { seq, delta }) => Some((seq, delta)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// A client's copy of the game, as sent in a snapshot
+    fn client_copy(game: &GameState) -> GameState {
+        game.to_json().into()
+    }
+
+    fn same_state(client: &GameState, server: &GameState) -> bool {
+        serde_json::to_value(client.to_json()).unwrap()
+            == serde_json::to_value(server.to_json()).unwrap()
+    }
+
+    fn judge(correct: bool) -> ClientToServer {
+        ClientToServer::Judge {
+            game_code: CODE,
+            correct,
+        }
+    }
+
+    #[test]
+    fn following_the_deltas_gives_the_server_state() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        let mut client = client_copy(&game);
+
+        let mut received = Vec::new();
+        received.extend(deltas(buzz(&mut game, alice, 200).unwrap()));
+        received.extend(deltas(buzz(&mut game, bob, 210).unwrap()));
+        received.extend(deltas(host(&mut game, host_id, judge(false)).unwrap()));
+        received.extend(deltas(host(&mut game, host_id, judge(true)).unwrap()));
+        received.extend(deltas(
+            host(&mut game, host_id, ClientToServer::Lock { game_code: CODE }).unwrap(),
+        ));
+        assert!(received.len() >= 5);
+
+        for (seq, delta) in received {
+            assert_eq!(client.check_sequence(seq), SequenceCheck::Next);
+            client.apply_delta(seq, delta);
+        }
+        assert!(same_state(&client, &game));
+        assert_eq!(client.scores[&bob], game.rules.points_correct);
+        assert!(client.globally_locked);
+    }
+
+    #[test]
+    fn only_the_next_number_is_applied() {
+        let (mut game, _) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let client = client_copy(&game);
+        let seq = client.seq;
+
+        assert_eq!(client.check_sequence(seq + 1), SequenceCheck::Next);
+        assert_eq!(client.check_sequence(seq), SequenceCheck::Stale);
+        assert_eq!(client.check_sequence(0), SequenceCheck::Stale);
+        assert_eq!(client.check_sequence(seq + 2), SequenceCheck::Gap);
+
+        let delta = deltas(buzz(&mut game, alice, 200).unwrap()).remove(0);
+        assert_eq!(delta.0, seq + 1);
+    }
+
+    #[test]
+    fn applying_a_delta_twice_changes_nothing_more() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let mut client = client_copy(&game);
+        let mut received = deltas(buzz(&mut game, alice, 200).unwrap());
+        received.extend(deltas(
+            host(
+                &mut game,
+                host_id,
+                ClientToServer::UpdateScore {
+                    game_code: CODE,
+                    player_id: alice,
+                    delta: 5,
+                    reason: None,
+                },
+            )
+            .unwrap(),
+        ));
+
+        // As when a client applied its own action optimistically first
+        for (seq, delta) in received.iter().chain(received.iter()) {
+            client.apply_delta(*seq, delta.clone());
+        }
+        assert!(same_state(&client, &game));
+        assert_eq!(order(&client), [alice]);
+        assert_eq!(client.scores[&alice], 5);
+    }
+
+    #[test]
+    fn a_snapshot_catches_up_after_a_gap() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        let mut client = client_copy(&game);
+
+        let mut sent = deltas(buzz(&mut game, alice, 200).unwrap());
+        sent.extend(deltas(buzz(&mut game, bob, 210).unwrap()));
+        // The first one got lost
+        let (seq, _) = sent[1];
+        assert_eq!(client.check_sequence(seq), SequenceCheck::Gap);
+
+        // The client asks for a snapshot and more deltas arrive meanwhile
+        sent.extend(deltas(host(&mut game, host_id, judge(true)).unwrap()));
+        client = client_copy(&game);
+        for (seq, _) in &sent {
+            assert_eq!(client.check_sequence(*seq), SequenceCheck::Stale);
+        }
+        assert!(same_state(&client, &game));
+
+        let lock = ClientToServer::Lock { game_code: CODE };
+        let (seq, delta) = deltas(host(&mut game, host_id, lock).unwrap()).remove(0);
+        assert_eq!(client.check_sequence(seq), SequenceCheck::Next);
+        client.apply_delta(seq, delta);
+        assert!(same_state(&client, &game));
+    }
+}
@@ -0,0 +1,215 @@
+use uuid::Uuid;
+
+use super::*;
+
+impl GameState {
+    /// How long `player_id` still may not buzz after a false start
+    pub fn false_start_remaining_ms(&self, player_id: &Uuid, now_ms: u64) -> u64 {
+        self.false_starts
+            .get(player_id)
+            .map_or(0, |until_ms| until_ms.saturating_sub(now_ms))
+    }
+
+    /// Whether buzzing now would be a false start rather than a buzz
+    pub fn waiting_for_unlock(&self) -> bool {
+        self.rules.false_start_lockout_secs > 0
+            && self.globally_locked
+            && self.buzzer_order.is_empty()
+    }
+
+    pub(crate) fn unlock_buzzers(&mut self, at_ms: u64) {
+        if self.globally_locked {
+            self.unlocked_ms = Some(at_ms);
+        }
+        self.globally_locked = false;
+    }
+
+    /// A buzz is too early if the buzzers are still locked for the next
+    /// question, or if it was pressed shortly before the unlock and only
+    /// arrived after it. A lock while someone is answering doesn't count.
+    pub(crate) fn is_false_start(
+        &self,
+        client_time_ms: Option<u64>,
+        clock: Option<ClockEstimate>,
+        received_ms: u64,
+    ) -> bool {
+        if self.rules.false_start_lockout_secs == 0 {
+            return false;
+        }
+        if self.globally_locked {
+            return self.buzzer_order.is_empty();
+        }
+        // Unlike for the queue order, the correction is not capped by the
+        // fairness window: claiming an earlier press only gets a client
+        // penalized, and the window may well be longer than the cap.
+        let press_time_ms = match (client_time_ms, clock) {
+            (Some(client_time_ms), Some(clock)) => {
+                clock.to_server_time(client_time_ms).min(received_ms)
+            }
+            _ => received_ms,
+        };
+        self.unlocked_ms.is_some_and(|unlocked_ms| {
+            press_time_ms < unlocked_ms
+                && unlocked_ms - press_time_ms <= self.rules.false_start_window_ms
+        })
+    }
+
+    /// Keeps the player from buzzing for a while, and takes the penalty
+    pub(crate) fn penalize_false_start(&mut self, now_ms: u64, player_id: Uuid) -> Vec<Outbound> {
+        let until_ms = now_ms + u64::from(self.rules.false_start_lockout_secs) * 1000;
+        self.record(
+            now_ms,
+            GameEvent::FalseStart {
+                player_id,
+                until_ms,
+            },
+        );
+        let mut out = Vec::new();
+        if self.rules.false_start_penalty != 0 {
+            out.extend(self.change_score(
+                now_ms,
+                player_id,
+                -self.rules.false_start_penalty,
+                None,
+                Some("False start".to_string()),
+            ));
+        }
+        out.push(self.state_update());
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    /// A game with a 3 second lockout and a 5 point penalty for false starts,
+    /// and presses up to 300 ms before an unlock counting as early
+    fn strict_game() -> (GameState, Uuid) {
+        let (mut game, host_id) = new_game();
+        let rules = GameRules {
+            false_start_lockout_secs: 3,
+            false_start_window_ms: 300,
+            false_start_penalty: 5,
+            ..GameRules::default()
+        };
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::SetRules {
+                game_code: CODE,
+                rules,
+            },
+        )
+        .unwrap();
+        host(&mut game, host_id, ClientToServer::Lock { game_code: CODE }).unwrap();
+        (game, host_id)
+    }
+
+    fn unlock(game: &mut GameState, host_id: Uuid, now_ms: u64) {
+        let unlock = ClientToServer::Unlock { game_code: CODE };
+        game.apply(host_id, unlock, &ctx(now_ms)).unwrap();
+    }
+
+    /// A buzz pressed at `client_time_ms` by a client whose clock matches the server's
+    fn timed_buzz(
+        game: &mut GameState,
+        player_id: Uuid,
+        client_time_ms: u64,
+        now_ms: u64,
+    ) -> Result<Vec<Outbound>, GameError> {
+        let ctx = ApplyContext {
+            clock: Some(ClockEstimate {
+                offset_ms: 0,
+                rtt_ms: 20,
+            }),
+            ..ctx(now_ms)
+        };
+        let buzz = ClientToServer::Buzz {
+            game_code: CODE,
+            player_id,
+            client_time_ms: Some(client_time_ms),
+        };
+        game.apply(player_id, buzz, &ctx)
+    }
+
+    #[test]
+    fn buzzing_before_the_unlock_is_penalized() {
+        let (mut game, _) = strict_game();
+        let (alice, _) = join(&mut game, "Alice");
+        assert!(game.waiting_for_unlock());
+
+        buzz(&mut game, alice, 1_000).unwrap();
+        assert!(game.buzzer_order.is_empty());
+        assert_eq!(game.false_start_remaining_ms(&alice, 1_000), 3_000);
+        assert_eq!(game.scores[&alice], -5);
+        assert!(game.score_ledger.entries.iter().any(|e| e.delta == -5));
+    }
+
+    #[test]
+    fn the_lockout_ends_after_its_time() {
+        let (mut game, host_id) = strict_game();
+        let (alice, _) = join(&mut game, "Alice");
+        buzz(&mut game, alice, 1_000).unwrap();
+        unlock(&mut game, host_id, 2_000);
+
+        assert!(matches!(
+            buzz(&mut game, alice, 2_500),
+            Err(GameError::FalseStart(2))
+        ));
+        // Waiting out the lockout costs nothing more
+        assert_eq!(game.scores[&alice], -5);
+        assert_eq!(game.false_start_remaining_ms(&alice, 4_000), 0);
+        buzz(&mut game, alice, 4_000).unwrap();
+        assert_eq!(order(&game), [alice]);
+    }
+
+    #[test]
+    fn a_press_just_before_the_unlock_is_early_too() {
+        let (mut game, host_id) = strict_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        unlock(&mut game, host_id, 2_000);
+
+        // Pressed 100 ms before the unlock, arrived after it
+        timed_buzz(&mut game, alice, 1_900, 2_050).unwrap();
+        assert!(game.false_start_remaining_ms(&alice, 2_050) > 0);
+        // Pressed before the window
+        timed_buzz(&mut game, bob, 1_600, 2_050).unwrap();
+        assert_eq!(order(&game), [bob]);
+        assert_eq!(game.scores[&bob], 0);
+    }
+
+    #[test]
+    fn a_lock_while_someone_answers_is_no_false_start() {
+        let (mut game, host_id) = strict_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        unlock(&mut game, host_id, 2_000);
+        buzz(&mut game, alice, 2_500).unwrap();
+        host(&mut game, host_id, ClientToServer::Lock { game_code: CODE }).unwrap();
+
+        assert!(!game.waiting_for_unlock());
+        assert!(matches!(
+            buzz(&mut game, bob, 3_000),
+            Err(GameError::Locked)
+        ));
+        assert_eq!(game.false_start_remaining_ms(&bob, 3_000), 0);
+        assert_eq!(game.scores[&bob], 0);
+    }
+
+    #[test]
+    fn without_a_lockout_early_buzzes_are_just_rejected() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        host(&mut game, host_id, ClientToServer::Lock { game_code: CODE }).unwrap();
+
+        assert!(!game.waiting_for_unlock());
+        assert!(matches!(
+            buzz(&mut game, alice, 1_000),
+            Err(GameError::Locked)
+        ));
+        assert!(game.false_starts.is_empty());
+    }
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+
+use super::MAX_SCORE;
+
+/// How a game is scored and how the buzzer queue behaves. Set by the host and
+/// enforced by the server.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct GameRules {
+    /// Awarded for a correct answer, and the step of the host's +/- buttons
+    pub points_correct: i32,
+    /// Taken for a wrong answer
+    pub penalty_wrong: i32,
+    /// Lock all buzzers as soon as someone buzzes
+    pub auto_lock_after_first_buzz: bool,
+    /// How many players may be queued at once; 0 means no limit
+    pub max_queue_length: usize,
+    /// Whether a player may buzz again after their answer was judged wrong
+    pub allow_rebuzz_after_wrong: bool,
+    /// Seconds the player at the head of the queue has to answer; 0 means no limit
+    pub answer_time_limit_secs: u32,
+    /// Count an answer as wrong when its time runs out, instead of leaving it
+    /// to the host
+    pub wrong_when_answer_time_up: bool,
+    /// Only the first member of a team to buzz gets a turn at a question
+    pub one_buzz_per_team: bool,
+    /// Whether players may start a new team rather than pick an existing one
+    pub players_create_teams: bool,
+    /// Seconds a player may not buzz after buzzing before the buzzers were
+    /// unlocked; 0 turns false starts off
+    pub false_start_lockout_secs: u32,
+    /// Presses made up to this many milliseconds before an unlock, that only
+    /// arrive after it, are false starts too
+    pub false_start_window_ms: u64,
+    /// Taken for a false start
+    pub false_start_penalty: i32,
+    /// Correct multiple-choice answers are worth less the longer they took
+    pub time_weighted_choices: bool,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self {
+            points_correct: 10,
+            penalty_wrong: 0,
+            auto_lock_after_first_buzz: false,
+            max_queue_length: 0,
+            allow_rebuzz_after_wrong: false,
+            answer_time_limit_secs: 0,
+            wrong_when_answer_time_up: false,
+            one_buzz_per_team: false,
+            players_create_teams: true,
+            time_weighted_choices: false,
+            false_start_lockout_secs: 0,
+            false_start_window_ms: 0,
+            false_start_penalty: 0,
+        }
+    }
+}
+
+impl GameRules {
+    /// Why these rules can't be used, if they can't
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.points_correct < 0 {
+            return Err("Points for a correct answer cannot be negative.");
+        }
+        if self.penalty_wrong < 0 {
+            return Err("The penalty for a wrong answer cannot be negative.");
+        }
+        if self.false_start_penalty < 0 {
+            return Err("The penalty for a false start cannot be negative.");
+        }
+        if self.points_correct > MAX_SCORE
+            || self.penalty_wrong > MAX_SCORE
+            || self.false_start_penalty > MAX_SCORE
+        {
+            return Err("Points and penalties cannot be larger than the highest score.");
+        }
+        Ok(())
+    }
+
+    /// Whether another buzz fits into a queue of `queued` players
+    pub fn queue_has_room(&self, queued: usize) -> bool {
+        self.max_queue_length == 0 || queued < self.max_queue_length
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_and_penalties_must_be_within_the_score_limit() {
+        assert!(GameRules::default().validate().is_ok());
+        let at_limit = GameRules {
+            points_correct: MAX_SCORE,
+            penalty_wrong: MAX_SCORE,
+            false_start_penalty: MAX_SCORE,
+            ..GameRules::default()
+        };
+        assert!(at_limit.validate().is_ok());
+
+        let too_large = [
+            GameRules {
+                points_correct: MAX_SCORE + 1,
+                ..GameRules::default()
+            },
+            GameRules {
+                penalty_wrong: i32::MAX,
+                ..GameRules::default()
+            },
+            GameRules {
+                false_start_penalty: MAX_SCORE + 1,
+                ..GameRules::default()
+            },
+        ];
+        for rules in too_large {
+            assert!(rules.validate().is_err(), "{:?}", rules);
+        }
+        let negative = GameRules {
+            penalty_wrong: -1,
+            ..GameRules::default()
+        };
+        assert!(negative.validate().is_err());
+    }
+}
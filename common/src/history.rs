@@ -0,0 +1,427 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::*;
+
+/// One mutation of a game. The game state is nothing but these events folded
+/// in order, so the log can rebuild it at any point in time.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind")]
+pub enum GameEvent {
+    GameCreated {
+        host_id: Uuid,
+    },
+    QuestionAdvanced {
+        new_round: bool,
+    },
+    /// The questions themselves are kept out of the log, which is public
+    QuestionsImported {
+        count: usize,
+    },
+    QuestionSelected {
+        index: usize,
+    },
+    QuestionRevealed {
+        question: RevealedQuestion,
+    },
+    BoardOpened {
+        board: Board,
+    },
+    BoardClosed,
+    ClueSelected {
+        index: usize,
+    },
+    /// The answer key stays off the log until the question is closed
+    ChoicesOpened {
+        options: Vec<String>,
+        #[serde(default)]
+        weighting_secs: Option<u32>,
+    },
+    ChoiceSubmitted {
+        player_id: Uuid,
+    },
+    ChoicesClosed {
+        correct: usize,
+        picks: HashMap<Uuid, usize>,
+    },
+    /// Like the picks, the texts only enter the log once they are shown
+    AnswersOpened,
+    AnswerSubmitted {
+        player_id: Uuid,
+    },
+    AnswersClosed,
+    AnswersShown {
+        texts: HashMap<Uuid, String>,
+    },
+    AnswerGraded {
+        player_id: Uuid,
+        grade: Grade,
+    },
+    /// Bets only enter the log once they are revealed or resolved
+    WagersOpened,
+    WagerPlaced {
+        player_id: Uuid,
+    },
+    WagersLocked,
+    WagersRevealed {
+        amounts: HashMap<Uuid, i32>,
+    },
+    WagerResolved {
+        player_id: Uuid,
+        amount: i32,
+        won: bool,
+        // The ledger entry that scored the resolution, if it changed the score
+        #[serde(default)]
+        entry_id: Option<u64>,
+    },
+    PlayerJoined {
+        player_id: Uuid,
+        player_name: String,
+    },
+    PlayerDisconnected {
+        player_id: Uuid,
+    },
+    TeamCreated {
+        team_id: Uuid,
+        name: String,
+    },
+    TeamRemoved {
+        team_id: Uuid,
+    },
+    TeamAssigned {
+        player_id: Uuid,
+        team_id: Option<Uuid>,
+    },
+    PlayerReconnected {
+        player_id: Uuid,
+    },
+    HostDisconnected,
+    HostReconnected,
+    Buzzed {
+        player_id: Uuid,
+        player_name: String,
+        position: usize,
+        timing: BuzzTiming,
+    },
+    Locked,
+    Unlocked,
+    QueueCleared,
+    /// The player buzzed before the buzzers were unlocked
+    FalseStart {
+        player_id: Uuid,
+        until_ms: u64,
+    },
+    AnswerClockStarted {
+        player_id: Uuid,
+        time_limit: u32,
+    },
+    AnswerClockStopped,
+    AnswerClockExpired {
+        player_id: Uuid,
+    },
+    AnswerJudged {
+        player_id: Uuid,
+        correct: bool,
+    },
+    ScoreChanged {
+        player_id: Uuid,
+        delta: i32,
+        #[serde(default)]
+        set_to: Option<i32>,
+        #[serde(default)]
+        reason: Option<String>,
+        #[serde(default)]
+        buzz_position: Option<usize>,
+    },
+    ScoreUndone {
+        entry_id: u64,
+    },
+    ScoreRedone {
+        entry_id: u64,
+    },
+    CountdownStarted {
+        time_limit: u32,
+    },
+    CountdownPaused,
+    CountdownResumed,
+    CountdownExpired {
+        deadline_ms: u64,
+    },
+    CountdownOptionsChanged {
+        auto_lock: bool,
+        pause_on_buzz: bool,
+    },
+    RulesChanged {
+        rules: GameRules,
+    },
+}
+
+/// An event and when it happened, in server milliseconds since the epoch
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LoggedEvent {
+    pub at_ms: u64,
+    pub event: GameEvent,
+}
+
+impl GameState {
+    /// Rebuilds a game from its log.
+    pub fn replay(events: &[LoggedEvent]) -> Self {
+        let mut game = Self::default();
+        for logged in events {
+            game.apply_event(logged.at_ms, &logged.event);
+        }
+        game
+    }
+
+    /// Applies an event and appends it to the game's log.
+    pub fn record(&mut self, at_ms: u64, event: GameEvent) {
+        self.apply_event(at_ms, &event);
+        self.events.push(LoggedEvent { at_ms, event });
+    }
+
+    fn apply_event(&mut self, at_ms: u64, event: &GameEvent) {
+        match event {
+            GameEvent::GameCreated { host_id } => {
+                self.host_id = *host_id;
+                self.host_connected = true;
+                self.players.insert(*host_id, Actor::Host { id: *host_id });
+                self.player_join_order = vec![*host_id];
+                self.progress.question_started_ms = at_ms;
+            }
+            GameEvent::QuestionAdvanced { new_round } => self.advance_question(at_ms, *new_round),
+            GameEvent::QuestionsImported { count } => {
+                self.current_question = (*count > 0).then_some(0);
+                self.revealed = None;
+                // The board points into the old question set
+                self.board = None;
+            }
+            GameEvent::QuestionSelected { index } => {
+                self.current_question = Some(*index);
+                self.revealed = None;
+            }
+            GameEvent::QuestionRevealed { question } => self.revealed = Some(question.clone()),
+            GameEvent::BoardOpened { board } => self.board = Some(board.clone()),
+            GameEvent::BoardClosed => self.board = None,
+            GameEvent::ClueSelected { index } => self.play_clue(at_ms, *index),
+            GameEvent::ChoicesOpened {
+                options,
+                weighting_secs,
+            } => self.start_choices(at_ms, options, *weighting_secs),
+            GameEvent::ChoiceSubmitted { player_id } => self.note_choice(at_ms, *player_id),
+            GameEvent::ChoicesClosed { correct, picks } => self.reveal_choices(*correct, picks),
+            GameEvent::AnswersOpened => {
+                self.written = Some(WrittenAnswers {
+                    open: true,
+                    ..Default::default()
+                })
+            }
+            GameEvent::AnswerSubmitted { player_id } => self.add_written_answer(at_ms, *player_id),
+            GameEvent::AnswersClosed => {
+                if let Some(written) = self.written.as_mut() {
+                    written.open = false;
+                }
+            }
+            GameEvent::AnswersShown { texts } => self.show_written_answers(texts),
+            GameEvent::AnswerGraded { player_id, grade } => {
+                self.grade_written_answer(*player_id, *grade)
+            }
+            GameEvent::WagersOpened => {
+                self.wager = Some(WagerRound {
+                    open: true,
+                    ..Default::default()
+                })
+            }
+            GameEvent::WagerPlaced { player_id } => self.note_wager(at_ms, *player_id),
+            GameEvent::WagersLocked => {
+                if let Some(round) = self.wager.as_mut() {
+                    round.open = false;
+                }
+            }
+            GameEvent::WagersRevealed { amounts } => self.reveal_wagers(amounts),
+            GameEvent::WagerResolved {
+                player_id,
+                amount,
+                won,
+                entry_id,
+            } => self.settle_wager(*player_id, *amount, *won, *entry_id),
+            GameEvent::PlayerJoined {
+                player_id,
+                player_name,
+            } => {
+                self.players.insert(
+                    *player_id,
+                    Actor::Player {
+                        id: *player_id,
+                        name: player_name.clone(),
+                    },
+                );
+                self.scores.insert(*player_id, 0);
+                self.player_join_order.push(*player_id);
+            }
+            GameEvent::PlayerDisconnected { player_id } => {
+                if let Some(mut actor) = self.players.get_mut(player_id) {
+                    let name = actor.name().to_string();
+                    *actor = Actor::Disconnected {
+                        id: *player_id,
+                        name,
+                    };
+                }
+            }
+            GameEvent::PlayerReconnected { player_id } => {
+                if let Some(mut actor) = self.players.get_mut(player_id) {
+                    let name = actor.name().to_string();
+                    *actor = Actor::Player {
+                        id: *player_id,
+                        name,
+                    };
+                }
+            }
+            GameEvent::TeamCreated { team_id, name } => self.add_team(*team_id, name),
+            GameEvent::TeamRemoved { team_id } => self.remove_team(team_id),
+            GameEvent::TeamAssigned { player_id, team_id } => {
+                self.set_team(*player_id, *team_id);
+            }
+            GameEvent::HostDisconnected => self.host_connected = false,
+            GameEvent::HostReconnected => self.host_connected = true,
+            GameEvent::Buzzed {
+                player_id,
+                player_name,
+                position,
+                timing,
+            } => {
+                let position = (*position).min(self.buzzer_order.len());
+                self.buzzer_order
+                    .insert(position, (*player_id, player_name.clone()));
+                self.buzz_timings.insert(*player_id, *timing);
+                self.note_buzz(*player_id, player_name, *timing);
+            }
+            GameEvent::Locked => self.globally_locked = true,
+            GameEvent::Unlocked => self.unlock_buzzers(at_ms),
+            GameEvent::QueueCleared => self.clear_buzzes(),
+            GameEvent::FalseStart {
+                player_id,
+                until_ms,
+            } => {
+                self.false_starts.insert(*player_id, *until_ms);
+            }
+            GameEvent::AnswerClockStarted {
+                player_id,
+                time_limit,
+            } => self.start_answer_clock(*player_id, *time_limit, at_ms),
+            GameEvent::AnswerClockStopped => self.answer_clock = None,
+            GameEvent::AnswerClockExpired { player_id } => {
+                if let Some(clock) = self.answer_clock.as_mut()
+                    && clock.player_id == *player_id
+                {
+                    clock.expired = true;
+                }
+            }
+            GameEvent::AnswerJudged { player_id, correct } => {
+                self.judge_answer(*player_id, *correct);
+            }
+            GameEvent::ScoreChanged {
+                player_id,
+                delta,
+                set_to,
+                reason,
+                buzz_position,
+            } => {
+                self.add_score_entry(
+                    at_ms,
+                    *player_id,
+                    *delta,
+                    *set_to,
+                    reason.clone(),
+                    *buzz_position,
+                );
+            }
+            GameEvent::ScoreUndone { entry_id } => self.undo_score_entry(*entry_id),
+            GameEvent::ScoreRedone { entry_id } => self.redo_score_entry(*entry_id),
+            GameEvent::CountdownStarted { time_limit } => {
+                self.start_countdown(*time_limit, at_ms);
+                self.time_choices(*time_limit);
+            }
+            GameEvent::CountdownPaused => {
+                self.pause_countdown(at_ms);
+            }
+            GameEvent::CountdownResumed => {
+                self.resume_countdown(at_ms);
+            }
+            GameEvent::CountdownExpired { deadline_ms } => {
+                self.expire_countdown(*deadline_ms);
+            }
+            GameEvent::CountdownOptionsChanged {
+                auto_lock,
+                pause_on_buzz,
+            } => {
+                self.countdown.auto_lock = *auto_lock;
+                self.countdown.pause_on_buzz = *pause_on_buzz;
+            }
+            GameEvent::RulesChanged { rules } => self.rules = *rules,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    #[test]
+    fn replaying_the_log_gives_the_live_game() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        buzz(&mut game, bob, 200).unwrap();
+        buzz(&mut game, alice, 210).unwrap();
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::Judge {
+                game_code: CODE,
+                correct: false,
+            },
+        )
+        .unwrap();
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::Judge {
+                game_code: CODE,
+                correct: true,
+            },
+        )
+        .unwrap();
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::UpdateScore {
+                game_code: CODE,
+                player_id: bob,
+                delta: 5,
+                reason: Some("Bonus".to_string()),
+            },
+        )
+        .unwrap();
+        host(&mut game, host_id, ClientToServer::Undo { game_code: CODE }).unwrap();
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::NextRound { game_code: CODE },
+        )
+        .unwrap();
+        buzz(&mut game, alice, 300).unwrap();
+        host(&mut game, host_id, ClientToServer::Lock { game_code: CODE }).unwrap();
+
+        let replayed = GameState::replay(&game.events);
+        let mut live = serde_json::to_value(game.to_json()).unwrap();
+        let mut rebuilt = serde_json::to_value(replayed.to_json()).unwrap();
+        // The sequence number counts broadcasts, which the log doesn't record
+        live["seq"] = 0.into();
+        rebuilt["seq"] = 0.into();
+        assert_eq!(rebuilt, live);
+        assert_eq!(replayed.score_ledger, game.score_ledger);
+        assert_eq!(replayed.scores[&alice], game.rules.points_correct);
+        assert_eq!(replayed.progress.round, 2);
+    }
+}
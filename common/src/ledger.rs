@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::*;
+
+/// How far a score may go either way; the host can't enter anything beyond
+pub const MAX_SCORE: i32 = 1_000_000;
+
+pub(crate) fn score_in_range(score: i32) -> bool {
+    (-MAX_SCORE..=MAX_SCORE).contains(&score)
+}
+
+/// One change to a player's score, kept so the host can review and revert it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScoreEntry {
+    /// Position in the ledger, starting at 0
+    pub id: u64,
+    pub player_id: Uuid,
+    pub at_ms: u64,
+    // The question the change was made during
+    #[serde(default)]
+    pub round: u32,
+    #[serde(default)]
+    pub question: u32,
+    pub delta: i32,
+    /// Set if the host entered an absolute score rather than a change
+    #[serde(default)]
+    pub set_to: Option<i32>,
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Where the player stood in the buzzer order when the change was made
+    #[serde(default)]
+    pub buzz_position: Option<usize>,
+    #[serde(default)]
+    pub undone: bool,
+}
+
+/// Every score change of a game, in the order they were made.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ScoreLedger {
+    pub entries: Vec<ScoreEntry>,
+    // Undone entries that can be redone, most recently undone last.
+    // A new change makes them permanent.
+    pub redo: Vec<u64>,
+}
+
+impl ScoreLedger {
+    /// The entry `Undo` would revert
+    pub fn undoable(&self) -> Option<&ScoreEntry> {
+        self.entries.iter().rev().find(|entry| !entry.undone)
+    }
+
+    /// The entry `Redo` would restore
+    pub fn redoable(&self) -> Option<&ScoreEntry> {
+        self.redo
+            .last()
+            .and_then(|id| self.entries.get(*id as usize))
+    }
+
+    fn next_id(&self) -> u64 {
+        self.entries.len() as u64
+    }
+
+    /// Adds or replaces the entry with the same id, so applying the same
+    /// entry twice changes nothing.
+    pub fn upsert(&mut self, entry: ScoreEntry) {
+        match self.entries.get_mut(entry.id as usize) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+        self.redo.clear();
+    }
+
+    /// Marks an entry undone and returns it, unless it already was
+    pub(crate) fn mark_undone(&mut self, entry_id: u64) -> Option<&ScoreEntry> {
+        let entry = self
+            .entries
+            .get_mut(entry_id as usize)
+            .filter(|entry| !entry.undone)?;
+        entry.undone = true;
+        self.redo.push(entry_id);
+        Some(entry)
+    }
+
+    /// Marks an undone entry as in effect again and returns it, unless it
+    /// already was
+    pub(crate) fn mark_redone(&mut self, entry_id: u64) -> Option<&ScoreEntry> {
+        let entry = self
+            .entries
+            .get_mut(entry_id as usize)
+            .filter(|entry| entry.undone)?;
+        entry.undone = false;
+        self.redo.retain(|id| *id != entry_id);
+        Some(entry)
+    }
+}
+
+impl GameState {
+    /// Rejects a change the host entered for someone who isn't a player, or
+    /// that would take the score out of range
+    pub(crate) fn check_score_change(&self, player_id: &Uuid, delta: i32) -> Result<(), GameError> {
+        self.check_player(player_id)?;
+        let current = self.scores.get(player_id).copied().unwrap_or_default();
+        match current.checked_add(delta) {
+            Some(score) if score_in_range(score) => Ok(()),
+            _ => Err(GameError::ScoreOutOfRange),
+        }
+    }
+
+    pub(crate) fn add_score_entry(
+        &mut self,
+        at_ms: u64,
+        player_id: Uuid,
+        delta: i32,
+        set_to: Option<i32>,
+        reason: Option<String>,
+        buzz_position: Option<usize>,
+    ) {
+        let entry = ScoreEntry {
+            id: self.score_ledger.next_id(),
+            player_id,
+            at_ms,
+            round: self.progress.round,
+            question: self.progress.question,
+            delta,
+            set_to,
+            reason,
+            buzz_position,
+            undone: false,
+        };
+        self.count_score(player_id, entry.round, delta);
+        self.score_ledger.upsert(entry);
+    }
+
+    pub(crate) fn undo_score_entry(&mut self, entry_id: u64) {
+        if let Some(entry) = self.score_ledger.mark_undone(entry_id) {
+            let (player_id, round, delta) = (entry.player_id, entry.round, entry.delta);
+            self.count_score(player_id, round, delta.saturating_neg());
+        }
+    }
+
+    pub(crate) fn redo_score_entry(&mut self, entry_id: u64) {
+        if let Some(entry) = self.score_ledger.mark_redone(entry_id) {
+            let (player_id, round, delta) = (entry.player_id, entry.round, entry.delta);
+            self.count_score(player_id, round, delta);
+        }
+    }
+
+    /// Adds to the player's score and to their points in `round`
+    fn count_score(&mut self, player_id: Uuid, round: u32, delta: i32) {
+        let score = self.scores.entry(player_id).or_insert(0);
+        *score = score.saturating_add(delta);
+        // Entries from before rounds existed belong to the first one
+        let round = round.max(1) as usize;
+        let totals = self.round_totals.entry(player_id).or_default();
+        if totals.len() < round {
+            totals.resize(round, 0);
+        }
+        totals[round - 1] = totals[round - 1].saturating_add(delta);
+    }
+
+    /// The broadcast for undoing or redoing `entry_id`
+    pub(crate) fn revert_delta(&mut self, entry_id: u64, undone: bool) -> Outbound {
+        let player_id = self
+            .score_ledger
+            .entries
+            .get(entry_id as usize)
+            .map(|entry| entry.player_id)
+            .unwrap_or_default();
+        let score = self.scores.get(&player_id).copied().unwrap_or_default();
+        let round_scores = self.round_scores(&player_id);
+        self.delta(if undone {
+            GameDelta::ScoreUndone {
+                entry_id,
+                player_id,
+                score,
+                round_scores,
+            }
+        } else {
+            GameDelta::ScoreRedone {
+                entry_id,
+                player_id,
+                score,
+                round_scores,
+            }
+        })
+    }
+}
@@ -1,13 +1,48 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+mod answer_clock;
+mod board;
+mod choices;
+mod countdown;
+mod delta;
+mod false_start;
+mod game_rules;
+mod history;
+mod ledger;
 mod message;
+mod protocol;
+mod question_bank;
+mod rounds;
+mod rules;
+mod teams;
+#[cfg(test)]
+mod test_support;
+mod timing;
+mod wagers;
+mod written;
+pub use answer_clock::*;
+pub use board::*;
+pub use choices::*;
+pub use countdown::*;
+pub use delta::*;
+pub use game_rules::*;
+pub use history::*;
+pub use ledger::*;
 pub use message::*;
+pub use protocol::*;
+pub use question_bank::*;
+pub use rounds::*;
+pub use rules::*;
+pub use teams::*;
+pub use timing::*;
+pub use wagers::*;
+pub use written::*;
 
-pub static HOST: &'static str = "HOST";
+pub static HOST: &str = "HOST";
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum Actor {
@@ -40,49 +75,189 @@ type PlayersMap = DashMap<Uuid, Actor>;
 #[derive(Debug, Clone, Default)]
 pub struct GameState {
     pub host_id: Uuid,
+    pub host_connected: bool,
     pub globally_locked: bool,
+    // When the buzzers were last unlocked, to tell false starts apart
+    pub unlocked_ms: Option<u64>,
     pub buzzer_order: VecDeque<(Uuid, String)>,
+    pub buzz_timings: HashMap<Uuid, BuzzTiming>,
+    // Players who answered the current question wrong and may not buzz again
+    pub locked_out: HashSet<Uuid>,
+    // Player id -> server time until which a false start keeps them from buzzing
+    pub false_starts: HashMap<Uuid, u64>,
     pub players: PlayersMap,
     pub scores: HashMap<Uuid, i32>,
+    pub teams: Vec<Team>,
+    // Player id -> team id, for players who are on a team
+    pub player_teams: HashMap<Uuid, Uuid>,
+    // Every score change; only the host gets it, snapshots leave it out
+    pub score_ledger: ScoreLedger,
+    // Points per round for every player, first round first
+    pub round_totals: HashMap<Uuid, Vec<i32>>,
     pub player_join_order: Vec<Uuid>,
     pub time_limit: Option<u32>,
+    pub countdown: CountdownState,
+    // Time the player at the head of the queue has left to answer
+    pub answer_clock: Option<AnswerClock>,
+    pub rules: GameRules,
+    pub progress: Progress,
+    // Position in `question_bank` the host is on, if a question set was imported
+    pub current_question: Option<usize>,
+    pub revealed: Option<RevealedQuestion>,
+    // Category/value grid when the game is played as a board
+    pub board: Option<Board>,
+    // The multiple-choice question that is open or was closed last
+    pub choice: Option<ChoiceQuestion>,
+    // Written answers being collected or graded for the current question
+    pub written: Option<WrittenAnswers>,
+    // Bets placed for a wager question
+    pub wager: Option<WagerRound>,
+    // Number of the last sequenced message that changed this state
+    pub seq: u64,
+    // Every mutation so far; not part of `GameStateJson` as it only grows
+    pub events: Vec<LoggedEvent>,
+    // The host's question set including the answers. Like the session tokens it
+    // is never part of `GameStateJson`, and not part of the event log either.
+    pub question_bank: Vec<Question>,
+    // Correct option and each player's pick for the open multiple-choice
+    // question; both only become public when it closes.
+    pub choice_key: Option<usize>,
+    pub choice_picks: HashMap<Uuid, usize>,
+    // Texts of the written answers; only the host sees them before they are shown
+    pub answer_texts: HashMap<Uuid, String>,
+    // Each player's bet; only the host sees them before they are revealed
+    pub wager_amounts: HashMap<Uuid, i32>,
+    // Secret token -> player id, used to resume a session after a dropped socket.
+    // Never part of `GameStateJson`, so it is not broadcast to other clients.
+    pub session_tokens: HashMap<Uuid, Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GameStateJson {
     host_id: Uuid,
+    host_connected: bool,
     locked: bool,
+    #[serde(default)]
+    unlocked_ms: Option<u64>,
     buzzer_order: VecDeque<(Uuid, String)>,
+    buzz_timings: HashMap<Uuid, BuzzTiming>,
+    #[serde(default)]
+    locked_out: HashSet<Uuid>,
+    #[serde(default)]
+    false_starts: HashMap<Uuid, u64>,
     players: HashMap<Uuid, Actor>,
     scores: HashMap<Uuid, i32>,
+    #[serde(default)]
+    teams: Vec<Team>,
+    #[serde(default)]
+    player_teams: HashMap<Uuid, Uuid>,
+    #[serde(default)]
+    round_totals: HashMap<Uuid, Vec<i32>>,
     player_join_order: Vec<Uuid>,
     time_limit: Option<u32>,
+    countdown: CountdownState,
+    #[serde(default)]
+    answer_clock: Option<AnswerClock>,
+    #[serde(default)]
+    rules: GameRules,
+    #[serde(default)]
+    progress: Progress,
+    #[serde(default)]
+    current_question: Option<usize>,
+    #[serde(default)]
+    revealed: Option<RevealedQuestion>,
+    #[serde(default)]
+    board: Option<Board>,
+    #[serde(default)]
+    choice: Option<ChoiceQuestion>,
+    #[serde(default)]
+    written: Option<WrittenAnswers>,
+    #[serde(default)]
+    wager: Option<WagerRound>,
+    seq: u64,
 }
 
 impl GameState {
     pub fn to_json(&self) -> GameStateJson {
         GameStateJson {
             host_id: self.host_id,
+            host_connected: self.host_connected,
             locked: self.globally_locked,
+            unlocked_ms: self.unlocked_ms,
             buzzer_order: self.buzzer_order.clone(),
+            buzz_timings: self.buzz_timings.clone(),
+            locked_out: self.locked_out.clone(),
+            false_starts: self.false_starts.clone(),
             players: self.players.clone().into_iter().collect(),
             scores: self.scores.clone(),
+            teams: self.teams.clone(),
+            player_teams: self.player_teams.clone(),
+            round_totals: self.round_totals.clone(),
             player_join_order: self.player_join_order.clone(),
             time_limit: self.time_limit,
+            countdown: self.countdown.clone(),
+            answer_clock: self.answer_clock.clone(),
+            rules: self.rules,
+            progress: self.progress.clone(),
+            current_question: self.current_question,
+            revealed: self.revealed.clone(),
+            board: self.board.clone(),
+            choice: self.choice.clone(),
+            written: self.written.clone(),
+            wager: self.wager.clone(),
+            seq: self.seq,
         }
     }
+
+    pub fn issue_session_token(&mut self, player_id: Uuid) -> Uuid {
+        let token = Uuid::new_v4();
+        self.session_tokens.insert(token, player_id);
+        token
+    }
+
+    /// Whether `token` is the host's session token
+    pub fn is_host_token(&self, token: &Uuid) -> bool {
+        self.session_tokens.get(token) == Some(&self.host_id)
+    }
 }
 
 impl From<GameStateJson> for GameState {
     fn from(json: GameStateJson) -> Self {
         Self {
             host_id: json.host_id,
+            host_connected: json.host_connected,
             globally_locked: json.locked,
+            unlocked_ms: json.unlocked_ms,
             buzzer_order: json.buzzer_order,
-            players: DashMap::from_iter(json.players.into_iter()),
+            buzz_timings: json.buzz_timings,
+            locked_out: json.locked_out,
+            false_starts: json.false_starts,
+            players: DashMap::from_iter(json.players),
             scores: json.scores,
+            teams: json.teams,
+            player_teams: json.player_teams,
+            score_ledger: ScoreLedger::default(),
+            round_totals: json.round_totals,
             player_join_order: json.player_join_order,
             time_limit: json.time_limit,
+            countdown: json.countdown,
+            answer_clock: json.answer_clock,
+            rules: json.rules,
+            progress: json.progress,
+            current_question: json.current_question,
+            revealed: json.revealed,
+            board: json.board,
+            choice: json.choice,
+            written: json.written,
+            wager: json.wager,
+            seq: json.seq,
+            events: Vec::new(),
+            question_bank: Vec::new(),
+            choice_key: None,
+            choice_picks: HashMap::new(),
+            answer_texts: HashMap::new(),
+            wager_amounts: HashMap::new(),
+            session_tokens: HashMap::new(),
         }
     }
 }
\ No newline at end of file
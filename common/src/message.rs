@@ -2,18 +2,44 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use super::*;
 
+/// A `ClientToServer` message plus an optional id chosen by the client. The
+/// server answers a request that has an id with `Ack` or `Error`, echoing it.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ClientRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<u64>,
+    #[serde(flatten)]
+    pub msg: ClientToServer,
+}
+
 // Messages from Client to Server
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(tag = "type")]
 pub enum ClientToServer {
+    /// Must be the first message on every connection
+    Hello {
+        protocol_version: u32,
+        #[serde(default)]
+        capabilities: Vec<Capability>,
+    },
     CreateGame,
     JoinGame {
         game_code: usize,
         player_name: String,
+        // Name of the team to join, or to create if there is none by that name
+        #[serde(default)]
+        team_name: Option<String>,
+    },
+    Rejoin {
+        game_code: usize,
+        token: Uuid,
     },
     Buzz {
         game_code: usize,
         player_id: Uuid,
+        // When the button was pressed, on the client's clock
+        #[serde(default)]
+        client_time_ms: Option<u64>,
     },
     Lock {
         game_code: usize,
@@ -24,10 +50,33 @@ pub enum ClientToServer {
     Clear {
         game_code: usize,
     },
+    /// Rules on the answer of the player at the head of the buzzer order,
+    /// scoring it according to the game's rules
+    Judge {
+        game_code: usize,
+        correct: bool,
+    },
     UpdateScore {
         game_code: usize,
         player_id: Uuid,
         delta: i32,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+    SetScore {
+        game_code: usize,
+        player_id: Uuid,
+        score: i32,
+        #[serde(default)]
+        reason: Option<String>,
+    },
+    /// Reverts the most recent score change that is still in effect
+    Undo {
+        game_code: usize,
+    },
+    /// Restores the most recently undone score change
+    Redo {
+        game_code: usize,
     },
     StartCountdown {
         game_code: usize,
@@ -39,26 +88,380 @@ pub enum ClientToServer {
     ResumeTimer {
         game_code: usize,
     },
+    SetCountdownOptions {
+        game_code: usize,
+        auto_lock: bool,
+        pause_on_buzz: bool,
+    },
+    SetRules {
+        game_code: usize,
+        rules: GameRules,
+    },
+    CreateTeam {
+        game_code: usize,
+        name: String,
+    },
+    /// Members of a removed team are left without a team
+    RemoveTeam {
+        game_code: usize,
+        team_id: Uuid,
+    },
+    /// Moves a player to a team, or off all teams if `team_id` is `None`
+    AssignTeam {
+        game_code: usize,
+        player_id: Uuid,
+        team_id: Option<Uuid>,
+    },
+    /// A player joins the team with this name, creating it if the rules allow
+    ChooseTeam {
+        game_code: usize,
+        team_name: String,
+    },
+    /// Archives the current question and starts the next one
+    NextQuestion {
+        game_code: usize,
+    },
+    /// Archives the current question and starts the next round
+    NextRound {
+        game_code: usize,
+    },
+    /// Replaces the game's question set
+    ImportQuestions {
+        game_code: usize,
+        questions: Vec<Question>,
+    },
+    /// Moves to the question set entry at `index`
+    SelectQuestion {
+        game_code: usize,
+        index: usize,
+    },
+    /// Shows the current question to the players
+    RevealQuestion {
+        game_code: usize,
+    },
+    /// Shows the current question and its answer to the players
+    RevealAnswer {
+        game_code: usize,
+    },
+    /// Lays the question set out as a board of categories and values
+    OpenBoard {
+        game_code: usize,
+    },
+    CloseBoard {
+        game_code: usize,
+    },
+    /// Shows the board clue at question set position `index` and unlocks
+    /// the buzzers for it
+    SelectClue {
+        game_code: usize,
+        index: usize,
+    },
+    /// Opens a multiple-choice question; `correct` indexes into `options`
+    OpenChoices {
+        game_code: usize,
+        options: Vec<String>,
+        correct: usize,
+    },
+    SubmitChoice {
+        game_code: usize,
+        choice: usize,
+    },
+    /// Stops taking answers, reveals the correct one and scores everyone
+    CloseChoices {
+        game_code: usize,
+    },
+    /// Starts collecting written answers to the current question
+    OpenAnswers {
+        game_code: usize,
+    },
+    SubmitAnswer {
+        game_code: usize,
+        text: String,
+    },
+    /// Stops taking written answers
+    CloseAnswers {
+        game_code: usize,
+    },
+    /// Shows the written answers to everyone
+    ShowAnswers {
+        game_code: usize,
+    },
+    /// Grades the written answers of these players and scores them
+    GradeAnswers {
+        game_code: usize,
+        player_ids: Vec<Uuid>,
+        grade: Grade,
+    },
+    /// Starts taking secret bets for a wager question
+    OpenWagers {
+        game_code: usize,
+    },
+    /// Places or changes the sender's bet
+    PlaceWager {
+        game_code: usize,
+        amount: i32,
+    },
+    LockWagers {
+        game_code: usize,
+    },
+    /// Shows every bet to everyone
+    RevealWagers {
+        game_code: usize,
+    },
+    /// Adds the player's bet to their score, or takes it away
+    ResolveWager {
+        game_code: usize,
+        player_id: Uuid,
+        correct: bool,
+    },
+    /// Sent by a client that noticed a gap in the delta sequence
+    RequestSnapshot {
+        game_code: usize,
+    },
+    ClockPong {
+        server_time_ms: u64,
+        client_time_ms: u64,
+    },
+}
+
+/// Who is allowed to send a given `ClientToServer` message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    // Not bound to a game yet, e.g. creating or joining one
+    Anyone,
+    // Any non-host player of the addressed game
+    Player,
+    // The host or any player of the addressed game
+    Member,
+    // Only the host of the addressed game
+    Host,
+}
+
+impl ClientToServer {
+    pub fn permission(&self) -> Permission {
+        match self {
+            Self::CreateGame | Self::JoinGame { .. } | Self::Rejoin { .. } => Permission::Anyone,
+            Self::Hello { .. } | Self::ClockPong { .. } => Permission::Anyone,
+            Self::Buzz { .. }
+            | Self::ChooseTeam { .. }
+            | Self::SubmitChoice { .. }
+            | Self::SubmitAnswer { .. }
+            | Self::PlaceWager { .. } => Permission::Player,
+            Self::RequestSnapshot { .. } => Permission::Member,
+            Self::Lock { .. }
+            | Self::Unlock { .. }
+            | Self::Clear { .. }
+            | Self::Judge { .. }
+            | Self::UpdateScore { .. }
+            | Self::SetScore { .. }
+            | Self::Undo { .. }
+            | Self::Redo { .. }
+            | Self::StartCountdown { .. }
+            | Self::PauseTimer { .. }
+            | Self::ResumeTimer { .. }
+            | Self::SetCountdownOptions { .. }
+            | Self::SetRules { .. }
+            | Self::NextQuestion { .. }
+            | Self::NextRound { .. }
+            | Self::ImportQuestions { .. }
+            | Self::SelectQuestion { .. }
+            | Self::RevealQuestion { .. }
+            | Self::RevealAnswer { .. }
+            | Self::OpenBoard { .. }
+            | Self::CloseBoard { .. }
+            | Self::SelectClue { .. }
+            | Self::OpenChoices { .. }
+            | Self::CloseChoices { .. }
+            | Self::OpenAnswers { .. }
+            | Self::CloseAnswers { .. }
+            | Self::ShowAnswers { .. }
+            | Self::GradeAnswers { .. }
+            | Self::OpenWagers { .. }
+            | Self::LockWagers { .. }
+            | Self::RevealWagers { .. }
+            | Self::ResolveWager { .. }
+            | Self::CreateTeam { .. }
+            | Self::RemoveTeam { .. }
+            | Self::AssignTeam { .. } => Permission::Host,
+        }
+    }
+
+    /// The game this message claims to address, if any
+    pub fn game_code(&self) -> Option<usize> {
+        match self {
+            Self::CreateGame | Self::Hello { .. } | Self::ClockPong { .. } => None,
+            Self::JoinGame { game_code, .. }
+            | Self::Rejoin { game_code, .. }
+            | Self::Buzz { game_code, .. }
+            | Self::Lock { game_code }
+            | Self::Unlock { game_code }
+            | Self::Clear { game_code }
+            | Self::Judge { game_code, .. }
+            | Self::UpdateScore { game_code, .. }
+            | Self::SetScore { game_code, .. }
+            | Self::Undo { game_code }
+            | Self::Redo { game_code }
+            | Self::StartCountdown { game_code, .. }
+            | Self::PauseTimer { game_code }
+            | Self::ResumeTimer { game_code }
+            | Self::RequestSnapshot { game_code }
+            | Self::SetCountdownOptions { game_code, .. }
+            | Self::SetRules { game_code, .. }
+            | Self::NextQuestion { game_code }
+            | Self::NextRound { game_code }
+            | Self::ImportQuestions { game_code, .. }
+            | Self::SelectQuestion { game_code, .. }
+            | Self::RevealQuestion { game_code }
+            | Self::RevealAnswer { game_code }
+            | Self::OpenBoard { game_code }
+            | Self::CloseBoard { game_code }
+            | Self::SelectClue { game_code, .. }
+            | Self::OpenChoices { game_code, .. }
+            | Self::SubmitChoice { game_code, .. }
+            | Self::CloseChoices { game_code }
+            | Self::OpenAnswers { game_code }
+            | Self::SubmitAnswer { game_code, .. }
+            | Self::CloseAnswers { game_code }
+            | Self::ShowAnswers { game_code }
+            | Self::GradeAnswers { game_code, .. }
+            | Self::OpenWagers { game_code }
+            | Self::PlaceWager { game_code, .. }
+            | Self::LockWagers { game_code }
+            | Self::RevealWagers { game_code }
+            | Self::ResolveWager { game_code, .. }
+            | Self::CreateTeam { game_code, .. }
+            | Self::RemoveTeam { game_code, .. }
+            | Self::AssignTeam { game_code, .. }
+            | Self::ChooseTeam { game_code, .. } => Some(*game_code),
+        }
+    }
+
+    /// The message type without its contents, for logging. Some messages
+    /// carry secrets such as session tokens, answer keys or written answers.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Hello { .. } => "Hello",
+            Self::CreateGame => "CreateGame",
+            Self::JoinGame { .. } => "JoinGame",
+            Self::Rejoin { .. } => "Rejoin",
+            Self::Buzz { .. } => "Buzz",
+            Self::Lock { .. } => "Lock",
+            Self::Unlock { .. } => "Unlock",
+            Self::Clear { .. } => "Clear",
+            Self::Judge { .. } => "Judge",
+            Self::UpdateScore { .. } => "UpdateScore",
+            Self::SetScore { .. } => "SetScore",
+            Self::Undo { .. } => "Undo",
+            Self::Redo { .. } => "Redo",
+            Self::StartCountdown { .. } => "StartCountdown",
+            Self::PauseTimer { .. } => "PauseTimer",
+            Self::ResumeTimer { .. } => "ResumeTimer",
+            Self::SetCountdownOptions { .. } => "SetCountdownOptions",
+            Self::SetRules { .. } => "SetRules",
+            Self::CreateTeam { .. } => "CreateTeam",
+            Self::RemoveTeam { .. } => "RemoveTeam",
+            Self::AssignTeam { .. } => "AssignTeam",
+            Self::ChooseTeam { .. } => "ChooseTeam",
+            Self::NextQuestion { .. } => "NextQuestion",
+            Self::NextRound { .. } => "NextRound",
+            Self::ImportQuestions { .. } => "ImportQuestions",
+            Self::SelectQuestion { .. } => "SelectQuestion",
+            Self::RevealQuestion { .. } => "RevealQuestion",
+            Self::RevealAnswer { .. } => "RevealAnswer",
+            Self::OpenBoard { .. } => "OpenBoard",
+            Self::CloseBoard { .. } => "CloseBoard",
+            Self::SelectClue { .. } => "SelectClue",
+            Self::OpenChoices { .. } => "OpenChoices",
+            Self::SubmitChoice { .. } => "SubmitChoice",
+            Self::CloseChoices { .. } => "CloseChoices",
+            Self::OpenAnswers { .. } => "OpenAnswers",
+            Self::SubmitAnswer { .. } => "SubmitAnswer",
+            Self::CloseAnswers { .. } => "CloseAnswers",
+            Self::ShowAnswers { .. } => "ShowAnswers",
+            Self::GradeAnswers { .. } => "GradeAnswers",
+            Self::OpenWagers { .. } => "OpenWagers",
+            Self::PlaceWager { .. } => "PlaceWager",
+            Self::LockWagers { .. } => "LockWagers",
+            Self::RevealWagers { .. } => "RevealWagers",
+            Self::ResolveWager { .. } => "ResolveWager",
+            Self::RequestSnapshot { .. } => "RequestSnapshot",
+            Self::ClockPong { .. } => "ClockPong",
+        }
+    }
 }
 
 // Messages from Server to Client
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum ServerToClient {
+    /// Accepts a `Hello`; `capabilities` are those both sides support
+    Welcome {
+        protocol_version: u32,
+        capabilities: Vec<Capability>,
+    },
+    /// The client is too old or too new for this server and has to reload
+    UpgradeRequired {
+        protocol_version: u32,
+        min_protocol_version: u32,
+    },
     GameCreated {
         game_code: usize,
         player_id: Uuid,
+        host_token: Uuid,
         game_state: GameStateJson,
     },
     GameJoined {
         player_id: Uuid,
         player_name: String,
+        session_token: Uuid,
         game_state: GameStateJson,
     },
+    /// A full snapshot; `game_state.seq` tells which deltas it already contains
     GameStateUpdate {
         game_state: GameStateJson,
     },
+    /// The change numbered `seq`, to be applied on top of everything before it
+    Delta {
+        seq: u64,
+        delta: GameDelta,
+    },
+    /// The question set with its answers; only ever sent to the host
+    QuestionBank {
+        questions: Vec<Question>,
+    },
+    /// Every written answer to the current question; only ever sent to the host
+    AnswerTexts {
+        texts: HashMap<Uuid, String>,
+    },
+    /// Every player's bet; only ever sent to the host
+    WagerAmounts {
+        amounts: HashMap<Uuid, i32>,
+    },
+    /// The score ledger and the finished questions, which snapshots leave
+    /// out; only ever sent to the host
+    GameRecords {
+        score_ledger: ScoreLedger,
+        history: Vec<QuestionRecord>,
+    },
+    /// The ledger entry behind a score change, which the broadcast leaves
+    /// out; only ever sent to the host
+    ScoreRecorded {
+        entry: ScoreEntry,
+    },
+    /// A question is over and was added to the history; only ever sent to
+    /// the host
+    QuestionArchived {
+        record: QuestionRecord,
+    },
+    /// The request with this id was carried out
+    Ack {
+        request_id: u64,
+    },
+    /// `request_id` is set if the failed request had one
     Error {
+        #[serde(default)]
+        request_id: Option<u64>,
+        code: ErrorCode,
         message: String,
     },
     PlayerBuzzed {
@@ -67,8 +470,71 @@ pub enum ServerToClient {
     },
     CountdownStarted {
         time_limit: u32,
+        deadline_ms: u64,
     },
     TimerPaused {
-        paused: bool,
+        remaining_ms: u64,
     },
+    TimerResumed {
+        deadline_ms: u64,
+    },
+    TimerExpired,
+    ClockPing {
+        server_time_ms: u64,
+    },
+    ClockSync {
+        offset_ms: i64,
+        rtt_ms: u64,
+    },
+    HostDisconnected {
+        grace_period_secs: u64,
+    },
+    HostReconnected,
+    GameClosed,
+}
+
+/// Stable reason for an `Error`; clients decide what to do based on this,
+/// the message is only meant for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorCode {
+    GameNotFound,
+    NotInGame,
+    WrongGame,
+    IdMismatch,
+    NotHost,
+    NotPlayer,
+    EmptyName,
+    NameTaken,
+    SessionExpired,
+    Locked,
+    AlreadyBuzzed,
+    LockedOut,
+    FalseStart,
+    NobodyBuzzed,
+    QueueFull,
+    InvalidRules,
+    TeammateBuzzed,
+    EmptyTeamName,
+    TeamNameTaken,
+    UnknownTeam,
+    UnknownPlayer,
+    InvalidQuestions,
+    NoQuestion,
+    InvalidBoard,
+    InvalidChoices,
+    NoChoices,
+    AlreadyAnswered,
+    NoAnswers,
+    InvalidAnswer,
+    NoWagers,
+    InvalidWager,
+    RateLimited,
+    NothingToUndo,
+    NothingToRedo,
+    ScoreOutOfRange,
+    AlreadyJoined,
+    InvalidTimestamp,
+    AlreadyInGame,
+    #[serde(other)]
+    Unknown,
 }
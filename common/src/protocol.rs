@@ -0,0 +1,81 @@
+use serde::{Deserialize, Serialize};
+
+/// Bumped whenever `ClientToServer` or `ServerToClient` change incompatibly.
+/// Version 3 added the question bank, the host-only answer, wager and record
+/// messages, and deltas for submissions and score reverts.
+pub const PROTOCOL_VERSION: u32 = 3;
+/// The oldest client protocol the server still understands. Older clients
+/// can't parse the messages added in version 3.
+pub const MIN_PROTOCOL_VERSION: u32 = 3;
+
+/// An optional protocol feature. Peers ignore capabilities they do not know,
+/// so new ones can be added without a version bump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Capability {
+    /// Sequenced `Delta` messages and `RequestSnapshot`
+    Deltas,
+    /// `ClockPing`/`ClockPong` offset estimation
+    ClockSync,
+    /// Resuming a seat with `Rejoin` and a session token
+    SessionResume,
+    /// `Ack`/`Error` replies to requests that carry a `request_id`
+    RequestIds,
+    #[serde(other)]
+    Unknown,
+}
+
+/// Everything this build supports
+pub const CAPABILITIES: &[Capability] = &[
+    Capability::Deltas,
+    Capability::ClockSync,
+    Capability::SessionResume,
+    Capability::RequestIds,
+];
+
+pub fn is_compatible(protocol_version: u32) -> bool {
+    (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&protocol_version)
+}
+
+/// The capabilities of `theirs` that this build supports as well
+pub fn negotiate(theirs: &[Capability]) -> Vec<Capability> {
+    CAPABILITIES
+        .iter()
+        .copied()
+        .filter(|c| theirs.contains(c))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_versions_from_the_minimum_to_the_current_one_are_compatible() {
+        assert!(is_compatible(PROTOCOL_VERSION));
+        assert!(is_compatible(MIN_PROTOCOL_VERSION));
+        assert!(!is_compatible(MIN_PROTOCOL_VERSION - 1));
+        assert!(!is_compatible(PROTOCOL_VERSION + 1));
+        // Bundles from before the handshake are treated as version 0
+        assert!(!is_compatible(0));
+    }
+
+    #[test]
+    fn negotiation_keeps_the_capabilities_both_sides_have() {
+        assert_eq!(
+            negotiate(&[
+                Capability::Unknown,
+                Capability::RequestIds,
+                Capability::Deltas
+            ]),
+            vec![Capability::Deltas, Capability::RequestIds]
+        );
+        assert!(negotiate(&[]).is_empty());
+        assert_eq!(negotiate(CAPABILITIES), CAPABILITIES.to_vec());
+    }
+
+    #[test]
+    fn unknown_capabilities_still_parse() {
+        let parsed: Vec<Capability> = serde_json::from_str(r#"["Deltas", "Telepathy"]"#).unwrap();
+        assert_eq!(parsed, vec![Capability::Deltas, Capability::Unknown]);
+    }
+}
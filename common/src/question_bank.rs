@@ -0,0 +1,406 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+// Keeps a single upload, and the snapshot it is stored in, reasonably small
+pub const MAX_QUESTIONS: usize = 500;
+
+/// One entry of the host's question set. The answer is only ever sent to the
+/// host until it is revealed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Question {
+    pub question: String,
+    pub answer: String,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub points: Option<i32>,
+    /// Link to an image or other file that goes with the question
+    #[serde(default)]
+    pub media: Option<String>,
+}
+
+/// A question as shown to players once the host revealed it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RevealedQuestion {
+    /// Position in the question set, counted from 0
+    pub index: usize,
+    pub question: String,
+    pub category: Option<String>,
+    pub points: Option<i32>,
+    pub media: Option<String>,
+    // Set once the host revealed the answer too
+    pub answer: Option<String>,
+}
+
+/// A problem with one row of a question set file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RowError {
+    /// Row of the file, counted from 1 like a spreadsheet does; 0 for
+    /// problems with the file as a whole
+    pub row: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.row == 0 {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "Row {}: {}", self.row, self.message)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuestionFormat {
+    Json,
+    Csv,
+}
+
+impl QuestionFormat {
+    /// Picks the format from a file name's extension
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        let extension = name.rsplit_once('.')?.1.to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(Self::Json),
+            "csv" => Some(Self::Csv),
+            _ => None,
+        }
+    }
+}
+
+impl Question {
+    /// Checks a question after parsing, and again on the server
+    pub fn validate(&self) -> Result<(), String> {
+        if self.question.trim().is_empty() {
+            return Err("the question is empty".to_string());
+        }
+        if self.answer.trim().is_empty() {
+            return Err("the answer is empty".to_string());
+        }
+        if let Some(points) = self.points
+            && points < 0
+        {
+            return Err(format!("points must not be negative, got {}", points));
+        }
+        if let Some(media) = &self.media
+            && !(media.starts_with("https://") || media.starts_with("http://"))
+        {
+            return Err(format!("media must be an http(s) link, got '{}'", media));
+        }
+        Ok(())
+    }
+}
+
+/// Parses a question set file. Either every row is fine, or every row that
+/// isn't is reported.
+pub fn parse_questions(
+    format: QuestionFormat,
+    content: &str,
+) -> Result<Vec<Question>, Vec<RowError>> {
+    let rows = match format {
+        QuestionFormat::Json => parse_json(content)?,
+        QuestionFormat::Csv => parse_csv(content)?,
+    };
+    check_questions(rows)
+}
+
+/// Each row number with the question parsed from it, or why it could not be.
+type ParsedRows = Vec<(usize, Result<Question, String>)>;
+
+/// Validates parsed rows, collecting the problems of all of them.
+fn check_questions(rows: ParsedRows) -> Result<Vec<Question>, Vec<RowError>> {
+    let mut questions = Vec::new();
+    let mut errors = Vec::new();
+    for (row, parsed) in rows {
+        match parsed.and_then(|question| question.validate().map(|_| question)) {
+            Ok(question) => questions.push(question),
+            Err(message) => errors.push(RowError { row, message }),
+        }
+    }
+    if questions.is_empty() && errors.is_empty() {
+        errors.push(file_error("the file contains no questions"));
+    }
+    if questions.len() > MAX_QUESTIONS {
+        errors.push(file_error(&format!(
+            "at most {} questions are allowed, the file has {}",
+            MAX_QUESTIONS,
+            questions.len()
+        )));
+    }
+    if errors.is_empty() {
+        Ok(questions)
+    } else {
+        Err(errors)
+    }
+}
+
+fn file_error(message: &str) -> RowError {
+    RowError {
+        row: 0,
+        message: message.to_string(),
+    }
+}
+
+/// A JSON array of question objects; row `n` is the `n`th element.
+fn parse_json(content: &str) -> Result<ParsedRows, Vec<RowError>> {
+    let values: Vec<serde_json::Value> = serde_json::from_str(content)
+        .map_err(|e| vec![file_error(&format!("not a JSON array of questions: {}", e))])?;
+    Ok(values
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| {
+            (
+                i + 1,
+                serde_json::from_value(value).map_err(|e| e.to_string()),
+            )
+        })
+        .collect())
+}
+
+/// A header row naming the columns, in any order, followed by one question
+/// per row. Unknown columns are ignored.
+fn parse_csv(content: &str) -> Result<ParsedRows, Vec<RowError>> {
+    let mut records = read_csv(content).map_err(|message| vec![file_error(&message)])?;
+    if records.is_empty() {
+        return Err(vec![file_error("the file is empty")]);
+    }
+    let (_, header) = records.remove(0);
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|title| title.trim().eq_ignore_ascii_case(name))
+    };
+    let (Some(question_col), Some(answer_col)) = (column("question"), column("answer")) else {
+        return Err(vec![RowError {
+            row: 1,
+            message: "the header needs at least a 'question' and an 'answer' column".to_string(),
+        }]);
+    };
+    let (category_col, points_col, media_col) =
+        (column("category"), column("points"), column("media"));
+
+    Ok(records
+        .into_iter()
+        .filter(|(_, fields)| fields.iter().any(|field| !field.trim().is_empty()))
+        .map(|(row, fields)| {
+            let cell = |col: Option<usize>| {
+                col.and_then(|col| fields.get(col))
+                    .map(|field| field.trim().to_string())
+                    .filter(|field| !field.is_empty())
+            };
+            let points = match cell(points_col) {
+                Some(points) => match points.parse::<i32>() {
+                    Ok(points) => Some(points),
+                    Err(_) => {
+                        return (
+                            row,
+                            Err(format!("points must be a whole number, got '{}'", points)),
+                        );
+                    }
+                },
+                None => None,
+            };
+            let question = Question {
+                question: cell(Some(question_col)).unwrap_or_default(),
+                answer: cell(Some(answer_col)).unwrap_or_default(),
+                category: cell(category_col),
+                points,
+                media: cell(media_col),
+            };
+            (row, Ok(question))
+        })
+        .collect())
+}
+
+/// Splits CSV text into records, each with the row it starts on. Quoted
+/// fields may contain commas, line breaks and doubled quotes.
+fn read_csv(content: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut row = 1;
+    let mut record_row = 1;
+    let mut chars = content.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.is_empty() => in_quotes = true,
+            '\n' if in_quotes => {
+                row += 1;
+                field.push(c);
+            }
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            '\n' | '\r' if !in_quotes => {
+                fields.push(std::mem::take(&mut field));
+                records.push((record_row, std::mem::take(&mut fields)));
+                row += 1;
+                record_row = row;
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(format!(
+            "row {} has a quote that is never closed",
+            record_row
+        ));
+    }
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        records.push((record_row, fields));
+    }
+    Ok(records)
+}
+
+impl GameState {
+    /// The question set entry the host is currently on
+    pub fn current_bank_question(&self) -> Option<&Question> {
+        self.question_bank.get(self.current_question?)
+    }
+
+    /// What a correct answer is worth: the points imported with the current
+    /// question if it has any, the usual points otherwise
+    pub fn question_points(&self) -> i32 {
+        self.current_bank_question()
+            .and_then(|question| question.points)
+            .unwrap_or(self.rules.points_correct)
+    }
+
+    /// A question as players get to see it, with or without its answer
+    pub(crate) fn revealed_question(
+        &self,
+        index: usize,
+        with_answer: bool,
+    ) -> Option<RevealedQuestion> {
+        let question = self.question_bank.get(index)?;
+        Some(RevealedQuestion {
+            index,
+            question: question.question.clone(),
+            category: question.category.clone(),
+            points: question.points,
+            media: question.media.clone(),
+            answer: with_answer.then(|| question.answer.clone()),
+        })
+    }
+
+    /// Checks questions sent by a client; the server can't trust its parsing.
+    pub(crate) fn check_import(questions: Vec<Question>) -> Result<Vec<Question>, Vec<RowError>> {
+        check_questions(
+            questions
+                .into_iter()
+                .enumerate()
+                .map(|(i, question)| (i + 1, Ok(question)))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(records: &[(usize, Vec<String>)]) -> Vec<Vec<&str>> {
+        records
+            .iter()
+            .map(|(_, fields)| fields.iter().map(String::as_str).collect())
+            .collect()
+    }
+
+    #[test]
+    fn quoted_fields_keep_commas_quotes_and_line_breaks() {
+        let records =
+            read_csv("a,\"b, c\",\"say \"\"hi\"\"\"\r\n\"two\nlines\",x,\"\"\r\nlast,row,")
+                .unwrap();
+        assert_eq!(
+            fields(&records),
+            [
+                vec!["a", "b, c", "say \"hi\""],
+                vec!["two\nlines", "x", ""],
+                vec!["last", "row", ""],
+            ]
+        );
+        // A record spanning lines is numbered by the row it starts on
+        let rows: Vec<usize> = records.iter().map(|(row, _)| *row).collect();
+        assert_eq!(rows, [1, 2, 4]);
+    }
+
+    #[test]
+    fn an_unclosed_quote_is_an_error() {
+        let error = read_csv("question,answer\n\"open,end\n").unwrap_err();
+        assert!(error.contains("row 2"), "{}", error);
+    }
+
+    #[test]
+    fn csv_columns_are_found_by_their_header() {
+        let content = "\u{feff}Points,Answer,Question,Notes\r\n\
+                       200,Paris,Capital of France?,easy\r\n\
+                       ,\"Tolstoy, Leo\",\"Who wrote \"\"War and Peace\"\"?\",\r\n";
+        let questions = parse_questions(QuestionFormat::Csv, content).unwrap();
+        assert_eq!(
+            questions,
+            [
+                Question {
+                    question: "Capital of France?".to_string(),
+                    answer: "Paris".to_string(),
+                    category: None,
+                    points: Some(200),
+                    media: None,
+                },
+                Question {
+                    question: "Who wrote \"War and Peace\"?".to_string(),
+                    answer: "Tolstoy, Leo".to_string(),
+                    category: None,
+                    points: None,
+                    media: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn a_header_without_question_and_answer_is_rejected() {
+        let errors = parse_questions(QuestionFormat::Csv, "prompt,reply\nA,B\n").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].row, 1);
+
+        let errors = parse_questions(QuestionFormat::Csv, "").unwrap_err();
+        assert_eq!(errors, [file_error("the file is empty")]);
+    }
+
+    #[test]
+    fn blank_rows_are_skipped() {
+        let content = "question,answer\n\nQ1,A1\n , \n\nQ2,A2";
+        let questions = parse_questions(QuestionFormat::Csv, content).unwrap();
+        let asked: Vec<&str> = questions.iter().map(|q| q.question.as_str()).collect();
+        assert_eq!(asked, ["Q1", "Q2"]);
+
+        let errors = parse_questions(QuestionFormat::Csv, "question,answer\n,\n").unwrap_err();
+        assert_eq!(errors, [file_error("the file contains no questions")]);
+    }
+
+    #[test]
+    fn every_bad_row_is_reported() {
+        let content = "question,answer,points,media\n\
+                       Q1,A1,ten,\n\
+                       Q2,A2,10,\n\
+                       ,A3,,\n\
+                       Q4,A4,,ftp://example.com/a.png\n";
+        let errors = parse_questions(QuestionFormat::Csv, content).unwrap_err();
+        let rows: Vec<usize> = errors.iter().map(|e| e.row).collect();
+        assert_eq!(rows, [2, 4, 5]);
+        assert_eq!(
+            errors[0].to_string(),
+            "Row 2: points must be a whole number, got 'ten'"
+        );
+    }
+}
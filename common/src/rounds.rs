@@ -0,0 +1,284 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::*;
+
+/// A buzz as it is kept for the question history
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuestionBuzz {
+    pub player_id: Uuid,
+    pub player_name: String,
+    pub timing: BuzzTiming,
+}
+
+/// What happened during a question that is over
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuestionRecord {
+    pub round: u32,
+    pub question: u32,
+    pub started_ms: u64,
+    pub ended_ms: u64,
+    /// Every buzz of the question in the order they arrived, including
+    /// those of players who were judged or cleared away
+    pub buzzes: Vec<QuestionBuzz>,
+    /// Ids of the score ledger entries made during the question
+    pub score_entries: Vec<u64>,
+    /// Written answers to the question; their texts only if they were shown
+    #[serde(default)]
+    pub answers: Vec<WrittenAnswer>,
+}
+
+/// Where the game is: the current round and question, counted from 1, and
+/// the questions that are over.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Progress {
+    pub round: u32,
+    pub question: u32,
+    pub question_started_ms: u64,
+    // Buzzes of the current question so far
+    pub buzzes: Vec<QuestionBuzz>,
+    // Only the host gets the finished questions, snapshots leave them out
+    #[serde(skip)]
+    pub history: Vec<QuestionRecord>,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self {
+            round: 1,
+            question: 1,
+            question_started_ms: 0,
+            buzzes: Vec::new(),
+            history: Vec::new(),
+        }
+    }
+}
+
+impl GameState {
+    /// Keeps a buzz for the question history. Seeing the same buzz twice,
+    /// e.g. as a delta after a snapshot, keeps it once.
+    pub(crate) fn note_buzz(&mut self, player_id: Uuid, player_name: &str, timing: BuzzTiming) {
+        let buzzes = &mut self.progress.buzzes;
+        if !buzzes
+            .iter()
+            .any(|buzz| buzz.player_id == player_id && buzz.timing == timing)
+        {
+            buzzes.push(QuestionBuzz {
+                player_id,
+                player_name: player_name.to_string(),
+                timing,
+            });
+        }
+    }
+
+    /// Archives the current question and moves on to the next one, or to the
+    /// first question of the next round. The buzzer queue, lock and countdown
+    /// start over.
+    pub(crate) fn advance_question(&mut self, at_ms: u64, new_round: bool) {
+        let (round, question) = (self.progress.round, self.progress.question);
+        let score_entries = self
+            .score_ledger
+            .entries
+            .iter()
+            .filter(|entry| entry.round == round && entry.question == question)
+            .map(|entry| entry.id)
+            .collect();
+        let record = QuestionRecord {
+            round,
+            question,
+            started_ms: self.progress.question_started_ms,
+            ended_ms: at_ms,
+            buzzes: std::mem::take(&mut self.progress.buzzes),
+            score_entries,
+            answers: self.written.take().map(|w| w.answers).unwrap_or_default(),
+        };
+        self.progress.history.push(record);
+        if new_round {
+            self.progress.round += 1;
+            self.progress.question = 1;
+        } else {
+            self.progress.question += 1;
+        }
+        self.progress.question_started_ms = at_ms;
+
+        self.clear_buzzes();
+        self.revealed = None;
+        self.choice = None;
+        self.wager = None;
+        if let Some(board) = self.board.as_mut() {
+            board.active = None;
+        }
+        self.unlock_buzzers(at_ms);
+        self.countdown.running = false;
+        self.countdown.deadline_ms = None;
+        self.countdown.remaining_ms = 0;
+    }
+
+    /// Points `player_id` made in each round so far, first round first.
+    /// Undone score changes don't count.
+    pub fn round_scores(&self, player_id: &Uuid) -> Vec<i32> {
+        let mut scores = self
+            .round_totals
+            .get(player_id)
+            .cloned()
+            .unwrap_or_default();
+        let rounds = scores.len().max(self.progress.round as usize);
+        scores.resize(rounds, 0);
+        scores
+    }
+
+    /// What snapshots leave out of the game's records, for the host
+    pub(crate) fn records_message(&self) -> ServerToClient {
+        ServerToClient::GameRecords {
+            score_ledger: self.score_ledger.clone(),
+            history: self.progress.history.clone(),
+        }
+    }
+
+    /// Archives the current question and tells the host about it
+    pub(crate) fn archive_question(&mut self, now_ms: u64, new_round: bool) -> Outbound {
+        self.record(now_ms, GameEvent::QuestionAdvanced { new_round });
+        let record = self.progress.history.last().cloned();
+        match record {
+            Some(record) => {
+                Outbound::Send(self.host_id, ServerToClient::QuestionArchived { record })
+            }
+            None => Outbound::Send(self.host_id, self.records_message()),
+        }
+    }
+
+    /// Adds a question the host was told about to the history, once
+    pub fn add_archived_question(&mut self, record: QuestionRecord) {
+        let history = &mut self.progress.history;
+        if !history
+            .iter()
+            .any(|r| r.round == record.round && r.question == record.question)
+        {
+            history.push(record);
+        }
+    }
+
+    /// Sum of `round_scores` over a team's members
+    pub fn team_round_scores(&self, team_id: &Uuid) -> Vec<i32> {
+        let mut scores = vec![0; self.progress.round as usize];
+        for player_id in self.team_members(team_id) {
+            for (total, score) in scores.iter_mut().zip(self.round_scores(&player_id)) {
+                *total += score;
+            }
+        }
+        scores
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    fn add_points(game: &mut GameState, host_id: Uuid, player_id: Uuid, delta: i32) {
+        host(
+            game,
+            host_id,
+            ClientToServer::UpdateScore {
+                game_code: CODE,
+                player_id,
+                delta,
+                reason: None,
+            },
+        )
+        .unwrap();
+    }
+
+    fn archived(out: &[Outbound]) -> &QuestionRecord {
+        out.iter()
+            .find_map(|o| match o {
+                Outbound::Send(_, ServerToClient::QuestionArchived { record }) => Some(record),
+                _ => None,
+            })
+            .expect("the host hears about the archived question")
+    }
+
+    #[test]
+    fn moving_on_archives_the_question() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        buzz(&mut game, bob, 200).unwrap();
+        buzz(&mut game, alice, 210).unwrap();
+        add_points(&mut game, host_id, alice, 10);
+
+        let out = host(
+            &mut game,
+            host_id,
+            ClientToServer::NextQuestion { game_code: CODE },
+        )
+        .unwrap();
+        let record = archived(&out);
+        assert_eq!((record.round, record.question), (1, 1));
+        let buzzed: Vec<Uuid> = record.buzzes.iter().map(|b| b.player_id).collect();
+        assert_eq!(buzzed, [bob, alice]);
+        assert_eq!(record.score_entries, [0]);
+        assert_eq!(record.ended_ms, 100);
+
+        assert_eq!((game.progress.round, game.progress.question), (1, 2));
+        assert!(game.buzzer_order.is_empty());
+        assert!(game.progress.buzzes.is_empty());
+        // Scores carry over
+        assert_eq!(game.scores[&alice], 10);
+
+        let out = host(
+            &mut game,
+            host_id,
+            ClientToServer::NextRound { game_code: CODE },
+        )
+        .unwrap();
+        let record = archived(&out);
+        assert_eq!((record.round, record.question), (1, 2));
+        assert!(record.buzzes.is_empty() && record.score_entries.is_empty());
+        assert_eq!((game.progress.round, game.progress.question), (2, 1));
+        assert_eq!(game.progress.history.len(), 2);
+    }
+
+    #[test]
+    fn round_totals_are_kept_per_round() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        add_points(&mut game, host_id, alice, 10);
+        add_points(&mut game, host_id, bob, 4);
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::NextRound { game_code: CODE },
+        )
+        .unwrap();
+        assert_eq!(game.round_scores(&alice), [10, 0]);
+
+        add_points(&mut game, host_id, alice, 5);
+        add_points(&mut game, host_id, alice, -3);
+        assert_eq!(game.round_scores(&alice), [10, 2]);
+        assert_eq!(game.round_scores(&bob), [4, 0]);
+        assert_eq!(game.scores[&alice], 12);
+
+        let team = ClientToServer::CreateTeam {
+            game_code: CODE,
+            name: "Red".to_string(),
+        };
+        host(&mut game, host_id, team).unwrap();
+        let red = game.teams[0].id;
+        for player_id in [alice, bob] {
+            game.set_team(player_id, Some(red));
+        }
+        assert_eq!(game.team_round_scores(&red), [14, 2]);
+
+        // Undoing a change made in an earlier round takes it from that round
+        for _ in 0..3 {
+            host(&mut game, host_id, ClientToServer::Undo { game_code: CODE }).unwrap();
+        }
+        assert_eq!(game.round_scores(&alice), [10, 0]);
+        assert_eq!(game.round_scores(&bob), [0, 0]);
+        host(&mut game, host_id, ClientToServer::Redo { game_code: CODE }).unwrap();
+        assert_eq!(game.round_scores(&bob), [4, 0]);
+    }
+}
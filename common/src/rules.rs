@@ -0,0 +1,1340 @@
+use std::fmt;
+
+use uuid::Uuid;
+
+use super::*;
+
+/// Everything `GameState::apply` needs that is not part of the game itself.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApplyContext {
+    pub now_ms: u64,
+    // Clock estimate of the sending connection, if one is known
+    pub clock: Option<ClockEstimate>,
+    pub buzz_fairness_window_ms: u64,
+}
+
+/// A rule violation; the game state is left untouched when one is returned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameError {
+    EmptyName,
+    NameTaken(String),
+    AlreadyJoined,
+    SessionExpired,
+    NotHost,
+    NotPlayer,
+    Locked,
+    AlreadyBuzzed,
+    LockedOut,
+    // Seconds until the player may buzz again
+    FalseStart(u64),
+    NobodyBuzzed,
+    QueueFull,
+    InvalidRules(&'static str),
+    TeammateBuzzed,
+    EmptyTeamName,
+    TeamNameTaken(String),
+    UnknownTeam,
+    UnknownPlayer,
+    InvalidQuestions(Vec<RowError>),
+    NoQuestion,
+    InvalidBoard(&'static str),
+    InvalidChoices(&'static str),
+    NoChoices,
+    AlreadyAnswered,
+    NoAnswers,
+    InvalidAnswer(&'static str),
+    NoWagers,
+    // The most the player may bet
+    InvalidWager(i32),
+    NothingToUndo,
+    NothingToRedo,
+    ScoreOutOfRange,
+    InvalidTimestamp,
+    IdMismatch,
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyName => write!(f, "Player name cannot be empty."),
+            Self::NameTaken(name) => write!(f, "Player name '{}' is already taken.", name),
+            Self::AlreadyJoined => write!(f, "You are already in this game."),
+            Self::SessionExpired => write!(f, "Your session has expired, please join again."),
+            Self::NotHost => write!(f, "Only the host can do that."),
+            Self::NotPlayer => write!(f, "Only players can do that."),
+            Self::Locked => write!(f, "Buzzers are locked."),
+            Self::AlreadyBuzzed => write!(f, "You already buzzed."),
+            Self::LockedOut => write!(f, "You already answered this question."),
+            Self::FalseStart(secs) => write!(
+                f,
+                "You buzzed before the buzzers were unlocked; wait {} more seconds.",
+                secs
+            ),
+            Self::NobodyBuzzed => write!(f, "Nobody has buzzed in yet."),
+            Self::QueueFull => write!(f, "Too many players have buzzed already."),
+            Self::InvalidRules(reason) => write!(f, "{}", reason),
+            Self::TeammateBuzzed => write!(f, "A teammate already buzzed."),
+            Self::EmptyTeamName => write!(f, "Team name cannot be empty."),
+            Self::TeamNameTaken(name) => write!(f, "Team name '{}' is already taken.", name),
+            Self::UnknownTeam => write!(f, "There is no such team."),
+            Self::UnknownPlayer => write!(f, "There is no such player."),
+            Self::InvalidQuestions(errors) => {
+                let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "The question set has problems: {}", errors.join("; "))
+            }
+            Self::NoQuestion => write!(f, "There is no such question."),
+            Self::InvalidBoard(reason) => write!(f, "{}", reason),
+            Self::InvalidChoices(reason) => write!(f, "{}", reason),
+            Self::NoChoices => write!(f, "There is no open multiple-choice question."),
+            Self::AlreadyAnswered => write!(f, "You have already answered this question."),
+            Self::NoAnswers => write!(f, "Answers are not being collected right now."),
+            Self::InvalidAnswer(reason) => write!(f, "{}", reason),
+            Self::NoWagers => write!(f, "Wagers are not being taken right now."),
+            Self::InvalidWager(max) => write!(f, "Your wager must be between 0 and {}.", max),
+            Self::NothingToUndo => write!(f, "There is no score change to undo."),
+            Self::NothingToRedo => write!(f, "There is no score change to redo."),
+            Self::ScoreOutOfRange => write!(
+                f,
+                "Scores must stay between {} and {}.",
+                -MAX_SCORE, MAX_SCORE
+            ),
+            Self::InvalidTimestamp => write!(f, "Your clock is too far off to buzz."),
+            Self::IdMismatch => write!(f, "You cannot act on behalf of another player."),
+        }
+    }
+}
+
+impl GameError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::EmptyName => ErrorCode::EmptyName,
+            Self::NameTaken(_) => ErrorCode::NameTaken,
+            Self::AlreadyJoined => ErrorCode::AlreadyJoined,
+            Self::SessionExpired => ErrorCode::SessionExpired,
+            Self::NotHost => ErrorCode::NotHost,
+            Self::NotPlayer => ErrorCode::NotPlayer,
+            Self::Locked => ErrorCode::Locked,
+            Self::AlreadyBuzzed => ErrorCode::AlreadyBuzzed,
+            Self::LockedOut => ErrorCode::LockedOut,
+            Self::FalseStart(_) => ErrorCode::FalseStart,
+            Self::NobodyBuzzed => ErrorCode::NobodyBuzzed,
+            Self::QueueFull => ErrorCode::QueueFull,
+            Self::InvalidRules(_) => ErrorCode::InvalidRules,
+            Self::TeammateBuzzed => ErrorCode::TeammateBuzzed,
+            Self::EmptyTeamName => ErrorCode::EmptyTeamName,
+            Self::TeamNameTaken(_) => ErrorCode::TeamNameTaken,
+            Self::UnknownTeam => ErrorCode::UnknownTeam,
+            Self::UnknownPlayer => ErrorCode::UnknownPlayer,
+            Self::InvalidQuestions(_) => ErrorCode::InvalidQuestions,
+            Self::NoQuestion => ErrorCode::NoQuestion,
+            Self::InvalidBoard(_) => ErrorCode::InvalidBoard,
+            Self::InvalidChoices(_) => ErrorCode::InvalidChoices,
+            Self::NoChoices => ErrorCode::NoChoices,
+            Self::AlreadyAnswered => ErrorCode::AlreadyAnswered,
+            Self::NoAnswers => ErrorCode::NoAnswers,
+            Self::InvalidAnswer(_) => ErrorCode::InvalidAnswer,
+            Self::NoWagers => ErrorCode::NoWagers,
+            Self::InvalidWager(_) => ErrorCode::InvalidWager,
+            Self::NothingToUndo => ErrorCode::NothingToUndo,
+            Self::NothingToRedo => ErrorCode::NothingToRedo,
+            Self::ScoreOutOfRange => ErrorCode::ScoreOutOfRange,
+            Self::InvalidTimestamp => ErrorCode::InvalidTimestamp,
+            Self::IdMismatch => ErrorCode::IdMismatch,
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+/// What the transport has to do after a state change, in order.
+#[derive(Debug)]
+pub enum Outbound {
+    /// Send a message to a single member of the game
+    Send(Uuid, ServerToClient),
+    /// Send a message to every member of the game
+    Broadcast(ServerToClient),
+    /// The sending connection now speaks for this player
+    Bind(Uuid),
+    ScheduleCountdownExpiry(u64),
+    CancelCountdownExpiry,
+    ScheduleAnswerExpiry(u64),
+    CancelAnswerExpiry,
+    /// Close the game unless the host comes back in time
+    StartHostGrace,
+    CancelHostGrace,
+}
+
+impl GameState {
+    pub fn new(host_id: Uuid, now_ms: u64) -> Self {
+        let mut game = Self::default();
+        game.record(now_ms, GameEvent::GameCreated { host_id });
+        game
+    }
+
+    /// Applies one client message sent by `actor` and returns what has to be
+    /// sent or scheduled as a result. Nothing here touches sockets or clocks,
+    /// so the server, the frontend and tests can all drive the same rules.
+    pub fn apply(
+        &mut self,
+        actor: Uuid,
+        msg: ClientToServer,
+        ctx: &ApplyContext,
+    ) -> Result<Vec<Outbound>, GameError> {
+        self.check_permission(actor, msg.permission())?;
+
+        let mut out = Vec::new();
+        match msg {
+            // Handled by the transport before a game is involved
+            ClientToServer::CreateGame
+            | ClientToServer::Hello { .. }
+            | ClientToServer::ClockPong { .. } => {}
+            ClientToServer::JoinGame {
+                player_name,
+                team_name,
+                ..
+            } => {
+                // Joining again would reset the score and the join order
+                if self.players.contains_key(&actor) {
+                    return Err(GameError::AlreadyJoined);
+                }
+                let player_name = player_name.trim().to_string();
+                if player_name.is_empty() {
+                    return Err(GameError::EmptyName);
+                }
+                if self.players.iter().any(|p| p.name() == player_name) {
+                    return Err(GameError::NameTaken(player_name));
+                }
+                let team = team_name
+                    .filter(|name| !name.trim().is_empty())
+                    .map(|name| self.choose_team(&name, self.rules.players_create_teams))
+                    .transpose()?;
+
+                self.record(
+                    ctx.now_ms,
+                    GameEvent::PlayerJoined {
+                        player_id: actor,
+                        player_name: player_name.clone(),
+                    },
+                );
+                let session_token = self.issue_session_token(actor);
+
+                out.push(Outbound::Bind(actor));
+                out.push(Outbound::Send(
+                    actor,
+                    ServerToClient::GameJoined {
+                        player_id: actor,
+                        player_name: player_name.clone(),
+                        session_token,
+                        game_state: self.to_json(),
+                    },
+                ));
+                out.push(self.delta(GameDelta::PlayerJoined {
+                    player_id: actor,
+                    player_name,
+                }));
+                if let Some(team) = team {
+                    out.extend(self.join_team(ctx.now_ms, actor, team));
+                }
+            }
+            ClientToServer::Rejoin { game_code, token } => {
+                let player_id = self
+                    .session_tokens
+                    .get(&token)
+                    .copied()
+                    .ok_or(GameError::SessionExpired)?;
+
+                out.push(Outbound::Bind(player_id));
+                if player_id == self.host_id {
+                    self.record(ctx.now_ms, GameEvent::HostReconnected);
+                    out.push(Outbound::CancelHostGrace);
+                    out.push(Outbound::Send(
+                        player_id,
+                        ServerToClient::GameCreated {
+                            game_code,
+                            player_id,
+                            host_token: token,
+                            game_state: self.to_json(),
+                        },
+                    ));
+                    if !self.question_bank.is_empty() {
+                        out.push(Outbound::Send(
+                            player_id,
+                            ServerToClient::QuestionBank {
+                                questions: self.question_bank.clone(),
+                            },
+                        ));
+                    }
+                    out.push(Outbound::Send(player_id, self.records_message()));
+                    out.extend(self.resend_answer_texts());
+                    out.extend(self.resend_wager_amounts());
+                    out.push(Outbound::Broadcast(ServerToClient::HostReconnected));
+                    out.push(self.state_update());
+                    return Ok(out);
+                }
+
+                let player_name = self
+                    .players
+                    .get(&player_id)
+                    .map(|p| p.name().to_owned())
+                    .ok_or(GameError::SessionExpired)?;
+
+                // Score, join order and buzz position are all keyed by the player id,
+                // so re-binding the connection is enough to restore them.
+                self.record(ctx.now_ms, GameEvent::PlayerReconnected { player_id });
+                out.push(Outbound::Send(
+                    player_id,
+                    ServerToClient::GameJoined {
+                        player_id,
+                        player_name,
+                        session_token: token,
+                        game_state: self.to_json(),
+                    },
+                ));
+                out.push(self.state_update());
+            }
+            ClientToServer::Buzz {
+                player_id,
+                client_time_ms,
+                ..
+            } => {
+                // Only ever for oneself, whoever passes the message along
+                if player_id != actor {
+                    return Err(GameError::IdMismatch);
+                }
+                // Players answer a multiple-choice question with their pick instead
+                if self.choices_open() {
+                    return Err(GameError::Locked);
+                }
+                if self.buzzer_order.iter().any(|(id, _)| *id == player_id) {
+                    return Err(GameError::AlreadyBuzzed);
+                }
+                if self.locked_out.contains(&player_id) {
+                    return Err(GameError::LockedOut);
+                }
+                let remaining_ms = self.false_start_remaining_ms(&player_id, ctx.now_ms);
+                if remaining_ms > 0 {
+                    return Err(GameError::FalseStart(remaining_ms.div_ceil(1000)));
+                }
+                if let (Some(client_time_ms), Some(clock)) = (client_time_ms, ctx.clock)
+                    && !clock.is_plausible(client_time_ms, ctx.now_ms)
+                {
+                    return Err(GameError::InvalidTimestamp);
+                }
+                if self.is_false_start(client_time_ms, ctx.clock, ctx.now_ms) {
+                    out.extend(self.penalize_false_start(ctx.now_ms, player_id));
+                    return Ok(out);
+                }
+                if self.globally_locked {
+                    return Err(GameError::Locked);
+                }
+                if self.rules.one_buzz_per_team && self.teammate_buzzed(&player_id) {
+                    return Err(GameError::TeammateBuzzed);
+                }
+                if !self.rules.queue_has_room(self.buzzer_order.len()) {
+                    return Err(GameError::QueueFull);
+                }
+                let Some(player_name) = self.players.get(&player_id).map(|p| p.name().to_owned())
+                else {
+                    return Ok(out);
+                };
+
+                let timing = BuzzTiming::new(
+                    client_time_ms,
+                    ctx.clock,
+                    ctx.now_ms,
+                    ctx.buzz_fairness_window_ms,
+                );
+                let is_first_buzz = self.buzzer_order.is_empty();
+                let position = self.buzz_position(&timing, ctx.buzz_fairness_window_ms);
+                self.record(
+                    ctx.now_ms,
+                    GameEvent::Buzzed {
+                        player_id,
+                        player_name: player_name.clone(),
+                        position,
+                        timing,
+                    },
+                );
+                out.push(Outbound::Send(
+                    self.host_id,
+                    ServerToClient::PlayerBuzzed {
+                        player_id,
+                        player_name: player_name.clone(),
+                    },
+                ));
+                out.push(self.delta(GameDelta::Buzzed {
+                    player_id,
+                    player_name,
+                    position,
+                    timing,
+                }));
+
+                if is_first_buzz && self.rules.auto_lock_after_first_buzz {
+                    self.record(ctx.now_ms, GameEvent::Locked);
+                    out.push(self.delta(GameDelta::LockChanged { locked: true }));
+                }
+                if is_first_buzz && self.countdown.pause_on_buzz && self.countdown.running {
+                    self.record(ctx.now_ms, GameEvent::CountdownPaused);
+                    let remaining_ms = self.countdown.remaining_ms;
+                    out.push(Outbound::CancelCountdownExpiry);
+                    out.push(Outbound::Broadcast(ServerToClient::TimerPaused {
+                        remaining_ms,
+                    }));
+                    out.push(self.state_update());
+                }
+            }
+            ClientToServer::Lock { .. } => {
+                self.record(ctx.now_ms, GameEvent::Locked);
+                out.push(self.delta(GameDelta::LockChanged { locked: true }));
+            }
+            ClientToServer::Unlock { .. } => {
+                self.record(ctx.now_ms, GameEvent::Unlocked);
+                out.push(self.delta(GameDelta::LockChanged { locked: false }));
+            }
+            ClientToServer::Clear { .. } => {
+                self.record(ctx.now_ms, GameEvent::QueueCleared);
+                out.push(self.delta(GameDelta::QueueCleared));
+            }
+            ClientToServer::Judge { correct, .. } => {
+                out.extend(self.judge_head(ctx.now_ms, correct)?)
+            }
+            ClientToServer::UpdateScore {
+                player_id,
+                delta,
+                reason,
+                ..
+            } => {
+                self.check_score_change(&player_id, delta)?;
+                out.extend(self.change_score(ctx.now_ms, player_id, delta, None, reason));
+            }
+            ClientToServer::SetScore {
+                player_id,
+                score,
+                reason,
+                ..
+            } => {
+                if !score_in_range(score) {
+                    return Err(GameError::ScoreOutOfRange);
+                }
+                let current = self.scores.get(&player_id).copied().unwrap_or_default();
+                self.check_score_change(&player_id, score - current)?;
+                out.extend(self.change_score(
+                    ctx.now_ms,
+                    player_id,
+                    score - current,
+                    Some(score),
+                    reason,
+                ));
+            }
+            ClientToServer::Undo { .. } => {
+                let entry_id = self
+                    .score_ledger
+                    .undoable()
+                    .map(|entry| entry.id)
+                    .ok_or(GameError::NothingToUndo)?;
+                self.record(ctx.now_ms, GameEvent::ScoreUndone { entry_id });
+                out.push(self.revert_delta(entry_id, true));
+            }
+            ClientToServer::Redo { .. } => {
+                let entry_id = self
+                    .score_ledger
+                    .redoable()
+                    .map(|entry| entry.id)
+                    .ok_or(GameError::NothingToRedo)?;
+                self.record(ctx.now_ms, GameEvent::ScoreRedone { entry_id });
+                out.push(self.revert_delta(entry_id, false));
+            }
+            ClientToServer::SetRules { rules, .. } => {
+                rules.validate().map_err(GameError::InvalidRules)?;
+                self.record(ctx.now_ms, GameEvent::RulesChanged { rules });
+                out.push(self.state_update());
+            }
+            ClientToServer::NextQuestion { .. } | ClientToServer::NextRound { .. } => {
+                let new_round = matches!(msg, ClientToServer::NextRound { .. });
+                out.push(self.archive_question(ctx.now_ms, new_round));
+                // On the board the host picks the next clue instead
+                if let Some(index) = self.current_question.map(|i| i + 1)
+                    && index < self.question_bank.len()
+                    && self.board.is_none()
+                {
+                    self.record(ctx.now_ms, GameEvent::QuestionSelected { index });
+                }
+                out.push(Outbound::CancelCountdownExpiry);
+                out.push(self.state_update());
+            }
+            ClientToServer::ImportQuestions { questions, .. } => {
+                let questions =
+                    Self::check_import(questions).map_err(GameError::InvalidQuestions)?;
+                let count = questions.len();
+                self.question_bank = questions;
+                self.record(ctx.now_ms, GameEvent::QuestionsImported { count });
+                out.push(Outbound::Send(
+                    actor,
+                    ServerToClient::QuestionBank {
+                        questions: self.question_bank.clone(),
+                    },
+                ));
+                out.push(self.state_update());
+            }
+            ClientToServer::SelectQuestion { index, .. } => {
+                if index >= self.question_bank.len() {
+                    return Err(GameError::NoQuestion);
+                }
+                self.record(ctx.now_ms, GameEvent::QuestionSelected { index });
+                out.push(self.state_update());
+            }
+            ClientToServer::RevealQuestion { .. } | ClientToServer::RevealAnswer { .. } => {
+                let with_answer = matches!(msg, ClientToServer::RevealAnswer { .. });
+                let index = self.current_question.ok_or(GameError::NoQuestion)?;
+                let question = self
+                    .revealed_question(index, with_answer)
+                    .ok_or(GameError::NoQuestion)?;
+                self.record(ctx.now_ms, GameEvent::QuestionRevealed { question });
+                out.push(self.state_update());
+            }
+            ClientToServer::OpenBoard { .. } => out.extend(self.open_board(ctx.now_ms)?),
+            ClientToServer::CloseBoard { .. } => {
+                if self.board.is_none() {
+                    return Err(GameError::InvalidBoard("There is no board to close."));
+                }
+                self.record(ctx.now_ms, GameEvent::BoardClosed);
+                out.push(self.state_update());
+            }
+            ClientToServer::SelectClue { index, .. } => {
+                out.extend(self.select_clue(ctx.now_ms, index)?)
+            }
+            ClientToServer::OpenChoices {
+                options, correct, ..
+            } => out.extend(self.open_choices(ctx.now_ms, options, correct)?),
+            ClientToServer::SubmitChoice { choice, .. } => {
+                out.extend(self.submit_choice(ctx.now_ms, actor, choice)?)
+            }
+            ClientToServer::CloseChoices { .. } => {
+                if !self.choices_open() {
+                    return Err(GameError::NoChoices);
+                }
+                out.extend(self.close_choices(ctx.now_ms));
+            }
+            ClientToServer::OpenAnswers { .. } => out.extend(self.open_answers(ctx.now_ms)),
+            ClientToServer::SubmitAnswer { text, .. } => {
+                out.extend(self.submit_answer(ctx.now_ms, actor, text)?)
+            }
+            ClientToServer::CloseAnswers { .. } => out.extend(self.close_answers(ctx.now_ms)?),
+            ClientToServer::ShowAnswers { .. } => out.extend(self.show_answers(ctx.now_ms)?),
+            ClientToServer::GradeAnswers {
+                player_ids, grade, ..
+            } => out.extend(self.grade_answers(ctx.now_ms, &player_ids, grade)?),
+            ClientToServer::OpenWagers { .. } => out.extend(self.open_wagers(ctx.now_ms)),
+            ClientToServer::PlaceWager { amount, .. } => {
+                out.extend(self.place_wager(ctx.now_ms, actor, amount)?)
+            }
+            ClientToServer::LockWagers { .. } => {
+                if !self.wagers_open() {
+                    return Err(GameError::NoWagers);
+                }
+                out.extend(self.lock_wagers(ctx.now_ms));
+            }
+            ClientToServer::RevealWagers { .. } => out.extend(self.show_wagers(ctx.now_ms)?),
+            ClientToServer::ResolveWager {
+                player_id, correct, ..
+            } => out.extend(self.resolve_wager(ctx.now_ms, player_id, correct)?),
+            ClientToServer::CreateTeam { name, .. } => match self.choose_team(&name, true)? {
+                TeamChoice::Existing(_) => return Err(GameError::TeamNameTaken(name)),
+                TeamChoice::New(name) => {
+                    out.push(self.create_team(ctx.now_ms, Uuid::new_v4(), name))
+                }
+            },
+            ClientToServer::RemoveTeam { team_id, .. } => {
+                if !self.teams.iter().any(|team| team.id == team_id) {
+                    return Err(GameError::UnknownTeam);
+                }
+                self.record(ctx.now_ms, GameEvent::TeamRemoved { team_id });
+                out.push(self.state_update());
+            }
+            ClientToServer::AssignTeam {
+                player_id, team_id, ..
+            } => {
+                self.check_player(&player_id)?;
+                if let Some(team_id) = team_id
+                    && !self.teams.iter().any(|team| team.id == team_id)
+                {
+                    return Err(GameError::UnknownTeam);
+                }
+                out.push(self.assign_team(ctx.now_ms, player_id, team_id));
+            }
+            ClientToServer::ChooseTeam { team_name, .. } => {
+                let team = self.choose_team(&team_name, self.rules.players_create_teams)?;
+                out.extend(self.join_team(ctx.now_ms, actor, team));
+            }
+            ClientToServer::RequestSnapshot { .. } => {
+                out.push(Outbound::Send(
+                    actor,
+                    ServerToClient::GameStateUpdate {
+                        game_state: self.to_json(),
+                    },
+                ));
+                if actor == self.host_id {
+                    out.push(Outbound::Send(actor, self.records_message()));
+                }
+            }
+            ClientToServer::StartCountdown { time_limit, .. } => {
+                if time_limit > 0 {
+                    self.record(ctx.now_ms, GameEvent::CountdownStarted { time_limit });
+                    let deadline_ms = self.countdown.deadline_ms.unwrap_or(ctx.now_ms);
+                    out.push(Outbound::ScheduleCountdownExpiry(deadline_ms));
+                    out.push(Outbound::Broadcast(ServerToClient::CountdownStarted {
+                        time_limit,
+                        deadline_ms,
+                    }));
+                    out.push(self.state_update());
+                }
+            }
+            ClientToServer::PauseTimer { .. } => {
+                if self.countdown.running {
+                    self.record(ctx.now_ms, GameEvent::CountdownPaused);
+                    let remaining_ms = self.countdown.remaining_ms;
+                    out.push(Outbound::CancelCountdownExpiry);
+                    out.push(Outbound::Broadcast(ServerToClient::TimerPaused {
+                        remaining_ms,
+                    }));
+                    out.push(self.state_update());
+                }
+            }
+            ClientToServer::ResumeTimer { .. } => {
+                if !self.countdown.running && self.countdown.remaining_ms > 0 {
+                    self.record(ctx.now_ms, GameEvent::CountdownResumed);
+                    let deadline_ms = self.countdown.deadline_ms.unwrap_or(ctx.now_ms);
+                    out.push(Outbound::ScheduleCountdownExpiry(deadline_ms));
+                    out.push(Outbound::Broadcast(ServerToClient::TimerResumed {
+                        deadline_ms,
+                    }));
+                    out.push(self.state_update());
+                }
+            }
+            ClientToServer::SetCountdownOptions {
+                auto_lock,
+                pause_on_buzz,
+                ..
+            } => {
+                self.record(
+                    ctx.now_ms,
+                    GameEvent::CountdownOptionsChanged {
+                        auto_lock,
+                        pause_on_buzz,
+                    },
+                );
+                out.push(self.state_update());
+            }
+        }
+        out.extend(self.sync_answer_clock(ctx.now_ms));
+        Ok(out)
+    }
+
+    /// Scores the answer of the player at the head of the buzzer queue
+    pub(crate) fn judge_head(
+        &mut self,
+        now_ms: u64,
+        correct: bool,
+    ) -> Result<Vec<Outbound>, GameError> {
+        let player_id = self
+            .buzzer_order
+            .front()
+            .map(|(id, _)| *id)
+            .ok_or(GameError::NobodyBuzzed)?;
+        // A clue on the board is worth its value either way
+        let clue_value = self.active_clue_value();
+        let (delta, reason) = if correct {
+            (
+                clue_value.unwrap_or_else(|| self.question_points()),
+                "Correct answer",
+            )
+        } else {
+            (
+                -clue_value.unwrap_or(self.rules.penalty_wrong),
+                "Wrong answer",
+            )
+        };
+        let mut out = Vec::new();
+        if delta != 0 {
+            out.extend(self.change_score(now_ms, player_id, delta, None, Some(reason.to_string())));
+        }
+        self.record(now_ms, GameEvent::AnswerJudged { player_id, correct });
+        out.push(self.delta(GameDelta::AnswerJudged { player_id, correct }));
+        // With auto-lock nobody else could buzz yet, so the question is open again
+        if !correct && self.rules.auto_lock_after_first_buzz && self.buzzer_order.is_empty() {
+            self.record(now_ms, GameEvent::Unlocked);
+            out.push(self.delta(GameDelta::LockChanged { locked: false }));
+        }
+        Ok(out)
+    }
+
+    /// A member's connection dropped. Players keep their seat; the host gets
+    /// `host_grace_period_secs` to come back before the game is closed.
+    pub fn disconnect(
+        &mut self,
+        player_id: Uuid,
+        host_grace_period_secs: u64,
+        now_ms: u64,
+    ) -> Vec<Outbound> {
+        let mut out = Vec::new();
+        if player_id == self.host_id {
+            self.record(now_ms, GameEvent::HostDisconnected);
+            out.push(Outbound::StartHostGrace);
+            out.push(Outbound::Broadcast(ServerToClient::HostDisconnected {
+                grace_period_secs: host_grace_period_secs,
+            }));
+        } else if self.players.contains_key(&player_id) {
+            self.record(now_ms, GameEvent::PlayerDisconnected { player_id });
+        }
+        out.push(self.state_update());
+        out
+    }
+
+    /// Every connection is gone at once, e.g. after the game was reloaded from
+    /// storage. Everyone has to rejoin, and running timers are re-armed.
+    pub fn connections_lost(&mut self, now_ms: u64) -> Vec<Outbound> {
+        let mut out = Vec::new();
+        let connected: Vec<Uuid> = self
+            .players
+            .iter()
+            .filter(|p| matches!(p.value(), Actor::Player { .. }))
+            .map(|p| p.id())
+            .collect();
+        for player_id in connected {
+            self.record(now_ms, GameEvent::PlayerDisconnected { player_id });
+        }
+        if self.host_connected {
+            self.record(now_ms, GameEvent::HostDisconnected);
+        }
+        out.push(Outbound::StartHostGrace);
+        if self.countdown.running
+            && let Some(deadline_ms) = self.countdown.deadline_ms
+        {
+            out.push(Outbound::ScheduleCountdownExpiry(deadline_ms));
+        }
+        if let Some(clock) = self.answer_clock.as_ref().filter(|clock| !clock.expired) {
+            out.push(Outbound::ScheduleAnswerExpiry(clock.deadline_ms));
+        }
+        out
+    }
+
+    /// The countdown that was due at `deadline_ms` ran out.
+    pub fn countdown_elapsed(&mut self, deadline_ms: u64) -> Vec<Outbound> {
+        if !self.countdown.running || self.countdown.deadline_ms != Some(deadline_ms) {
+            return Vec::new();
+        }
+        self.record(deadline_ms, GameEvent::CountdownExpired { deadline_ms });
+        let mut out = vec![
+            Outbound::Broadcast(ServerToClient::TimerExpired),
+            self.state_update(),
+        ];
+        out.extend(self.close_choices(deadline_ms));
+        out.extend(self.lock_wagers(deadline_ms));
+        out
+    }
+
+    /// Fails unless `player_id` is a player of this game rather than the host
+    pub(crate) fn check_player(&self, player_id: &Uuid) -> Result<(), GameError> {
+        if *player_id == self.host_id || !self.players.contains_key(player_id) {
+            return Err(GameError::UnknownPlayer);
+        }
+        Ok(())
+    }
+
+    fn check_permission(&self, actor: Uuid, permission: Permission) -> Result<(), GameError> {
+        let is_host = actor == self.host_id;
+        match permission {
+            Permission::Anyone => Ok(()),
+            Permission::Host if is_host => Ok(()),
+            Permission::Host => Err(GameError::NotHost),
+            Permission::Player if !is_host && self.players.contains_key(&actor) => Ok(()),
+            Permission::Player => Err(GameError::NotPlayer),
+            Permission::Member if self.players.contains_key(&actor) => Ok(()),
+            Permission::Member => Err(GameError::NotPlayer),
+        }
+    }
+
+    /// Records a score change in the ledger, linked to the player's buzz if
+    /// they are in the buzzer order. A change the rules made stops at the
+    /// score limit. Everyone learns the new score; only the host gets the
+    /// ledger entry with its reason.
+    pub(crate) fn change_score(
+        &mut self,
+        now_ms: u64,
+        player_id: Uuid,
+        delta: i32,
+        set_to: Option<i32>,
+        reason: Option<String>,
+    ) -> Vec<Outbound> {
+        let reason = reason
+            .map(|reason| reason.trim().to_string())
+            .filter(|reason| !reason.is_empty());
+        let current = self.scores.get(&player_id).copied().unwrap_or_default();
+        let delta = current
+            .saturating_add(delta)
+            .clamp(-MAX_SCORE, MAX_SCORE)
+            .saturating_sub(current);
+        let buzz_position = self
+            .buzzer_order
+            .iter()
+            .position(|(id, _)| *id == player_id);
+        self.record(
+            now_ms,
+            GameEvent::ScoreChanged {
+                player_id,
+                delta,
+                set_to,
+                reason,
+                buzz_position,
+            },
+        );
+        let score = self.scores.get(&player_id).copied().unwrap_or_default();
+        let round_scores = self.round_scores(&player_id);
+        let mut out = Vec::new();
+        if let Some(entry) = self.score_ledger.entries.last().cloned() {
+            out.push(Outbound::Send(
+                self.host_id,
+                ServerToClient::ScoreRecorded { entry },
+            ));
+        }
+        out.push(self.delta(GameDelta::ScoreChanged {
+            player_id,
+            score,
+            round_scores,
+        }));
+        out
+    }
+
+    /// Numbers a change and broadcasts the full state, for changes that have
+    /// no delta of their own.
+    pub(crate) fn state_update(&mut self) -> Outbound {
+        self.seq += 1;
+        Outbound::Broadcast(ServerToClient::GameStateUpdate {
+            game_state: self.to_json(),
+        })
+    }
+
+    pub(crate) fn delta(&mut self, delta: GameDelta) -> Outbound {
+        self.seq += 1;
+        Outbound::Broadcast(ServerToClient::Delta {
+            seq: self.seq,
+            delta,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    #[test]
+    fn join_adds_the_player_with_a_zero_score() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "  Alice ");
+
+        assert_eq!(game.players.get(&alice).unwrap().name(), "Alice");
+        assert_eq!(game.player_join_order, vec![host_id, alice]);
+        assert_eq!(game.scores.get(&alice).copied().unwrap_or_default(), 0);
+    }
+
+    #[test]
+    fn join_rejects_bad_names() {
+        let (mut game, _) = new_game();
+        join(&mut game, "Alice");
+        let msg = |name: &str| ClientToServer::JoinGame {
+            game_code: CODE,
+            player_name: name.to_string(),
+            team_name: None,
+        };
+
+        assert_eq!(
+            game.apply(Uuid::new_v4(), msg("   "), &ctx(20))
+                .unwrap_err(),
+            GameError::EmptyName
+        );
+        assert_eq!(
+            game.apply(Uuid::new_v4(), msg("Alice"), &ctx(20))
+                .unwrap_err(),
+            GameError::NameTaken("Alice".to_string())
+        );
+    }
+
+    #[test]
+    fn join_rejects_existing_members() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::UpdateScore {
+                game_code: CODE,
+                player_id: alice,
+                delta: 30,
+                reason: None,
+            },
+        )
+        .unwrap();
+
+        for actor in [alice, host_id] {
+            let result = game.apply(
+                actor,
+                ClientToServer::JoinGame {
+                    game_code: CODE,
+                    player_name: "Again".to_string(),
+                    team_name: None,
+                },
+                &ctx(20),
+            );
+            assert_eq!(result.unwrap_err(), GameError::AlreadyJoined);
+        }
+        assert_eq!(game.scores[&alice], 30);
+        assert_eq!(game.player_join_order, vec![host_id, alice]);
+    }
+
+    #[test]
+    fn rejoin_keeps_score_and_buzz_position() {
+        let (mut game, host_id) = new_game();
+        let (alice, token) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        buzz(&mut game, bob, 50).unwrap();
+        buzz(&mut game, alice, 60).unwrap();
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::UpdateScore {
+                game_code: CODE,
+                player_id: alice,
+                delta: 5,
+                reason: None,
+            },
+        )
+        .unwrap();
+        game.disconnect(alice, 30, 70);
+
+        let out = game
+            .apply(
+                Uuid::new_v4(),
+                ClientToServer::Rejoin {
+                    game_code: CODE,
+                    token,
+                },
+                &ctx(80),
+            )
+            .unwrap();
+
+        assert!(matches!(out[0], Outbound::Bind(id) if id == alice));
+        assert_eq!(game.scores[&alice], 5);
+        assert_eq!(order(&game), vec![bob, alice]);
+    }
+
+    #[test]
+    fn rejoin_with_an_unknown_token_fails() {
+        let (mut game, _) = new_game();
+        let result = game.apply(
+            Uuid::new_v4(),
+            ClientToServer::Rejoin {
+                game_code: CODE,
+                token: Uuid::new_v4(),
+            },
+            &ctx(20),
+        );
+        assert_eq!(result.unwrap_err(), GameError::SessionExpired);
+    }
+
+    #[test]
+    fn buzzes_queue_in_arrival_order_once_each() {
+        let (mut game, _) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        let (carol, _) = join(&mut game, "Carol");
+
+        buzz(&mut game, bob, 50).unwrap();
+        buzz(&mut game, carol, 51).unwrap();
+        buzz(&mut game, alice, 52).unwrap();
+
+        assert_eq!(order(&game), vec![bob, carol, alice]);
+        assert_eq!(
+            buzz(&mut game, bob, 53).unwrap_err(),
+            GameError::AlreadyBuzzed
+        );
+    }
+
+    #[test]
+    fn an_earlier_press_within_the_fairness_window_goes_first() {
+        let (mut game, _) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        let ctx = |now_ms| ApplyContext {
+            now_ms,
+            clock: Some(ClockEstimate {
+                offset_ms: 0,
+                rtt_ms: 10,
+            }),
+            buzz_fairness_window_ms: 50,
+        };
+        let buzz_at = |player_id, client_time_ms| ClientToServer::Buzz {
+            game_code: CODE,
+            player_id,
+            client_time_ms: Some(client_time_ms),
+        };
+
+        game.apply(bob, buzz_at(bob, 100), &ctx(100)).unwrap();
+        game.apply(alice, buzz_at(alice, 90), &ctx(130)).unwrap();
+
+        assert_eq!(order(&game), vec![alice, bob]);
+    }
+
+    #[test]
+    fn buzzes_with_a_bogus_client_time_are_rejected() {
+        let (mut game, _) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let ctx = ApplyContext {
+            now_ms: 1_000_000,
+            clock: Some(ClockEstimate {
+                offset_ms: -5_000,
+                rtt_ms: 10,
+            }),
+            buzz_fairness_window_ms: 50,
+        };
+
+        for client_time_ms in [u64::MAX, 0] {
+            let msg = ClientToServer::Buzz {
+                game_code: CODE,
+                player_id: alice,
+                client_time_ms: Some(client_time_ms),
+            };
+            assert_eq!(
+                game.apply(alice, msg, &ctx).unwrap_err(),
+                GameError::InvalidTimestamp
+            );
+        }
+        assert!(game.buzzer_order.is_empty());
+    }
+
+    #[test]
+    fn only_players_may_buzz() {
+        let (mut game, host_id) = new_game();
+        assert_eq!(
+            buzz(&mut game, host_id, 50).unwrap_err(),
+            GameError::NotPlayer
+        );
+        assert_eq!(
+            buzz(&mut game, Uuid::new_v4(), 50).unwrap_err(),
+            GameError::NotPlayer
+        );
+    }
+
+    #[test]
+    fn players_may_only_buzz_for_themselves() {
+        let (mut game, _) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+
+        let result = game.apply(
+            bob,
+            ClientToServer::Buzz {
+                game_code: CODE,
+                player_id: alice,
+                client_time_ms: None,
+            },
+            &ctx(50),
+        );
+        assert_eq!(result.unwrap_err(), GameError::IdMismatch);
+        assert!(game.buzzer_order.is_empty());
+    }
+
+    #[test]
+    fn lock_stops_buzzes_until_unlocked() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+
+        host(&mut game, host_id, ClientToServer::Lock { game_code: CODE }).unwrap();
+        assert_eq!(buzz(&mut game, alice, 50).unwrap_err(), GameError::Locked);
+        assert!(game.buzzer_order.is_empty());
+
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::Unlock { game_code: CODE },
+        )
+        .unwrap();
+        buzz(&mut game, alice, 60).unwrap();
+        assert_eq!(order(&game), vec![alice]);
+    }
+
+    #[test]
+    fn only_the_host_may_lock() {
+        let (mut game, _) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let result = game.apply(alice, ClientToServer::Lock { game_code: CODE }, &ctx(50));
+        assert_eq!(result.unwrap_err(), GameError::NotHost);
+        assert!(!game.globally_locked);
+    }
+
+    #[test]
+    fn judging_scores_the_head_and_moves_the_queue_on() {
+        let (mut game, host_id) = new_game();
+        game.rules.penalty_wrong = 3;
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        buzz(&mut game, alice, 50).unwrap();
+        buzz(&mut game, bob, 51).unwrap();
+
+        let judge = |correct| ClientToServer::Judge {
+            game_code: CODE,
+            correct,
+        };
+        host(&mut game, host_id, judge(false)).unwrap();
+        assert_eq!(game.scores[&alice], -3);
+        assert_eq!(order(&game), vec![bob]);
+        assert_eq!(
+            buzz(&mut game, alice, 60).unwrap_err(),
+            GameError::LockedOut
+        );
+
+        host(&mut game, host_id, judge(true)).unwrap();
+        assert_eq!(game.scores[&bob], 10);
+        assert_eq!(
+            host(&mut game, host_id, judge(true)).unwrap_err(),
+            GameError::NobodyBuzzed
+        );
+    }
+
+    #[test]
+    fn undo_and_redo_revert_score_changes() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        buzz(&mut game, alice, 50).unwrap();
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::Judge {
+                game_code: CODE,
+                correct: true,
+            },
+        )
+        .unwrap();
+        assert_eq!(game.scores[&alice], 10);
+
+        host(&mut game, host_id, ClientToServer::Undo { game_code: CODE }).unwrap();
+        assert_eq!(game.scores[&alice], 0);
+        assert_eq!(
+            host(&mut game, host_id, ClientToServer::Undo { game_code: CODE }).unwrap_err(),
+            GameError::NothingToUndo
+        );
+
+        host(&mut game, host_id, ClientToServer::Redo { game_code: CODE }).unwrap();
+        assert_eq!(game.scores[&alice], 10);
+        assert_eq!(
+            host(&mut game, host_id, ClientToServer::Redo { game_code: CODE }).unwrap_err(),
+            GameError::NothingToRedo
+        );
+    }
+
+    #[test]
+    fn snapshots_leave_out_the_ledger_and_history() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        buzz(&mut game, alice, 50).unwrap();
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::Judge {
+                game_code: CODE,
+                correct: true,
+            },
+        )
+        .unwrap();
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::NextRound { game_code: CODE },
+        )
+        .unwrap();
+        host(&mut game, host_id, ClientToServer::Undo { game_code: CODE }).unwrap();
+
+        let json = serde_json::to_value(game.to_json()).unwrap();
+        assert!(json.get("score_ledger").is_none());
+        assert!(json["progress"].get("history").is_none());
+        assert_eq!(game.progress.history.len(), 1);
+
+        let snapshot: GameState = game.to_json().into();
+        assert_eq!(snapshot.round_scores(&alice), vec![0, 0]);
+        assert_eq!(snapshot.scores[&alice], 0);
+    }
+
+    #[test]
+    fn score_changes_out_of_range_are_rejected() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let update = |delta| ClientToServer::UpdateScore {
+            game_code: CODE,
+            player_id: alice,
+            delta,
+            reason: None,
+        };
+
+        host(&mut game, host_id, update(MAX_SCORE)).unwrap();
+        assert_eq!(
+            host(&mut game, host_id, update(1)).unwrap_err(),
+            GameError::ScoreOutOfRange
+        );
+        assert_eq!(
+            host(&mut game, host_id, update(i32::MIN)).unwrap_err(),
+            GameError::ScoreOutOfRange
+        );
+        assert_eq!(
+            host(
+                &mut game,
+                host_id,
+                ClientToServer::SetScore {
+                    game_code: CODE,
+                    player_id: alice,
+                    score: i32::MIN,
+                    reason: None,
+                },
+            )
+            .unwrap_err(),
+            GameError::ScoreOutOfRange
+        );
+        assert_eq!(game.scores[&alice], MAX_SCORE);
+    }
+
+    #[test]
+    fn only_players_can_be_scored() {
+        let (mut game, host_id) = new_game();
+        join(&mut game, "Alice");
+
+        for player_id in [host_id, Uuid::new_v4()] {
+            let update = ClientToServer::UpdateScore {
+                game_code: CODE,
+                player_id,
+                delta: 5,
+                reason: None,
+            };
+            let set = ClientToServer::SetScore {
+                game_code: CODE,
+                player_id,
+                score: 5,
+                reason: None,
+            };
+            for msg in [update, set] {
+                assert_eq!(
+                    host(&mut game, host_id, msg).unwrap_err(),
+                    GameError::UnknownPlayer
+                );
+            }
+            assert!(!game.scores.contains_key(&player_id));
+        }
+        assert!(game.score_ledger.entries.is_empty());
+    }
+
+    #[test]
+    fn only_the_host_gets_the_ledger_entry_of_a_score_change() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let out = host(
+            &mut game,
+            host_id,
+            ClientToServer::UpdateScore {
+                game_code: CODE,
+                player_id: alice,
+                delta: 5,
+                reason: Some("Nice try".to_string()),
+            },
+        )
+        .unwrap();
+
+        let entry = out.iter().find_map(|o| match o {
+            Outbound::Send(to, ServerToClient::ScoreRecorded { entry }) if *to == host_id => {
+                Some(entry)
+            }
+            _ => None,
+        });
+        assert_eq!(entry.unwrap().reason.as_deref(), Some("Nice try"));
+        for o in &out {
+            if let Outbound::Broadcast(message) = o {
+                let json = serde_json::to_string(message).unwrap();
+                assert!(!json.contains("Nice try"), "{}", json);
+            }
+        }
+    }
+
+    #[test]
+    fn scores_from_the_rules_stop_at_the_limit() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let rules = GameRules {
+            points_correct: MAX_SCORE,
+            ..GameRules::default()
+        };
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::SetRules {
+                game_code: CODE,
+                rules,
+            },
+        )
+        .unwrap();
+
+        for now_ms in [200, 300] {
+            buzz(&mut game, alice, now_ms).unwrap();
+            host(
+                &mut game,
+                host_id,
+                ClientToServer::Judge {
+                    game_code: CODE,
+                    correct: true,
+                },
+            )
+            .unwrap();
+            host(
+                &mut game,
+                host_id,
+                ClientToServer::Unlock { game_code: CODE },
+            )
+            .unwrap();
+        }
+        assert_eq!(game.scores[&alice], MAX_SCORE);
+        let deltas: Vec<i32> = game.score_ledger.entries.iter().map(|e| e.delta).collect();
+        assert_eq!(deltas, vec![MAX_SCORE, 0]);
+        assert_eq!(game.round_scores(&alice), vec![MAX_SCORE]);
+
+        let too_large = GameRules {
+            points_correct: MAX_SCORE + 1,
+            ..GameRules::default()
+        };
+        assert!(matches!(
+            host(
+                &mut game,
+                host_id,
+                ClientToServer::SetRules {
+                    game_code: CODE,
+                    rules: too_large
+                }
+            ),
+            Err(GameError::InvalidRules(_))
+        ));
+    }
+
+    #[test]
+    fn failed_messages_leave_the_state_untouched() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let seq = game.seq;
+        let events = game.events.len();
+
+        assert!(host(&mut game, host_id, ClientToServer::Undo { game_code: CODE }).is_err());
+        assert!(buzz(&mut game, host_id, 50).is_err());
+        host(&mut game, host_id, ClientToServer::Lock { game_code: CODE }).unwrap();
+        let seq_after_lock = game.seq;
+        let events_after_lock = game.events.len();
+        assert!(buzz(&mut game, alice, 60).is_err());
+
+        assert!(seq_after_lock > seq && events_after_lock > events);
+        assert_eq!(game.seq, seq_after_lock);
+        assert_eq!(game.events.len(), events_after_lock);
+    }
+}
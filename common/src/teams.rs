@@ -0,0 +1,351 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Team {
+    pub id: Uuid,
+    pub name: String,
+}
+
+/// Where a player asked to be placed
+pub(crate) enum TeamChoice {
+    Existing(Uuid),
+    New(String),
+}
+
+impl GameState {
+    pub fn team_of(&self, player_id: &Uuid) -> Option<&Team> {
+        let team_id = self.player_teams.get(player_id)?;
+        self.teams.iter().find(|team| team.id == *team_id)
+    }
+
+    /// Players of a team, in join order
+    pub fn team_members(&self, team_id: &Uuid) -> Vec<Uuid> {
+        self.player_join_order
+            .iter()
+            .filter(|player_id| self.player_teams.get(player_id) == Some(team_id))
+            .copied()
+            .collect()
+    }
+
+    /// The sum of the scores of a team's members
+    pub fn team_score(&self, team_id: &Uuid) -> i32 {
+        self.team_members(team_id)
+            .iter()
+            .map(|player_id| self.scores.get(player_id).copied().unwrap_or_default())
+            .sum()
+    }
+
+    /// Whether a teammate of `player_id` already had their turn at the
+    /// current question
+    pub fn teammate_buzzed(&self, player_id: &Uuid) -> bool {
+        let Some(team_id) = self.player_teams.get(player_id) else {
+            return false;
+        };
+        self.buzzer_order
+            .iter()
+            .map(|(id, _)| id)
+            .chain(self.locked_out.iter())
+            .any(|id| id != player_id && self.player_teams.get(id) == Some(team_id))
+    }
+
+    /// Finds the team called `name`, or checks that a new team may be created
+    /// under that name.
+    pub(crate) fn choose_team(
+        &self,
+        name: &str,
+        may_create: bool,
+    ) -> Result<TeamChoice, GameError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(GameError::EmptyTeamName);
+        }
+        if let Some(team) = self
+            .teams
+            .iter()
+            .find(|team| team.name.eq_ignore_ascii_case(name))
+        {
+            return Ok(TeamChoice::Existing(team.id));
+        }
+        if !may_create {
+            return Err(GameError::UnknownTeam);
+        }
+        Ok(TeamChoice::New(name.to_string()))
+    }
+
+    /// Records the events that put `player_id` into the chosen team.
+    pub(crate) fn join_team(
+        &mut self,
+        now_ms: u64,
+        player_id: Uuid,
+        choice: TeamChoice,
+    ) -> Vec<Outbound> {
+        let mut out = Vec::new();
+        let team_id = match choice {
+            TeamChoice::Existing(team_id) => team_id,
+            TeamChoice::New(name) => {
+                let team_id = Uuid::new_v4();
+                out.push(self.create_team(now_ms, team_id, name));
+                team_id
+            }
+        };
+        out.push(self.assign_team(now_ms, player_id, Some(team_id)));
+        out
+    }
+
+    pub(crate) fn create_team(&mut self, now_ms: u64, team_id: Uuid, name: String) -> Outbound {
+        self.record(
+            now_ms,
+            GameEvent::TeamCreated {
+                team_id,
+                name: name.clone(),
+            },
+        );
+        self.delta(GameDelta::TeamCreated { team_id, name })
+    }
+
+    pub(crate) fn assign_team(
+        &mut self,
+        now_ms: u64,
+        player_id: Uuid,
+        team_id: Option<Uuid>,
+    ) -> Outbound {
+        self.record(now_ms, GameEvent::TeamAssigned { player_id, team_id });
+        self.delta(GameDelta::TeamAssigned { player_id, team_id })
+    }
+
+    pub(crate) fn add_team(&mut self, team_id: Uuid, name: &str) {
+        if !self.teams.iter().any(|team| team.id == team_id) {
+            self.teams.push(Team {
+                id: team_id,
+                name: name.to_string(),
+            });
+        }
+    }
+
+    pub(crate) fn remove_team(&mut self, team_id: &Uuid) {
+        self.teams.retain(|team| team.id != *team_id);
+        self.player_teams.retain(|_, id| id != team_id);
+    }
+
+    pub(crate) fn set_team(&mut self, player_id: Uuid, team_id: Option<Uuid>) {
+        match team_id {
+            Some(team_id) => self.player_teams.insert(player_id, team_id),
+            None => self.player_teams.remove(&player_id),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    fn new_team(game: &mut GameState, host_id: Uuid, name: &str) -> Uuid {
+        host(
+            game,
+            host_id,
+            ClientToServer::CreateTeam {
+                game_code: CODE,
+                name: name.to_string(),
+            },
+        )
+        .unwrap();
+        game.teams.iter().find(|team| team.name == name).unwrap().id
+    }
+
+    fn assign(
+        game: &mut GameState,
+        host_id: Uuid,
+        player_id: Uuid,
+        team_id: Option<Uuid>,
+    ) -> Result<Vec<Outbound>, GameError> {
+        host(
+            game,
+            host_id,
+            ClientToServer::AssignTeam {
+                game_code: CODE,
+                player_id,
+                team_id,
+            },
+        )
+    }
+
+    fn choose(
+        game: &mut GameState,
+        player_id: Uuid,
+        name: &str,
+    ) -> Result<Vec<Outbound>, GameError> {
+        game.apply(
+            player_id,
+            ClientToServer::ChooseTeam {
+                game_code: CODE,
+                team_name: name.to_string(),
+            },
+            &ctx(100),
+        )
+    }
+
+    #[test]
+    fn only_one_member_of_a_team_buzzes_per_question() {
+        let (mut game, host_id) = new_game();
+        let rules = GameRules {
+            one_buzz_per_team: true,
+            ..GameRules::default()
+        };
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::SetRules {
+                game_code: CODE,
+                rules,
+            },
+        )
+        .unwrap();
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        let (carol, _) = join(&mut game, "Carol");
+        let (dave, _) = join(&mut game, "Dave");
+        let red = new_team(&mut game, host_id, "Red");
+        let blue = new_team(&mut game, host_id, "Blue");
+        assign(&mut game, host_id, alice, Some(red)).unwrap();
+        assign(&mut game, host_id, bob, Some(red)).unwrap();
+        assign(&mut game, host_id, carol, Some(blue)).unwrap();
+
+        buzz(&mut game, alice, 200).unwrap();
+        assert!(matches!(
+            buzz(&mut game, bob, 210),
+            Err(GameError::TeammateBuzzed)
+        ));
+        buzz(&mut game, carol, 220).unwrap();
+        // Players without a team are on their own
+        buzz(&mut game, dave, 230).unwrap();
+
+        // Red's turn stays used up after Alice was judged wrong
+        let judge = ClientToServer::Judge {
+            game_code: CODE,
+            correct: false,
+        };
+        host(&mut game, host_id, judge).unwrap();
+        assert!(matches!(
+            buzz(&mut game, bob, 240),
+            Err(GameError::TeammateBuzzed)
+        ));
+
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::Clear { game_code: CODE },
+        )
+        .unwrap();
+        buzz(&mut game, bob, 300).unwrap();
+        assert_eq!(order(&game), [bob]);
+    }
+
+    #[test]
+    fn teammates_buzz_freely_without_the_rule() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        let red = new_team(&mut game, host_id, "Red");
+        assign(&mut game, host_id, alice, Some(red)).unwrap();
+        assign(&mut game, host_id, bob, Some(red)).unwrap();
+
+        buzz(&mut game, alice, 200).unwrap();
+        buzz(&mut game, bob, 210).unwrap();
+        assert_eq!(order(&game), [alice, bob]);
+    }
+
+    #[test]
+    fn reassigning_moves_a_player_and_their_score() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        let red = new_team(&mut game, host_id, "Red");
+        let blue = new_team(&mut game, host_id, "Blue");
+        assign(&mut game, host_id, alice, Some(red)).unwrap();
+        assign(&mut game, host_id, bob, Some(red)).unwrap();
+        game.scores.insert(alice, 30);
+        game.scores.insert(bob, 5);
+        assert_eq!(game.team_score(&red), 35);
+
+        assign(&mut game, host_id, alice, Some(blue)).unwrap();
+        assert_eq!(game.team_of(&alice).unwrap().id, blue);
+        assert_eq!(game.team_members(&red), [bob]);
+        assert_eq!(game.team_members(&blue), [alice]);
+        assert_eq!(game.team_score(&red), 5);
+        assert_eq!(game.team_score(&blue), 30);
+
+        assign(&mut game, host_id, alice, None).unwrap();
+        assert!(game.team_of(&alice).is_none());
+        assert!(game.team_members(&blue).is_empty());
+
+        let unknown = assign(&mut game, host_id, alice, Some(Uuid::new_v4()));
+        assert!(matches!(unknown, Err(GameError::UnknownTeam)));
+        let not_a_player = assign(&mut game, host_id, host_id, Some(red));
+        assert!(matches!(not_a_player, Err(GameError::UnknownPlayer)));
+    }
+
+    #[test]
+    fn removing_a_team_leaves_its_members_without_one() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let red = new_team(&mut game, host_id, "Red");
+        assign(&mut game, host_id, alice, Some(red)).unwrap();
+
+        let remove = ClientToServer::RemoveTeam {
+            game_code: CODE,
+            team_id: red,
+        };
+        host(&mut game, host_id, remove).unwrap();
+        assert!(game.teams.is_empty());
+        assert!(game.team_of(&alice).is_none());
+    }
+
+    #[test]
+    fn players_join_teams_by_name() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        choose(&mut game, alice, " Red ").unwrap();
+        choose(&mut game, bob, "RED").unwrap();
+        assert_eq!(game.teams.len(), 1);
+        let red = game.teams[0].id;
+        assert_eq!(game.team_members(&red), [alice, bob]);
+
+        assert!(matches!(
+            choose(&mut game, bob, "  "),
+            Err(GameError::EmptyTeamName)
+        ));
+        let taken = host(
+            &mut game,
+            host_id,
+            ClientToServer::CreateTeam {
+                game_code: CODE,
+                name: "red".to_string(),
+            },
+        );
+        assert!(matches!(taken, Err(GameError::TeamNameTaken(_))));
+
+        let rules = GameRules {
+            players_create_teams: false,
+            ..GameRules::default()
+        };
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::SetRules {
+                game_code: CODE,
+                rules,
+            },
+        )
+        .unwrap();
+        assert!(matches!(
+            choose(&mut game, bob, "Blue"),
+            Err(GameError::UnknownTeam)
+        ));
+        assert_eq!(game.team_of(&bob).unwrap().id, red);
+    }
+}
@@ -0,0 +1,72 @@
+use uuid::Uuid;
+
+use super::*;
+
+pub(crate) const CODE: usize = 1234;
+
+pub(crate) fn ctx(now_ms: u64) -> ApplyContext {
+    ApplyContext {
+        now_ms,
+        clock: None,
+        buzz_fairness_window_ms: 0,
+    }
+}
+
+pub(crate) fn new_game() -> (GameState, Uuid) {
+    let host_id = Uuid::new_v4();
+    (GameState::new(host_id, 0), host_id)
+}
+
+/// Joins a new player and returns their id and session token
+pub(crate) fn join(game: &mut GameState, name: &str) -> (Uuid, Uuid) {
+    let player_id = Uuid::new_v4();
+    let out = game
+        .apply(
+            player_id,
+            ClientToServer::JoinGame {
+                game_code: CODE,
+                player_name: name.to_string(),
+                team_name: None,
+            },
+            &ctx(10),
+        )
+        .unwrap();
+    let token = out
+        .iter()
+        .find_map(|o| match o {
+            Outbound::Send(_, ServerToClient::GameJoined { session_token, .. }) => {
+                Some(*session_token)
+            }
+            _ => None,
+        })
+        .expect("joining sends the session token");
+    (player_id, token)
+}
+
+pub(crate) fn buzz(
+    game: &mut GameState,
+    player_id: Uuid,
+    now_ms: u64,
+) -> Result<Vec<Outbound>, GameError> {
+    game.apply(
+        player_id,
+        ClientToServer::Buzz {
+            game_code: CODE,
+            player_id,
+            client_time_ms: None,
+        },
+        &ctx(now_ms),
+    )
+}
+
+pub(crate) fn host(
+    game: &mut GameState,
+    host_id: Uuid,
+    msg: ClientToServer,
+) -> Result<Vec<Outbound>, GameError> {
+    game.apply(host_id, msg, &ctx(100))
+}
+
+pub(crate) fn order(game: &GameState) -> Vec<Uuid> {
+    game.buzzer_order.iter().map(|(id, _)| *id).collect()
+}
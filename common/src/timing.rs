@@ -0,0 +1,190 @@
+use std::collections::VecDeque;
+
+use uuid::Uuid;
+
+use serde::{Deserialize, Serialize};
+
+use super::GameState;
+
+// Number of recent ping samples to pick the best clock estimate from
+const CLOCK_SAMPLE_WINDOW: usize = 8;
+
+/// How far a corrected press time may be from the time the buzz arrived.
+/// Anything further off is a broken or forged timestamp.
+pub const MAX_CLOCK_SKEW_MS: u64 = 60_000;
+
+/// NTP-style estimate of how far a client's clock is ahead of the server's.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct ClockEstimate {
+    pub offset_ms: i64,
+    pub rtt_ms: u64,
+}
+
+impl ClockEstimate {
+    /// Builds an estimate from a single ping round trip, assuming the
+    /// latency is the same in both directions.
+    pub fn from_round_trip(
+        server_sent_ms: u64,
+        client_time_ms: u64,
+        server_received_ms: u64,
+    ) -> Self {
+        // Both client times come straight from the client, so nothing here
+        // may overflow however far off they are
+        let rtt_ms = server_received_ms.saturating_sub(server_sent_ms);
+        let midpoint_ms = server_sent_ms.saturating_add(rtt_ms / 2);
+        let offset_ms = i128::from(client_time_ms) - i128::from(midpoint_ms);
+        Self {
+            offset_ms: offset_ms.clamp(i64::MIN.into(), i64::MAX.into()) as i64,
+            rtt_ms,
+        }
+    }
+
+    /// Converts a timestamp taken on the client's clock to the server's clock.
+    pub fn to_server_time(&self, client_time_ms: u64) -> u64 {
+        let server_time_ms = i128::from(client_time_ms) - i128::from(self.offset_ms);
+        server_time_ms.clamp(0, u64::MAX.into()) as u64
+    }
+
+    /// Whether a press at `client_time_ms` could have happened around
+    /// `received_ms`, the time the buzz arrived.
+    pub fn is_plausible(&self, client_time_ms: u64, received_ms: u64) -> bool {
+        self.to_server_time(client_time_ms).abs_diff(received_ms) <= MAX_CLOCK_SKEW_MS
+    }
+}
+
+/// Recent clock samples of one connection. The sample with the lowest
+/// round trip is the least disturbed by queuing, so that one wins.
+#[derive(Debug, Clone, Default)]
+pub struct ClockSamples {
+    samples: VecDeque<ClockEstimate>,
+}
+
+impl ClockSamples {
+    pub fn push(&mut self, sample: ClockEstimate) {
+        if self.samples.len() == CLOCK_SAMPLE_WINDOW {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn best(&self) -> Option<ClockEstimate> {
+        self.samples.iter().min_by_key(|s| s.rtt_ms).copied()
+    }
+}
+
+/// When a buzz was pressed and received, both on the server's clock.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct BuzzTiming {
+    pub press_time_ms: u64,
+    pub received_ms: u64,
+    // Clock estimate used for the correction, if the client had one
+    pub clock: Option<ClockEstimate>,
+}
+
+impl BuzzTiming {
+    /// Corrects the client's press time by its clock offset. The correction
+    /// is capped at `fairness_window_ms` so a client cannot claim to have
+    /// pressed arbitrarily early.
+    pub fn new(
+        client_time_ms: Option<u64>,
+        clock: Option<ClockEstimate>,
+        received_ms: u64,
+        fairness_window_ms: u64,
+    ) -> Self {
+        let press_time_ms = match (client_time_ms, clock) {
+            (Some(client_time_ms), Some(clock)) => clock
+                .to_server_time(client_time_ms)
+                .clamp(received_ms.saturating_sub(fairness_window_ms), received_ms),
+            _ => received_ms,
+        };
+        Self {
+            press_time_ms,
+            received_ms,
+            clock,
+        }
+    }
+}
+
+impl GameState {
+    /// Where a buzz goes in the queue. It may overtake buzzes that were pressed
+    /// later but reached the server first, as long as they arrived within
+    /// `fairness_window_ms` of this one.
+    pub fn buzz_position(&self, timing: &BuzzTiming, fairness_window_ms: u64) -> usize {
+        let mut position = self.buzzer_order.len();
+        while position > 0 {
+            let (earlier_id, _) = &self.buzzer_order[position - 1];
+            let Some(earlier) = self.buzz_timings.get(earlier_id) else {
+                break;
+            };
+            let within_window =
+                timing.received_ms.saturating_sub(earlier.received_ms) <= fairness_window_ms;
+            if !within_window || earlier.press_time_ms <= timing.press_time_ms {
+                break;
+            }
+            position -= 1;
+        }
+        position
+    }
+
+    /// Ends the question: everyone may buzz again.
+    pub fn clear_buzzes(&mut self) {
+        self.buzzer_order.clear();
+        self.buzz_timings.clear();
+        self.locked_out.clear();
+    }
+
+    /// The host ruled on `player_id`'s answer. A correct answer ends the
+    /// question; a wrong one hands the turn to the next buzzer and keeps the
+    /// player from buzzing again until the queue is cleared.
+    pub(crate) fn judge_answer(&mut self, player_id: Uuid, correct: bool) {
+        if correct {
+            self.globally_locked = true;
+            self.clear_buzzes();
+        } else {
+            self.buzzer_order.retain(|(id, _)| *id != player_id);
+            self.buzz_timings.remove(&player_id);
+            if !self.rules.allow_rebuzz_after_wrong {
+                self.locked_out.insert(player_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip_offsets_saturate_instead_of_overflowing() {
+        let ahead = ClockEstimate::from_round_trip(1_000, u64::MAX, 1_020);
+        assert_eq!((ahead.offset_ms, ahead.rtt_ms), (i64::MAX, 20));
+        let behind = ClockEstimate::from_round_trip(u64::MAX, 0, u64::MAX);
+        assert_eq!(behind.offset_ms, i64::MIN);
+    }
+
+    #[test]
+    fn server_times_stay_in_range() {
+        let clock = ClockEstimate {
+            offset_ms: i64::MIN,
+            rtt_ms: 0,
+        };
+        assert_eq!(clock.to_server_time(u64::MAX), u64::MAX);
+        let clock = ClockEstimate {
+            offset_ms: i64::MAX,
+            rtt_ms: 0,
+        };
+        assert_eq!(clock.to_server_time(5), 0);
+    }
+
+    #[test]
+    fn only_press_times_near_the_arrival_are_plausible() {
+        let clock = ClockEstimate {
+            offset_ms: 500,
+            rtt_ms: 10,
+        };
+        assert!(clock.is_plausible(10_500, 10_020));
+        assert!(clock.is_plausible(10_500 + MAX_CLOCK_SKEW_MS, 10_000));
+        assert!(!clock.is_plausible(u64::MAX, 10_000));
+        assert!(!clock.is_plausible(0, 10_000 + MAX_CLOCK_SKEW_MS));
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::*;
+
+/// One player's bet for the wager question
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Wager {
+    pub player_id: Uuid,
+    /// When the server received the latest bet
+    pub at_ms: u64,
+    /// Kept in `GameState::wager_amounts`, and only sent to the host, until
+    /// the bet is resolved or the host reveals all of them
+    #[serde(default)]
+    pub amount: Option<i32>,
+    /// Whether the player answered right, once the host resolved the bet
+    #[serde(default)]
+    pub won: Option<bool>,
+    /// Ledger entries that scored the bet, one per time it was resolved
+    #[serde(default)]
+    pub entry_ids: Vec<u64>,
+}
+
+/// The bets placed before a wager question
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct WagerRound {
+    /// Whether players may still bet
+    pub open: bool,
+    pub wagers: Vec<Wager>,
+}
+
+impl WagerRound {
+    pub fn wager_of(&self, player_id: &Uuid) -> Option<&Wager> {
+        self.wagers.iter().find(|w| w.player_id == *player_id)
+    }
+}
+
+impl GameState {
+    pub fn wagers_open(&self) -> bool {
+        self.wager.as_ref().is_some_and(|w| w.open)
+    }
+
+    /// The most `player_id` may bet: everything they have, if anything
+    pub fn max_wager(&self, player_id: &Uuid) -> i32 {
+        self.scores
+            .get(player_id)
+            .copied()
+            .unwrap_or_default()
+            .max(0)
+    }
+
+    pub(crate) fn note_wager(&mut self, at_ms: u64, player_id: Uuid) {
+        let Some(round) = self.wager.as_mut() else {
+            return;
+        };
+        match round.wagers.iter_mut().find(|w| w.player_id == player_id) {
+            Some(wager) => wager.at_ms = at_ms,
+            None => round.wagers.push(Wager {
+                player_id,
+                at_ms,
+                amount: None,
+                won: None,
+                entry_ids: Vec::new(),
+            }),
+        }
+    }
+
+    pub(crate) fn reveal_wagers(&mut self, amounts: &HashMap<Uuid, i32>) {
+        if let Some(round) = self.wager.as_mut() {
+            round.open = false;
+            for wager in &mut round.wagers {
+                if let Some(amount) = amounts.get(&wager.player_id) {
+                    wager.amount = Some(*amount);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn settle_wager(
+        &mut self,
+        player_id: Uuid,
+        amount: i32,
+        won: bool,
+        entry_id: Option<u64>,
+    ) {
+        if let Some(wager) = self
+            .wager
+            .as_mut()
+            .and_then(|round| round.wagers.iter_mut().find(|w| w.player_id == player_id))
+        {
+            wager.amount = Some(amount);
+            wager.won = Some(won);
+            wager.entry_ids.extend(entry_id);
+        }
+    }
+
+    /// How much the bet's ledger entries add to the score right now; entries
+    /// the host undid don't count
+    fn applied_wager(&self, wager: &Wager) -> i64 {
+        wager
+            .entry_ids
+            .iter()
+            .filter_map(|id| self.score_ledger.entries.get(*id as usize))
+            .filter(|entry| !entry.undone)
+            .map(|entry| i64::from(entry.delta))
+            .sum()
+    }
+
+    fn wager_amounts_message(&self) -> ServerToClient {
+        ServerToClient::WagerAmounts {
+            amounts: self.wager_amounts.clone(),
+        }
+    }
+
+    /// What to send a host that just came back
+    pub(crate) fn resend_wager_amounts(&self) -> Option<Outbound> {
+        (!self.wager_amounts.is_empty())
+            .then(|| Outbound::Send(self.host_id, self.wager_amounts_message()))
+    }
+
+    pub(crate) fn open_wagers(&mut self, now_ms: u64) -> Vec<Outbound> {
+        self.wager_amounts.clear();
+        self.record(now_ms, GameEvent::WagersOpened);
+        vec![
+            Outbound::Send(self.host_id, self.wager_amounts_message()),
+            self.state_update(),
+        ]
+    }
+
+    /// Places or changes a bet while wagering is open
+    pub(crate) fn place_wager(
+        &mut self,
+        now_ms: u64,
+        player_id: Uuid,
+        amount: i32,
+    ) -> Result<Vec<Outbound>, GameError> {
+        if !self.wagers_open() {
+            return Err(GameError::NoWagers);
+        }
+        let max = self.max_wager(&player_id);
+        if !(0..=max).contains(&amount) {
+            return Err(GameError::InvalidWager(max));
+        }
+        self.wager_amounts.insert(player_id, amount);
+        self.record(now_ms, GameEvent::WagerPlaced { player_id });
+        Ok(vec![
+            Outbound::Send(self.host_id, self.wager_amounts_message()),
+            self.delta(GameDelta::WagerPlaced {
+                player_id,
+                at_ms: now_ms,
+            }),
+        ])
+    }
+
+    /// Stops taking bets. Does nothing if wagering is not open.
+    pub(crate) fn lock_wagers(&mut self, now_ms: u64) -> Vec<Outbound> {
+        if !self.wagers_open() {
+            return Vec::new();
+        }
+        self.record(now_ms, GameEvent::WagersLocked);
+        vec![self.state_update()]
+    }
+
+    /// Shows every bet to everyone; wagering is over by then
+    pub(crate) fn show_wagers(&mut self, now_ms: u64) -> Result<Vec<Outbound>, GameError> {
+        if self.wager.is_none() {
+            return Err(GameError::NoWagers);
+        }
+        let amounts = self.wager_amounts.clone();
+        self.record(now_ms, GameEvent::WagersRevealed { amounts });
+        Ok(vec![self.state_update()])
+    }
+
+    /// Adds or takes the player's bet depending on their answer. Resolving a
+    /// bet again only changes the score by what its earlier resolutions
+    /// still add to it, so undoing one of them is taken into account.
+    pub(crate) fn resolve_wager(
+        &mut self,
+        now_ms: u64,
+        player_id: Uuid,
+        won: bool,
+    ) -> Result<Vec<Outbound>, GameError> {
+        let round = self.wager.as_ref().ok_or(GameError::NoWagers)?;
+        let wager = round.wager_of(&player_id).ok_or(GameError::UnknownPlayer)?;
+        let amount = self
+            .wager_amounts
+            .get(&player_id)
+            .copied()
+            .unwrap_or_default();
+        let outcome = if won { amount } else { amount.saturating_neg() };
+        let delta = i64::from(outcome) - self.applied_wager(wager);
+        let delta = delta.clamp(i32::MIN.into(), i32::MAX.into()) as i32;
+        if wager.won == Some(won) && delta == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut out = self.lock_wagers(now_ms);
+        let mut entry_id = None;
+        if delta != 0 {
+            let reason = if won { "Wager won" } else { "Wager lost" };
+            out.extend(self.change_score(now_ms, player_id, delta, None, Some(reason.to_string())));
+            entry_id = self.score_ledger.entries.last().map(|entry| entry.id);
+        }
+        self.record(
+            now_ms,
+            GameEvent::WagerResolved {
+                player_id,
+                amount,
+                won,
+                entry_id,
+            },
+        );
+        out.push(self.state_update());
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    /// A game where Alice, with `score` points, bet `amount` of them
+    fn wagered(score: i32, amount: i32) -> (GameState, Uuid, Uuid) {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::SetScore {
+                game_code: CODE,
+                player_id: alice,
+                score,
+                reason: None,
+            },
+        )
+        .unwrap();
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::OpenWagers { game_code: CODE },
+        )
+        .unwrap();
+        let bet = ClientToServer::PlaceWager {
+            game_code: CODE,
+            amount,
+        };
+        game.apply(alice, bet, &ctx(50)).unwrap();
+        (game, host_id, alice)
+    }
+
+    fn resolve(game: &mut GameState, host_id: Uuid, player_id: Uuid, correct: bool) {
+        let msg = ClientToServer::ResolveWager {
+            game_code: CODE,
+            player_id,
+            correct,
+        };
+        host(game, host_id, msg).unwrap();
+    }
+
+    #[test]
+    fn resolving_again_only_applies_the_difference() {
+        let (mut game, host_id, alice) = wagered(100, 40);
+
+        resolve(&mut game, host_id, alice, true);
+        assert_eq!(game.scores[&alice], 140);
+        resolve(&mut game, host_id, alice, false);
+        assert_eq!(game.scores[&alice], 60);
+        let entries = game.score_ledger.entries.len();
+        resolve(&mut game, host_id, alice, false);
+        assert_eq!(game.scores[&alice], 60);
+        assert_eq!(game.score_ledger.entries.len(), entries);
+    }
+
+    #[test]
+    fn resolving_after_an_undo_counts_only_what_is_applied() {
+        let (mut game, host_id, alice) = wagered(100, 40);
+
+        resolve(&mut game, host_id, alice, true);
+        host(&mut game, host_id, ClientToServer::Undo { game_code: CODE }).unwrap();
+        assert_eq!(game.scores[&alice], 100);
+
+        // The win no longer counts, so resolving it again scores it again
+        resolve(&mut game, host_id, alice, true);
+        assert_eq!(game.scores[&alice], 140);
+        resolve(&mut game, host_id, alice, false);
+        assert_eq!(game.scores[&alice], 60);
+    }
+
+    #[test]
+    fn wagers_near_the_score_limit_stop_there() {
+        let (mut game, host_id, alice) = wagered(MAX_SCORE, MAX_SCORE);
+
+        resolve(&mut game, host_id, alice, true);
+        assert_eq!(game.scores[&alice], MAX_SCORE);
+        resolve(&mut game, host_id, alice, false);
+        assert_eq!(game.scores[&alice], 0);
+        resolve(&mut game, host_id, alice, true);
+        assert_eq!(game.scores[&alice], MAX_SCORE);
+    }
+
+    #[test]
+    fn replaying_resolved_wagers_gives_the_same_scores() {
+        let (mut game, host_id, alice) = wagered(100, 40);
+        resolve(&mut game, host_id, alice, true);
+        host(&mut game, host_id, ClientToServer::Undo { game_code: CODE }).unwrap();
+        resolve(&mut game, host_id, alice, false);
+
+        let replayed = GameState::replay(&game.events);
+        assert_eq!(replayed.scores, game.scores);
+        assert_eq!(replayed.wager, game.wager);
+    }
+}
@@ -0,0 +1,404 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::*;
+
+// Short answers only; anything longer is an essay, not a quiz answer
+pub const MAX_ANSWER_LENGTH: usize = 200;
+
+/// How the host graded a written answer
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Grade {
+    Correct,
+    /// Worth half the points of a correct answer
+    Partial,
+    Incorrect,
+}
+
+impl Grade {
+    pub fn points(&self, rules: &GameRules) -> i32 {
+        match self {
+            Self::Correct => rules.points_correct,
+            Self::Partial => rules.points_correct / 2,
+            Self::Incorrect => -rules.penalty_wrong,
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self {
+            Self::Correct => "Correct answer",
+            Self::Partial => "Partly correct answer",
+            Self::Incorrect => "Wrong answer",
+        }
+    }
+}
+
+/// One player's written answer to the current question
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WrittenAnswer {
+    pub player_id: Uuid,
+    /// When the server received the answer
+    pub at_ms: u64,
+    /// Kept in `GameState::answer_texts`, and only sent to the host, until
+    /// the host shows the answers to everyone
+    #[serde(default)]
+    pub text: Option<String>,
+    #[serde(default)]
+    pub grade: Option<Grade>,
+}
+
+/// The written answers collected for the current question
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct WrittenAnswers {
+    /// Whether players may still submit
+    pub open: bool,
+    /// Whether everyone can see the texts
+    pub shown: bool,
+    pub answers: Vec<WrittenAnswer>,
+}
+
+impl WrittenAnswers {
+    pub fn answer_of(&self, player_id: &Uuid) -> Option<&WrittenAnswer> {
+        self.answers.iter().find(|a| a.player_id == *player_id)
+    }
+}
+
+/// Answers that read the same, so the host can grade them in one go
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnswerGroup {
+    /// The first of the answers, as it was typed
+    pub text: String,
+    pub player_ids: Vec<Uuid>,
+}
+
+/// Groups answers that are equal once case, punctuation and a leading
+/// article are ignored, or that are only a typo apart. Keeps the order in
+/// which the answers were given.
+pub fn group_answers<'a>(answers: impl IntoIterator<Item = (Uuid, &'a str)>) -> Vec<AnswerGroup> {
+    let mut groups: Vec<(String, AnswerGroup)> = Vec::new();
+    for (player_id, text) in answers {
+        let key = normalize_answer(text);
+        match groups
+            .iter_mut()
+            .find(|(other, _)| near_duplicate(other, &key))
+        {
+            Some((_, group)) => group.player_ids.push(player_id),
+            None => groups.push((
+                key,
+                AnswerGroup {
+                    text: text.to_string(),
+                    player_ids: vec![player_id],
+                },
+            )),
+        }
+    }
+    groups.into_iter().map(|(_, group)| group).collect()
+}
+
+fn normalize_answer(text: &str) -> String {
+    let lowered: String = text
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    let words: Vec<&str> = lowered.split_whitespace().collect();
+    let words = match words.split_first() {
+        Some((first, rest)) if !rest.is_empty() && ["the", "a", "an"].contains(first) => rest,
+        _ => &words[..],
+    };
+    words.join(" ")
+}
+
+/// Equal, or a typo apart: one edit for short answers, two for long ones
+fn near_duplicate(a: &str, b: &str) -> bool {
+    if a == b {
+        return true;
+    }
+    let allowed = match a.chars().count().min(b.chars().count()) {
+        0..=3 => return false,
+        4..=7 => 1,
+        _ => 2,
+    };
+    edit_distance(a, b) <= allowed
+}
+
+/// Levenshtein distance between two strings, counted in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+impl GameState {
+    pub fn answers_open(&self) -> bool {
+        self.written.as_ref().is_some_and(|w| w.open)
+    }
+
+    pub(crate) fn add_written_answer(&mut self, at_ms: u64, player_id: Uuid) {
+        if let Some(written) = self.written.as_mut()
+            && written.answer_of(&player_id).is_none()
+        {
+            written.answers.push(WrittenAnswer {
+                player_id,
+                at_ms,
+                text: None,
+                grade: None,
+            });
+        }
+    }
+
+    pub(crate) fn show_written_answers(&mut self, texts: &HashMap<Uuid, String>) {
+        if let Some(written) = self.written.as_mut() {
+            written.open = false;
+            written.shown = true;
+            for answer in &mut written.answers {
+                answer.text = texts.get(&answer.player_id).cloned();
+            }
+        }
+    }
+
+    pub(crate) fn grade_written_answer(&mut self, player_id: Uuid, grade: Grade) {
+        if let Some(answer) = self
+            .written
+            .as_mut()
+            .and_then(|w| w.answers.iter_mut().find(|a| a.player_id == player_id))
+        {
+            answer.grade = Some(grade);
+        }
+    }
+
+    /// The answer texts, for the host
+    fn answer_texts_message(&self) -> ServerToClient {
+        ServerToClient::AnswerTexts {
+            texts: self.answer_texts.clone(),
+        }
+    }
+
+    /// What to send a host that just came back
+    pub(crate) fn resend_answer_texts(&self) -> Option<Outbound> {
+        (!self.answer_texts.is_empty())
+            .then(|| Outbound::Send(self.host_id, self.answer_texts_message()))
+    }
+
+    pub(crate) fn open_answers(&mut self, now_ms: u64) -> Vec<Outbound> {
+        self.answer_texts.clear();
+        self.record(now_ms, GameEvent::AnswersOpened);
+        vec![
+            Outbound::Send(self.host_id, self.answer_texts_message()),
+            self.state_update(),
+        ]
+    }
+
+    pub(crate) fn submit_answer(
+        &mut self,
+        now_ms: u64,
+        player_id: Uuid,
+        text: String,
+    ) -> Result<Vec<Outbound>, GameError> {
+        let Some(written) = self.written.as_ref().filter(|w| w.open) else {
+            return Err(GameError::NoAnswers);
+        };
+        if written.answer_of(&player_id).is_some() {
+            return Err(GameError::AlreadyAnswered);
+        }
+        let text = text.trim().to_string();
+        if text.is_empty() {
+            return Err(GameError::InvalidAnswer("The answer is empty."));
+        }
+        if text.chars().count() > MAX_ANSWER_LENGTH {
+            return Err(GameError::InvalidAnswer("The answer is too long."));
+        }
+        self.answer_texts.insert(player_id, text);
+        self.record(now_ms, GameEvent::AnswerSubmitted { player_id });
+        Ok(vec![
+            Outbound::Send(self.host_id, self.answer_texts_message()),
+            self.delta(GameDelta::AnswerSubmitted {
+                player_id,
+                at_ms: now_ms,
+            }),
+        ])
+    }
+
+    pub(crate) fn close_answers(&mut self, now_ms: u64) -> Result<Vec<Outbound>, GameError> {
+        if !self.answers_open() {
+            return Err(GameError::NoAnswers);
+        }
+        self.record(now_ms, GameEvent::AnswersClosed);
+        Ok(vec![self.state_update()])
+    }
+
+    pub(crate) fn show_answers(&mut self, now_ms: u64) -> Result<Vec<Outbound>, GameError> {
+        if self.written.is_none() {
+            return Err(GameError::NoAnswers);
+        }
+        let texts = self.answer_texts.clone();
+        self.record(now_ms, GameEvent::AnswersShown { texts });
+        Ok(vec![self.state_update()])
+    }
+
+    /// Grades every listed answer the same. Grading an answer again only
+    /// changes the score by the difference, and an id listed twice counts once.
+    pub(crate) fn grade_answers(
+        &mut self,
+        now_ms: u64,
+        player_ids: &[Uuid],
+        grade: Grade,
+    ) -> Result<Vec<Outbound>, GameError> {
+        let written = self.written.as_ref().ok_or(GameError::NoAnswers)?;
+        let mut seen = HashSet::new();
+        let previous = player_ids
+            .iter()
+            .filter(|id| seen.insert(**id))
+            .map(|id| {
+                let answer = written.answer_of(id).ok_or(GameError::UnknownPlayer)?;
+                Ok((*id, answer.grade))
+            })
+            .collect::<Result<Vec<_>, GameError>>()?;
+
+        let mut out = Vec::new();
+        for (player_id, previous) in previous {
+            if previous == Some(grade) {
+                continue;
+            }
+            let amount = grade.points(&self.rules)
+                - previous.map_or(0, |previous| previous.points(&self.rules));
+            self.record(now_ms, GameEvent::AnswerGraded { player_id, grade });
+            if amount != 0 {
+                out.extend(self.change_score(
+                    now_ms,
+                    player_id,
+                    amount,
+                    None,
+                    Some(grade.reason().to_string()),
+                ));
+            }
+        }
+        out.push(self.state_update());
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    fn ids(count: usize) -> Vec<Uuid> {
+        (0..count).map(|_| Uuid::new_v4()).collect()
+    }
+
+    #[test]
+    fn edit_distance_counts_characters() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("paris", ""), 5);
+        assert_eq!(edit_distance("paris", "paris"), 0);
+        assert_eq!(edit_distance("paris", "pariss"), 1);
+        assert_eq!(edit_distance("paris", "parsi"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("zürich", "zurich"), 1);
+    }
+
+    #[test]
+    fn group_answers_ignores_case_punctuation_and_articles() {
+        let ids = ids(4);
+        let groups = group_answers([
+            (ids[0], "The Eiffel Tower"),
+            (ids[1], "eiffel tower!"),
+            (ids[2], "Big Ben"),
+            (ids[3], "  the  EIFFEL-tower "),
+        ]);
+        assert_eq!(
+            groups,
+            vec![
+                AnswerGroup {
+                    text: "The Eiffel Tower".to_string(),
+                    player_ids: vec![ids[0], ids[1], ids[3]],
+                },
+                AnswerGroup {
+                    text: "Big Ben".to_string(),
+                    player_ids: vec![ids[2]],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn group_answers_tolerates_typos_in_longer_answers_only() {
+        let ids = ids(6);
+        let groups = group_answers([
+            (ids[0], "Paris"),
+            (ids[1], "Pariss"),
+            (ids[2], "cat"),
+            (ids[3], "car"),
+            (ids[4], "Mississippi"),
+            (ids[5], "Missisippi"),
+        ]);
+        let player_ids: Vec<_> = groups.iter().map(|g| g.player_ids.clone()).collect();
+        assert_eq!(
+            player_ids,
+            vec![
+                vec![ids[0], ids[1]],
+                vec![ids[2]],
+                vec![ids[3]],
+                vec![ids[4], ids[5]],
+            ]
+        );
+        // A lone article is the answer, not something to skip
+        assert_eq!(normalize_answer("A"), "a");
+    }
+
+    #[test]
+    fn grading_an_id_twice_scores_it_once() {
+        let (mut game, host_id) = new_game();
+        let (alice, _) = join(&mut game, "Alice");
+        let (bob, _) = join(&mut game, "Bob");
+        host(
+            &mut game,
+            host_id,
+            ClientToServer::OpenAnswers { game_code: CODE },
+        )
+        .unwrap();
+        for (player_id, text) in [(alice, "Paris"), (bob, "Rome")] {
+            let msg = ClientToServer::SubmitAnswer {
+                game_code: CODE,
+                text: text.to_string(),
+            };
+            game.apply(player_id, msg, &ctx(50)).unwrap();
+        }
+        let grade = |player_ids: Vec<Uuid>, grade| ClientToServer::GradeAnswers {
+            game_code: CODE,
+            player_ids,
+            grade,
+        };
+
+        host(
+            &mut game,
+            host_id,
+            grade(vec![alice, alice, alice], Grade::Correct),
+        )
+        .unwrap();
+        assert_eq!(game.scores[&alice], game.rules.points_correct);
+
+        // Regrading only applies the difference, once
+        host(
+            &mut game,
+            host_id,
+            grade(vec![alice, bob, alice], Grade::Partial),
+        )
+        .unwrap();
+        let partial = game.rules.points_correct / 2;
+        assert_eq!(game.scores[&alice], partial);
+        assert_eq!(game.scores[&bob], partial);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::AppContext;
+use common::*;
+use dioxus::prelude::*;
+
+/// The category/value grid. The host picks clues from it; everyone else
+/// sees which clues have been played.
+#[component]
+pub fn GameBoard() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let game_state = app_ctx.game_state.read();
+    let Some(game) = game_state.as_ref() else {
+        return rsx! {};
+    };
+    let is_host = *app_ctx.player_id.read() == Some(game.host_id);
+    let has_questions = !app_ctx.question_bank.read().is_empty();
+
+    let send = move |msg: fn(usize) -> ClientToServer| {
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(msg(code));
+        }
+    };
+    let select = move |index: usize| {
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(ClientToServer::SelectClue {
+                game_code: code,
+                index,
+            });
+        }
+    };
+
+    let Some(board) = game.board.as_ref() else {
+        return rsx! {
+            if is_host && has_questions {
+                div {
+                    class: "host-controls",
+                    button {
+                        class: "control-button",
+                        onclick: move |_| send(|game_code| ClientToServer::OpenBoard { game_code }),
+                        "Play as Board"
+                    }
+                }
+            }
+        };
+    };
+    let columns = board.categories.len();
+    let names: Vec<String> = board.categories.iter().map(|c| c.name.clone()).collect();
+    let rows = board.categories.iter().map(|c| c.clues.len()).max().unwrap_or_default();
+    // Row by row, so the cells fill the grid in order. Shorter categories
+    // leave their bottom cells empty.
+    let cells: Vec<_> = (0..rows)
+        .flat_map(|row| {
+            board.categories.iter().map(move |category| match category.clues.get(row) {
+                Some(clue) => {
+                    let class = if board.active == Some(clue.index) {
+                        "board-cell active"
+                    } else if clue.used {
+                        "board-cell used"
+                    } else {
+                        "board-cell"
+                    };
+                    (Some(clue.index), clue.value.to_string(), clue.used, class)
+                }
+                None => (None, String::new(), true, "board-cell empty"),
+            })
+        })
+        .collect();
+    let finished = board.finished();
+
+    rsx! {
+        div {
+            class: "game-board-container",
+            if is_host {
+                div {
+                    class: "host-controls",
+                    if finished {
+                        span { "Every clue has been played." }
+                    }
+                    button {
+                        class: "control-button",
+                        onclick: move |_| send(|game_code| ClientToServer::CloseBoard { game_code }),
+                        "Close Board"
+                    }
+                }
+            }
+            div {
+                class: "game-board",
+                style: "grid-template-columns: repeat({columns}, 1fr);",
+                for name in names {
+                    div { class: "board-category", "{name}" }
+                }
+                for (index, label, used, class) in cells {
+                    button {
+                        class: "{class}",
+                        disabled: !is_host || used,
+                        onclick: move |_| {
+                            if let Some(index) = index {
+                                select(index);
+                            }
+                        },
+                        "{label}"
+                    }
+                }
+            }
+        }
+    }
+}
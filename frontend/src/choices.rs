@@ -0,0 +1,152 @@
+use crate::AppContext;
+use common::*;
+use dioxus::prelude::*;
+
+/// Lets the host write and open a multiple-choice question, then close it
+#[component]
+pub fn HostChoicePanel() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let mut options = use_signal(|| vec![String::new(); CHOICE_LABELS.len()]);
+    let mut correct = use_signal(|| 0_usize);
+    let game_state = app_ctx.game_state.read();
+    let Some(game) = game_state.as_ref() else {
+        return rsx! {};
+    };
+    let open = game.choices_open();
+    let answered = game.choice.as_ref().map_or(0, |c| c.answers.len());
+    let players = game.player_join_order.len().saturating_sub(1);
+    let inputs: Vec<_> = CHOICE_LABELS.iter().zip(options.read().clone()).collect();
+
+    let on_open = move |_| {
+        // Trailing empty options are simply not offered
+        let mut filled = options.read().clone();
+        while filled.last().is_some_and(|o| o.trim().is_empty()) {
+            filled.pop();
+        }
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(ClientToServer::OpenChoices {
+                game_code: code,
+                options: filled,
+                correct: correct(),
+            });
+        }
+    };
+    let on_close = move |_| {
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(ClientToServer::CloseChoices { game_code: code });
+        }
+    };
+
+    rsx! {
+        div {
+            class: "choice-panel",
+            h3 { "Multiple Choice" }
+            if open {
+                p { "{answered} of {players} players answered" }
+                button { class: "control-button", onclick: on_close, "Close and Score" }
+            } else {
+                for (i, (label, value)) in inputs.into_iter().enumerate() {
+                    div {
+                        class: "choice-option-input",
+                        input {
+                            r#type: "radio",
+                            name: "correct-choice",
+                            "aria-label": "Option {label} is correct",
+                            checked: correct() == i,
+                            onchange: move |_| correct.set(i),
+                        }
+                        span { class: "choice-label", "{label}" }
+                        input {
+                            r#type: "text",
+                            placeholder: "Option {label}",
+                            value: "{value}",
+                            oninput: move |evt| options.write()[i] = evt.value(),
+                        }
+                    }
+                }
+                button { class: "control-button", onclick: on_open, "Open Question" }
+            }
+            ChoiceResults {}
+        }
+    }
+}
+
+/// A–D buttons that take the place of the buzzer while a question is open
+#[component]
+pub fn PlayerChoices() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let mut my_choice = use_signal(|| None::<usize>);
+    let game_state = app_ctx.game_state.read();
+    let Some(question) = game_state.as_ref().and_then(|game| game.choice.as_ref()) else {
+        return rsx! {};
+    };
+    let answer = (*app_ctx.player_id.read()).and_then(|id| question.answer_of(&id).cloned());
+    let answered = answer.is_some();
+    // Our pick stays hidden in the state until the question closes, so
+    // remember it ourselves until then
+    let picked = answer.and_then(|a| a.choice).or(answered.then(|| *my_choice.read()).flatten());
+    let options: Vec<_> = question
+        .options
+        .iter()
+        .enumerate()
+        .map(|(i, option)| (i, ChoiceQuestion::label(i), option.clone()))
+        .collect();
+
+    rsx! {
+        div {
+            class: "choice-buttons",
+            for (i, label, option) in options {
+                button {
+                    class: if picked == Some(i) { "choice-button picked" } else { "choice-button" },
+                    disabled: answered || question.closed,
+                    onclick: move |_| {
+                        my_choice.set(Some(i));
+                        if let Some(code) = *app_ctx.game_code.read() {
+                            app_ctx.send(ClientToServer::SubmitChoice {
+                                game_code: code,
+                                choice: i,
+                            });
+                        }
+                    },
+                    span { class: "choice-label", "{label}" }
+                    " {option}"
+                }
+            }
+        }
+    }
+}
+
+/// How many players picked each option, once the question is closed
+#[component]
+pub fn ChoiceResults() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let game_state = app_ctx.game_state.read();
+    let Some(question) = game_state
+        .as_ref()
+        .and_then(|game| game.choice.as_ref())
+        .filter(|c| c.closed)
+    else {
+        return rsx! {};
+    };
+    let counts = question.distribution();
+    let total = counts.iter().sum::<usize>().max(1);
+    let rows = question.options.iter().zip(counts).enumerate().map(|(i, (option, count))| {
+        let width = count * 100 / total;
+        (ChoiceQuestion::label(i), option.clone(), count, width, question.correct == Some(i))
+    });
+
+    rsx! {
+        ul {
+            class: "choice-results",
+            for (label, option, count, width, is_correct) in rows {
+                li {
+                    class: if is_correct { "correct" },
+                    span { class: "choice-label", "{label}" }
+                    span { class: "choice-option", "{option}" }
+                    span { class: "choice-bar", style: "width: {width}%" }
+                    span { class: "choice-count", "{count}" }
+                }
+            }
+        }
+    }
+}
@@ -1,14 +1,34 @@
-use crate::{AppContext, SOUND_OPTIONS, timer::Timer};
+use crate::{
+    board::GameBoard,
+    choices::HostChoicePanel,
+    questions::{QuestionImport, QuestionPanel},
+    rounds::{QuestionBanner, QuestionHistory, RoundScores},
+    teams::{group_by_team, NewTeamForm, TeamSelect},
+    timer::{AnswerTimer, FalseStartBadge, Timer},
+    wagers::HostWagerPanel,
+    written::{HostGradingQueue, ShownAnswers},
+    AppContext, SOUND_OPTIONS,
+};
 use common::*;
 use dioxus::prelude::*;
 use log::info;
 use web_sys::{window, HtmlAudioElement};
 use uuid::Uuid;
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, Copy, PartialEq)]
 struct HostContext {
     pub copied: Signal<bool>,
-    pub score_delta: Signal<i32>,
+    pub countdown_secs: Signal<u32>,
+    pub score_reason: Signal<String>,
+}
+
+impl HostContext {
+    /// The reason typed for the next score change, cleared once it is used
+    fn take_reason(&mut self) -> Option<String> {
+        let reason = self.score_reason.peek().trim().to_string();
+        self.score_reason.set(String::new());
+        (!reason.is_empty()).then_some(reason)
+    }
 }
 
 #[derive(Clone, PartialEq, Copy)]
@@ -33,12 +53,14 @@ pub fn HostView(file_url: Signal<Option<String>>) -> Element {
     let mut players_data = use_signal(Vec::new);
 
     let copied = use_signal(|| false);
-    let score_delta = use_signal(|| 10_i32);
+    let countdown_secs = use_signal(|| 10_u32);
+    let mut score_reason = use_signal(String::new);
     let mut show_settings = use_signal(|| false);
 
     use_context_provider(|| HostContext {
         copied,
-        score_delta,
+        countdown_secs,
+        score_reason,
     });
 
     use_effect(move || {
@@ -88,6 +110,17 @@ pub fn HostView(file_url: Signal<Option<String>>) -> Element {
         }
     };
 
+    let on_next_question = move |_| {
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(ClientToServer::NextQuestion { game_code: code });
+        }
+    };
+    let on_next_round = move |_| {
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(ClientToServer::NextRound { game_code: code });
+        }
+    };
+
     let on_sort = move |_| {
         let new_sort_by = sort_by().flip();
         sort_by.set(new_sort_by);
@@ -123,6 +156,18 @@ pub fn HostView(file_url: Signal<Option<String>>) -> Element {
                         CopyButton {}
                         CreateLink {}
                     }
+                    div {
+                        class: "host-controls",
+                        QuestionBanner {}
+                        button { class: "control-button", onclick: on_next_question, "Next Question" }
+                        button { class: "control-button", onclick: on_next_round, "Next Round" }
+                    }
+                    GameBoard {}
+                    QuestionPanel {}
+                    HostChoicePanel {}
+                    HostGradingQueue {}
+                    ShownAnswers {}
+                    HostWagerPanel {}
                     div {
                         class: "host-controls",
                         if game.globally_locked {
@@ -157,33 +202,68 @@ pub fn HostView(file_url: Signal<Option<String>>) -> Element {
                         SettingsMenu { is_open: show_settings, file_url }
                     }
                     PlayerBuzzOrderListHost {
-                        if let Some(time_limit) = app_ctx.time_limit.read().clone() {
-                            Timer { time_limit: time_limit }
-                        }
+                        Timer {}
+                        AnswerTimer {}
                     }
                     div {
                         class: "player-list-container",
                         div {
                             class: "player-list-header",
                             h3 { "Players & Scores" }
+                            input {
+                                r#type: "text",
+                                class: "score-reason-input",
+                                placeholder: "Reason (optional)",
+                                value: "{score_reason}",
+                                oninput: move |evt| score_reason.set(evt.value()),
+                            }
                             button {
                                 class: "control-button sort-button",
                                 onclick: on_sort,
                                 "Sort"
                             }
                         }
+                        NewTeamForm {}
                         ul {
                             class: "player-list",
-                            for (player_id, player_name, score, is_disconnected) in players_data.read().iter().cloned() {
-                                PlayerListItem {
-                                    player_id: player_id,
-                                    player_name: player_name,
-                                    score: score,
-                                    is_disconnected: is_disconnected,
+                            for group in group_by_team(game, players_data.read().clone(), |p| p.0) {
+                                if let Some((team_id, team_name, team_score)) = group.team {
+                                    li {
+                                        class: "team-header",
+                                        span { class: "player-name", "{team_name}" }
+                                        span { class: "score-display", "{team_score}" }
+                                        button {
+                                            class: "score-button",
+                                            "aria-label": "Remove team {team_name}",
+                                            onclick: move |_| {
+                                                if let Some(code) = *app_ctx.game_code.read() {
+                                                    app_ctx.send(ClientToServer::RemoveTeam {
+                                                        game_code: code,
+                                                        team_id,
+                                                    });
+                                                }
+                                            },
+                                            "✕"
+                                        }
+                                    }
+                                } else if !game.teams.is_empty() {
+                                    li { class: "team-header", span { class: "player-name", "No team" } }
+                                }
+                                for (player_id, player_name, score, is_disconnected) in group.players {
+                                    PlayerListItem {
+                                        player_id,
+                                        player_name: player_name,
+                                        score: score,
+                                        is_disconnected: is_disconnected,
+                                        team_select: true,
+                                    }
                                 }
                             }
                         }
                     }
+                    ScoreHistory {}
+                    RoundScores {}
+                    QuestionHistory {}
                 }
             }
         }
@@ -191,9 +271,18 @@ pub fn HostView(file_url: Signal<Option<String>>) -> Element {
 }
 
 #[component]
-pub fn PlayerListItem(player_id: Uuid, player_name: String, score: i32, is_disconnected: bool) -> Element {
+pub fn PlayerListItem(
+    player_id: Uuid,
+    player_name: String,
+    score: i32,
+    is_disconnected: bool,
+    buzz_note: Option<String>,
+    // Show a picker to move the player to another team
+    #[props(default)]
+    team_select: bool,
+) -> Element {
     let app_ctx = use_context::<AppContext>();
-    let host_ctx = use_context::<HostContext>();
+    let mut host_ctx = use_context::<HostContext>();
     let li_class = if is_disconnected {
         "player-list-item disconnected"
     } else {
@@ -204,7 +293,33 @@ pub fn PlayerListItem(player_id: Uuid, player_name: String, score: i32, is_disco
         li {
             class: li_class,
             span { class: "player-name", "{player_name}" }
-            span { class: "score-display", "{score}" }
+            FalseStartBadge { player_id }
+            if let Some(note) = buzz_note {
+                span { class: "buzz-timing", "{note}" }
+            }
+            if team_select {
+                TeamSelect { player_id }
+            }
+            input {
+                r#type: "number",
+                class: "score-display",
+                "aria-label": "Score of {player_name}",
+                value: "{score}",
+                onchange: move |evt| {
+                    if let (Some(code), Ok(new_score)) =
+                        (*app_ctx.game_code.read(), evt.value().parse::<i32>())
+                    {
+                        if new_score != score {
+                            app_ctx.send(ClientToServer::SetScore {
+                                game_code: code,
+                                player_id,
+                                score: new_score,
+                                reason: host_ctx.take_reason(),
+                            });
+                        }
+                    }
+                },
+            }
             div {
                 class: "score-buttons-container",
                 button {
@@ -213,8 +328,9 @@ pub fn PlayerListItem(player_id: Uuid, player_name: String, score: i32, is_disco
                         if let Some(code) = *app_ctx.game_code.read() {
                             app_ctx.send(ClientToServer::UpdateScore {
                                 game_code: code,
-                                player_id: player_id,
-                                delta: *host_ctx.score_delta.read(),
+                                player_id,
+                                delta: score_step(app_ctx),
+                                reason: host_ctx.take_reason(),
                             });
                         }
                     },
@@ -226,8 +342,9 @@ pub fn PlayerListItem(player_id: Uuid, player_name: String, score: i32, is_disco
                         if let Some(code) = *app_ctx.game_code.read() {
                             app_ctx.send(ClientToServer::UpdateScore {
                                 game_code: code,
-                                player_id: player_id,
-                                delta: -(*host_ctx.score_delta.read()),
+                                player_id,
+                                delta: -score_step(app_ctx),
+                                reason: host_ctx.take_reason(),
                             });
                         }
                     },
@@ -238,6 +355,288 @@ pub fn PlayerListItem(player_id: Uuid, player_name: String, score: i32, is_disco
     }
 }
 
+/// Every score change of the game, newest first, with undo and redo.
+#[component]
+fn ScoreHistory() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let game_state = app_ctx.game_state.read();
+    let Some(game) = game_state.as_ref() else {
+        return rsx! {};
+    };
+    let can_undo = game.score_ledger.undoable().is_some();
+    let can_redo = game.score_ledger.redoable().is_some();
+    let rows: Vec<(u64, String, bool)> = game
+        .score_ledger
+        .entries
+        .iter()
+        .rev()
+        .map(|entry| {
+            let name = game
+                .players
+                .get(&entry.player_id)
+                .map_or_else(|| "Unknown player".to_string(), |p| p.name().to_string());
+            let question = format!("R{} Q{}", entry.round.max(1), entry.question.max(1));
+            let mut text = match entry.set_to {
+                Some(score) => format!("{} {}: set to {} ({:+})", question, name, score, entry.delta),
+                None => format!("{} {}: {:+}", question, name, entry.delta),
+            };
+            if let Some(position) = entry.buzz_position {
+                text.push_str(&format!(", buzzed #{}", position + 1));
+            }
+            if let Some(reason) = &entry.reason {
+                text.push_str(&format!(" — {}", reason));
+            }
+            (entry.id, text, entry.undone)
+        })
+        .collect();
+
+    let on_undo = move |_| {
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(ClientToServer::Undo { game_code: code });
+        }
+    };
+    let on_redo = move |_| {
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(ClientToServer::Redo { game_code: code });
+        }
+    };
+
+    rsx! {
+        div {
+            class: "score-history-container",
+            div {
+                class: "player-list-header",
+                h3 { "Score History" }
+                button { class: "control-button", disabled: !can_undo, onclick: on_undo, "Undo" }
+                button { class: "control-button", disabled: !can_redo, onclick: on_redo, "Redo" }
+            }
+            if rows.is_empty() {
+                p { class: "score-history-empty", "No score changes yet." }
+            }
+            ol {
+                class: "score-history",
+                for (id, text, undone) in rows {
+                    li {
+                        key: "{id}",
+                        class: if undone { "score-history-item undone" } else { "score-history-item" },
+                        "{text}"
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The step of the host's +/- score buttons
+fn score_step(app_ctx: AppContext) -> i32 {
+    app_ctx
+        .game_state
+        .read()
+        .as_ref()
+        .map_or(GameRules::default().points_correct, |game| game.rules.points_correct)
+}
+
+/// Editors for the game's `GameRules`; every change is sent to the server,
+/// which broadcasts the new rules to everyone.
+#[component]
+fn RulesSettings() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let rules = app_ctx
+        .game_state
+        .read()
+        .as_ref()
+        .map(|game| game.rules)
+        .unwrap_or_default();
+    let send_rules = move |rules: GameRules| {
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(ClientToServer::SetRules {
+                game_code: code,
+                rules,
+            });
+        }
+    };
+
+    rsx! {
+        div {
+            class: "setting-item",
+            label { r#for: "points-input", "Points for a correct answer:" }
+            input {
+                r#type: "number",
+                id: "points-input",
+                min: "0",
+                value: "{rules.points_correct}",
+                onchange: move |evt| {
+                    if let Ok(val) = evt.value().parse::<i32>() {
+                        send_rules(GameRules { points_correct: val.max(0), ..rules });
+                    }
+                }
+            }
+        }
+        div {
+            class: "setting-item",
+            label { r#for: "penalty-input", "Penalty for a wrong answer:" }
+            input {
+                r#type: "number",
+                id: "penalty-input",
+                min: "0",
+                value: "{rules.penalty_wrong}",
+                onchange: move |evt| {
+                    if let Ok(val) = evt.value().parse::<i32>() {
+                        send_rules(GameRules { penalty_wrong: val.max(0), ..rules });
+                    }
+                }
+            }
+        }
+        div {
+            class: "setting-item",
+            label { r#for: "auto-lock-buzz-input", "Lock buzzers after the first buzz:" }
+            input {
+                r#type: "checkbox",
+                id: "auto-lock-buzz-input",
+                checked: rules.auto_lock_after_first_buzz,
+                onchange: move |evt| {
+                    send_rules(GameRules { auto_lock_after_first_buzz: evt.checked(), ..rules });
+                }
+            }
+        }
+        div {
+            class: "setting-item",
+            label { r#for: "max-queue-input", "Most players in the queue (0 = no limit):" }
+            input {
+                r#type: "number",
+                id: "max-queue-input",
+                min: "0",
+                value: "{rules.max_queue_length}",
+                onchange: move |evt| {
+                    if let Ok(val) = evt.value().parse::<usize>() {
+                        send_rules(GameRules { max_queue_length: val, ..rules });
+                    }
+                }
+            }
+        }
+        div {
+            class: "setting-item",
+            label { r#for: "rebuzz-input", "Allow buzzing again after a wrong answer:" }
+            input {
+                r#type: "checkbox",
+                id: "rebuzz-input",
+                checked: rules.allow_rebuzz_after_wrong,
+                onchange: move |evt| {
+                    send_rules(GameRules { allow_rebuzz_after_wrong: evt.checked(), ..rules });
+                }
+            }
+        }
+        div {
+            class: "setting-item",
+            label { r#for: "team-buzz-input", "Only one buzz per team:" }
+            input {
+                r#type: "checkbox",
+                id: "team-buzz-input",
+                checked: rules.one_buzz_per_team,
+                onchange: move |evt| {
+                    send_rules(GameRules { one_buzz_per_team: evt.checked(), ..rules });
+                }
+            }
+        }
+        div {
+            class: "setting-item",
+            label { r#for: "create-teams-input", "Players may start teams:" }
+            input {
+                r#type: "checkbox",
+                id: "create-teams-input",
+                checked: rules.players_create_teams,
+                onchange: move |evt| {
+                    send_rules(GameRules { players_create_teams: evt.checked(), ..rules });
+                }
+            }
+        }
+        div {
+            class: "setting-item",
+            label { r#for: "time-weighted-input", "Faster multiple-choice answers score more:" }
+            input {
+                r#type: "checkbox",
+                id: "time-weighted-input",
+                checked: rules.time_weighted_choices,
+                onchange: move |evt| {
+                    send_rules(GameRules { time_weighted_choices: evt.checked(), ..rules });
+                }
+            }
+        }
+        div {
+            class: "setting-item",
+            label { r#for: "answer-time-input", "Seconds to answer (0 = no limit):" }
+            input {
+                r#type: "number",
+                id: "answer-time-input",
+                min: "0",
+                value: "{rules.answer_time_limit_secs}",
+                onchange: move |evt| {
+                    if let Ok(val) = evt.value().parse::<u32>() {
+                        send_rules(GameRules { answer_time_limit_secs: val, ..rules });
+                    }
+                }
+            }
+        }
+        div {
+            class: "setting-item",
+            label { r#for: "false-start-input", "Seconds out for buzzing before the unlock (0 = off):" }
+            input {
+                r#type: "number",
+                id: "false-start-input",
+                min: "0",
+                value: "{rules.false_start_lockout_secs}",
+                onchange: move |evt| {
+                    if let Ok(val) = evt.value().parse::<u32>() {
+                        send_rules(GameRules { false_start_lockout_secs: val, ..rules });
+                    }
+                }
+            }
+        }
+        div {
+            class: "setting-item",
+            label { r#for: "false-start-window-input", "Also count presses this many ms before the unlock:" }
+            input {
+                r#type: "number",
+                id: "false-start-window-input",
+                min: "0",
+                value: "{rules.false_start_window_ms}",
+                onchange: move |evt| {
+                    if let Ok(val) = evt.value().parse::<u64>() {
+                        send_rules(GameRules { false_start_window_ms: val, ..rules });
+                    }
+                }
+            }
+        }
+        div {
+            class: "setting-item",
+            label { r#for: "false-start-penalty-input", "Penalty for a false start:" }
+            input {
+                r#type: "number",
+                id: "false-start-penalty-input",
+                min: "0",
+                value: "{rules.false_start_penalty}",
+                onchange: move |evt| {
+                    if let Ok(val) = evt.value().parse::<i32>() {
+                        send_rules(GameRules { false_start_penalty: val.max(0), ..rules });
+                    }
+                }
+            }
+        }
+        div {
+            class: "setting-item",
+            label { r#for: "answer-timeout-input", "Count an answer as wrong when its time is up:" }
+            input {
+                r#type: "checkbox",
+                id: "answer-timeout-input",
+                checked: rules.wrong_when_answer_time_up,
+                onchange: move |evt| {
+                    send_rules(GameRules { wrong_when_answer_time_up: evt.checked(), ..rules });
+                }
+            }
+        }
+    }
+}
+
 #[component]
 fn CreateLink() -> Element {
     let app_ctx = use_context::<AppContext>();
@@ -323,7 +722,22 @@ fn CopyButton() -> Element {
 #[component]
 fn SettingsMenu(is_open: Signal<bool>, file_url: Signal<Option<String>>) -> Element {
     let mut app_ctx = use_context::<AppContext>();
-    let mut host_ctx = use_context::<HostContext>();
+    let (auto_lock, pause_on_buzz) = app_ctx
+        .game_state
+        .read()
+        .as_ref()
+        .map_or((false, false), |game| {
+            (game.countdown.auto_lock, game.countdown.pause_on_buzz)
+        });
+    let send_countdown_options = move |auto_lock: bool, pause_on_buzz: bool| {
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(ClientToServer::SetCountdownOptions {
+                game_code: code,
+                auto_lock,
+                pause_on_buzz,
+            });
+        }
+    };
 
     rsx! {
         div {
@@ -333,21 +747,7 @@ fn SettingsMenu(is_open: Signal<bool>, file_url: Signal<Option<String>>) -> Elem
         div {
             class: "settings-menu",
             h2 { "Settings" }
-            div {
-                class: "setting-item",
-                label { r#for: "delta-input", "Score Increment:" }
-                input {
-                    r#type: "number",
-                    id: "delta-input",
-                    min: "1",
-                    value: "{host_ctx.score_delta}",
-                    oninput: move |evt| {
-                        if let Ok(val) = evt.value().parse::<i32>() {
-                            host_ctx.score_delta.set(val.max(1));
-                        }
-                    }
-                }
-            }
+            RulesSettings {}
             div {
                 class: "setting-item",
                 label { r#for: "sound-select", "Buzzer Sound:" }
@@ -368,6 +768,41 @@ fn SettingsMenu(is_open: Signal<bool>, file_url: Signal<Option<String>>) -> Elem
                     }
                 }
             }
+            div {
+                class: "setting-item",
+                label { r#for: "auto-lock-input", "Lock buzzers when time is up:" }
+                input {
+                    r#type: "checkbox",
+                    id: "auto-lock-input",
+                    checked: auto_lock,
+                    onchange: move |evt| send_countdown_options(evt.checked(), pause_on_buzz),
+                }
+            }
+            div {
+                class: "setting-item",
+                label { r#for: "pause-on-buzz-input", "Pause timer on first buzz:" }
+                input {
+                    r#type: "checkbox",
+                    id: "pause-on-buzz-input",
+                    checked: pause_on_buzz,
+                    onchange: move |evt| send_countdown_options(auto_lock, evt.checked()),
+                }
+            }
+            if let Some(code) = *app_ctx.game_code.read() {
+                div {
+                    class: "setting-item",
+                    Link {
+                        to: crate::Route::Replay { code },
+                        new_tab: true,
+                        "Open the replay of this game"
+                    }
+                }
+            }
+            div {
+                class: "setting-item",
+                label { r#for: "questions-upload", "Import questions (JSON or CSV):" }
+                QuestionImport {}
+            }
             div {
                 class: "setting-item",
                 label { r#for: "pdf-upload", "Upload PDF:" }
@@ -471,16 +906,49 @@ fn FileUploader(mut file_url: Signal<Option<String>>) -> Element {
 #[component]
 pub fn PlayerBuzzOrderListHost(children: Element) -> Element {
     let app_ctx = use_context::<AppContext>();
+    let mut host_ctx = use_context::<HostContext>();
+    let (countdown_running, countdown_paused, answer_time_up) = app_ctx
+        .game_state
+        .read()
+        .as_ref()
+        .map_or((false, false, false), |game| {
+            (
+                game.countdown.running,
+                game.countdown.remaining_ms > 0,
+                game.answer_clock.as_ref().is_some_and(|clock| clock.expired),
+            )
+        });
     let order: Vec<_> = if let Some(ref game_state) = *app_ctx.game_state.read() {
+        // Corrected press times relative to the winner, so close calls can be settled
+        let first_press_ms = game_state
+            .buzzer_order
+            .front()
+            .and_then(|(id, _)| game_state.buzz_timings.get(id))
+            .map(|timing| timing.press_time_ms);
         game_state.buzzer_order
             .iter()
             .filter_map(|p| {
-                game_state.scores.get(&p.0).map(|score| (p.0, p.1.clone(), score.clone(), false))
+                let note = game_state
+                    .buzz_timings
+                    .get(&p.0)
+                    .zip(first_press_ms)
+                    .map(|(timing, first)| buzz_timing_note(timing, first));
+                game_state.scores.get(&p.0).map(|score| (p.0, p.1.clone(), *score, false, note))
             })
             .collect()
     } else {
         vec![]
     };
+    // The head of the queue is the player whose answer is up for judging
+    let answerer = order.first().map(|(_, player_name, ..)| player_name.clone());
+    let judge = move |correct: bool| {
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(ClientToServer::Judge {
+                game_code: code,
+                correct,
+            });
+        }
+    };
 
     rsx! {
         div {
@@ -492,31 +960,93 @@ pub fn PlayerBuzzOrderListHost(children: Element) -> Element {
                 { children }
             }
             div {
+                input {
+                    r#type: "number",
+                    class: "timer-duration-input",
+                    "aria-label": "Countdown seconds",
+                    min: "1",
+                    value: "{host_ctx.countdown_secs}",
+                    oninput: move |evt| {
+                        if let Ok(val) = evt.value().parse::<u32>() {
+                            host_ctx.countdown_secs.set(val.max(1));
+                        }
+                    }
+                }
                 button {
                     class: "control-button",
                     onclick: move |_| {
                         if let Some(code) = *app_ctx.game_code.read() {
                             app_ctx.send(ClientToServer::StartCountdown {
                                 game_code: code,
-                                time_limit: 10,
+                                time_limit: *host_ctx.countdown_secs.read(),
                             });
                         }
                     },
                     "Start Timer"
                 }
+                if countdown_running {
+                    button {
+                        class: "control-button",
+                        onclick: move |_| {
+                            if let Some(code) = *app_ctx.game_code.read() {
+                                app_ctx.send(ClientToServer::PauseTimer { game_code: code });
+                            }
+                        },
+                        "Pause"
+                    }
+                } else if countdown_paused {
+                    button {
+                        class: "control-button",
+                        onclick: move |_| {
+                            if let Some(code) = *app_ctx.game_code.read() {
+                                app_ctx.send(ClientToServer::ResumeTimer { game_code: code });
+                            }
+                        },
+                        "Resume"
+                    }
+                }
             }
         }
-        if !order.is_empty() {
+        if let Some(answerer) = answerer {
+            div {
+                class: "current-answerer",
+                span { class: "player-name", "Answering: {answerer}" }
+                if answer_time_up {
+                    span { class: "answer-time-up", "Time's up! Wrong answer?" }
+                }
+                button {
+                    class: "judge-button correct",
+                    onclick: move |_| judge(true),
+                    "Correct"
+                }
+                button {
+                    class: "judge-button wrong",
+                    onclick: move |_| judge(false),
+                    "Wrong"
+                }
+            }
             ol { class: "player-list buzzed-order-list",
-                for (player_id, player_name, score, is_disconnected) in order {
+                for (player_id, player_name, score, is_disconnected, buzz_note) in order {
                     PlayerListItem {
-                        player_id: player_id,
+                        player_id,
                         player_name: player_name,
                         score: score,
                         is_disconnected: is_disconnected,
+                        buzz_note: buzz_note,
                     }
                 }
             }
         }
     }
 }
+
+fn buzz_timing_note(timing: &BuzzTiming, first_press_ms: u64) -> String {
+    let delta_ms = timing.press_time_ms.saturating_sub(first_press_ms);
+    match timing.clock {
+        Some(clock) => format!(
+            "+{} ms (offset {} ms, rtt {} ms)",
+            delta_ms, clock.offset_ms, clock.rtt_ms
+        ),
+        None => format!("+{} ms (uncorrected)", delta_ms),
+    }
+}
@@ -6,22 +6,36 @@ use gloo_net::websocket::{futures::WebSocket, Message};
 use host::*;
 use log::{error, info};
 use player::PlayerView;
-use std::fmt;
+use replay::Replay;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
 use uuid::Uuid;
 use web_sys::HtmlAudioElement;
 
+mod board;
+mod choices;
 mod host;
 mod player;
+mod questions;
+mod replay;
+mod rounds;
+mod session;
+mod teams;
 mod timer;
+mod wagers;
+mod written;
 
 static CSS: Asset = asset!("/assets/main.css");
-const SOUND_OPTIONS: [(&'static str, &'static str); 4] = [
+const SOUND_OPTIONS: [(&str, &str); 4] = [
     ("Default Buzzer", "../assets/sounds/ding-101492.mp3"),
     ("New notification", "../assets/sounds/new-notification-09-352705.mp3"),
     ("Pop", "../assets/sounds/pop-39222.mp3"),
     ("Bubble pop", "../assets/sounds/bubble-pop-389501.mp3"),
 ];
 const FAVICON: Asset = asset!("/assets/favicon.svg");
+const RECONNECT_DELAY_MS: u32 = 1_000;
 
 #[derive(Clone, Copy)]
 struct AppContext {
@@ -30,12 +44,28 @@ struct AppContext {
     player_id: Signal<Option<Uuid>>,
     player_name: Signal<Option<String>>,
     game_code: Signal<Option<usize>>,
+    session_token: Signal<Option<Uuid>>,
+    host_grace_period: Signal<Option<u64>>,
+    // How far our clock is ahead of the server's, as estimated by the server
+    clock_offset_ms: Signal<i64>,
+    // Set after a gap in the delta sequence until the requested snapshot arrives
+    awaiting_snapshot: Signal<bool>,
+    next_request_id: Signal<u64>,
+    // Requests we already applied locally and that the server has not answered yet
+    pending_requests: Signal<HashSet<u64>>,
     error_message: Signal<Option<String>>,
+    // Set when the server no longer speaks our protocol version
+    upgrade_required: Signal<bool>,
     locally_locked: Signal<bool>,
     buzzer_sound: Signal<String>,
     is_host: Signal<bool>,
-    time_limit: Signal<Option<u32>>,
     url: Signal<String>,
+    // The question set with answers; only the host ever receives it
+    question_bank: Signal<Vec<Question>>,
+    // Texts of the written answers to the current question; host only
+    answer_texts: Signal<HashMap<Uuid, String>>,
+    // Every player's bet for the wager question; host only
+    wager_amounts: Signal<HashMap<Uuid, i32>>,
 }
 
 impl fmt::Debug for AppContext {
@@ -50,9 +80,22 @@ impl fmt::Debug for AppContext {
 
 impl AppContext {
     fn send(&self, msg: ClientToServer) {
+        let optimistic = self.apply_optimistic(&msg);
+        // Clock replies are answered by the next ping, everything else gets an id
+        let request_id = (!matches!(msg, ClientToServer::ClockPong { .. })).then(|| {
+            let mut next_request_id = self.next_request_id;
+            let request_id = *next_request_id.peek() + 1;
+            next_request_id.set(request_id);
+            request_id
+        });
+        if let Some(request_id) = request_id.filter(|_| optimistic) {
+            let mut pending_requests = self.pending_requests;
+            pending_requests.write().insert(request_id);
+        }
+        let request = ClientRequest { request_id, msg };
         let mut ws_tx_signal = self.ws_tx;
         spawn(async move {
-            let json_msg = serde_json::to_string(&msg).unwrap();
+            let json_msg = serde_json::to_string(&request).unwrap();
             // 1. Lock the signal and TAKE the sender, leaving `None` behind.
             //    This gives this task full ownership of the sender.
             let sender = ws_tx_signal.write().take();
@@ -73,6 +116,73 @@ impl AppContext {
             }
         });
     }
+
+    /// Runs our own in-game actions through the shared rules right away so the
+    /// UI reacts before the round trip. The server's next state update wins.
+    /// Returns whether the local state was changed.
+    fn apply_optimistic(&self, msg: &ClientToServer) -> bool {
+        if msg.permission() == Permission::Anyone {
+            return false;
+        }
+        // The server makes up the ids of new teams, and a guessed one would
+        // leave a duplicate behind once the real team arrives
+        if matches!(
+            msg,
+            ClientToServer::CreateTeam { .. } | ClientToServer::ChooseTeam { .. }
+        ) {
+            return false;
+        }
+        let Some(my_id) = *self.player_id.peek() else {
+            return false;
+        };
+        let mut game_state = self.game_state;
+        let Some(mut game) = game_state.peek().clone() else {
+            return false;
+        };
+        let ctx = ApplyContext {
+            now_ms: (timer::now_ms() as i64 - *self.clock_offset_ms.peek()).max(0) as u64,
+            ..Default::default()
+        };
+        // Only the server numbers changes, so a guess must not advance the sequence
+        let seq = game.seq;
+        if game.apply(my_id, msg.clone(), &ctx).is_err() {
+            return false;
+        }
+        game.seq = seq;
+        game_state.set(Some(game));
+        true
+    }
+
+    /// Forgets the current game and its session, e.g. once it was closed.
+    fn leave_game(&mut self, reason: &str, nav: Navigator) {
+        if let Some(code) = *self.game_code.peek() {
+            session::forget_token(code);
+        }
+        *self.session_token.write() = None;
+        *self.host_grace_period.write() = None;
+        *self.game_state.write() = None;
+        *self.game_code.write() = None;
+        self.question_bank.write().clear();
+        self.answer_texts.write().clear();
+        self.wager_amounts.write().clear();
+        *self.error_message.write() = Some(reason.to_string());
+        nav.push(Route::Home {});
+    }
+
+    /// Tries to resume a previous session for `code` using the stored token.
+    /// Returns false if there is nothing to resume.
+    fn rejoin(&mut self, code: usize) -> bool {
+        let Some(token) = session::load_token(code) else {
+            return false;
+        };
+        *self.game_code.write() = Some(code);
+        *self.session_token.write() = Some(token);
+        self.send(ClientToServer::Rejoin {
+            game_code: code,
+            token,
+        });
+        true
+    }
 }
 
 fn main() {
@@ -95,12 +205,21 @@ fn AppLayout() -> Element {
     let player_id = use_signal::<Option<Uuid>>(|| None);
     let player_name = use_signal::<Option<String>>(|| None);
     let game_code = use_signal::<Option<usize>>(|| None);
+    let session_token = use_signal::<Option<Uuid>>(|| None);
+    let host_grace_period = use_signal::<Option<u64>>(|| None);
+    let clock_offset_ms = use_signal::<i64>(|| 0);
+    let awaiting_snapshot = use_signal(|| false);
+    let next_request_id = use_signal(|| 0u64);
+    let pending_requests = use_signal(HashSet::<u64>::new);
     let error_message = use_signal::<Option<String>>(|| None);
+    let upgrade_required = use_signal(|| false);
     let locally_locked = use_signal::<bool>(|| false);
     let buzzer_sound = use_signal(|| "../assets/ding-101492.mp3".to_string());
     let is_host = use_signal(|| false);
-    let time_limit = use_signal::<Option<u32>>(|| Some(5));
     let url = use_signal::<String>(|| "".to_owned());
+    let question_bank = use_signal(Vec::<Question>::new);
+    let answer_texts = use_signal(HashMap::<Uuid, String>::new);
+    let wager_amounts = use_signal(HashMap::<Uuid, i32>::new);
 
     // Provide the context to all child components
     let mut app_ctx = use_context_provider(|| AppContext {
@@ -109,12 +228,21 @@ fn AppLayout() -> Element {
         player_id,
         player_name,
         game_code,
+        session_token,
+        host_grace_period,
+        clock_offset_ms,
+        awaiting_snapshot,
+        next_request_id,
+        pending_requests,
         error_message,
+        upgrade_required,
         locally_locked,
         buzzer_sound,
         is_host,
-        time_limit,
-        url
+        url,
+        question_bank,
+        answer_texts,
+        wager_amounts,
     });
 
     let nav = navigator();
@@ -136,99 +264,89 @@ fn AppLayout() -> Element {
                     (host.clone(), format!("{}//{}/ws", ws_protocol, host))
                 }
             };
-            let ws = WebSocket::open(&ws_url).expect("Failed to open WebSocket");
-            info!("WebSocket connection opened");
-            let (tx, mut rx) = ws.split();
-            *app_ctx.ws_tx.write() = Some(tx);
-            *app_ctx.url.write() = url.to_owned();
-            // This loop listens for messages from the server
-            while let Some(Ok(Message::Text(text))) = rx.next().await {
-                match serde_json::from_str::<ServerToClient>(&text) {
-                    Ok(msg) => {
-                        info!("Received message: {:?}", msg);
-                        // Clear previous error on new message
-                        *app_ctx.error_message.write() = None;
-
-                        match msg {
-                            ServerToClient::GameCreated {
-                                game_code: code,
-                                player_id: id,
-                                game_state: state,
-                            } => {
-                                *app_ctx.game_code.write() = Some(code.clone());
-                                *app_ctx.player_id.write() = Some(id);
-                                *app_ctx.game_state.write() = Some(state.into());
-
-                                nav.push(Route::GameRoom { code });
-                            }
-                            ServerToClient::GameJoined {
-                                player_id: id,
-                                player_name,
-                                game_state: state,
-                            } => {
-                                info!("ServerToClient: game joined");
-                                *app_ctx.player_id.write() = Some(id);
-                                *app_ctx.player_name.write() = Some(player_name);
-                                *app_ctx.game_state.write() = Some(state.into());
-                                if let Some(code) = app_ctx.game_code.read().clone() {
-                                    info!("Navigate to GameRoom");
-                                    nav.push(Route::GameRoom { code });
-                                }
-                            }
-                            ServerToClient::GameStateUpdate { game_state: state } => {
-                                let state: GameState = state.into();
-                                if state.globally_locked != *app_ctx.locally_locked.read() {
-                                    *app_ctx.locally_locked.write() = state.globally_locked;
-                                }
-                                *app_ctx.game_state.write() = Some(state);
-                            }
-                            ServerToClient::PlayerBuzzed {
-                                player_id: _,
-                                player_name,
-                            } => {
-                                if *app_ctx.is_host.read() {
-                                    let is_first_buzz = app_ctx.game_state.read()
-                                        .as_ref()
-                                        .map_or(false, |gs| gs.buzzer_order.is_empty());
-
-                                    if is_first_buzz {
-                                        log::info!(
-                                            "First player '{}' buzzed! Playing sound for host.",
-                                            player_name
-                                        );
-
-                                        // Get the sound selected in the settings menu
-                                        let sound_src = app_ctx.buzzer_sound.read().clone();
-                                        if let Ok(audio) = HtmlAudioElement::new_with_src(&sound_src) {
-                                            let _ = audio.play();
-                                        }
-                                    } else {
-                                         log::info!(
-                                            "Player '{}' buzzed, but was not first. Sound suppressed.",
-                                            player_name
-                                        );
-                                    }
-                                }
-                            }
-                            ServerToClient::CountdownStarted { time_limit }  => {
-                                // start counting down in the timer component
-                            }
-                            ServerToClient::TimerPaused { paused } => {}
-                            ServerToClient::Error { message } => {
-                                *app_ctx.error_message.write() = Some(message);
+            loop {
+                let ws = match WebSocket::open(&ws_url) {
+                    Ok(ws) => ws,
+                    Err(e) => {
+                        error!("Failed to open WebSocket: {}", e);
+                        gloo_timers::future::TimeoutFuture::new(RECONNECT_DELAY_MS).await;
+                        continue;
+                    }
+                };
+                info!("WebSocket connection opened");
+                let (mut tx, mut rx) = ws.split();
+
+                let hello = ClientToServer::Hello {
+                    protocol_version: PROTOCOL_VERSION,
+                    capabilities: CAPABILITIES.to_vec(),
+                };
+                let json_msg = serde_json::to_string(&hello).unwrap();
+                if tx.send(Message::Text(json_msg)).await.is_err() {
+                    error!("Failed to send hello.");
+                }
+
+                // Resume our seat if we were already in a game before the connection dropped
+                let resume = (*app_ctx.game_code.read(), *app_ctx.session_token.read());
+                if let (Some(game_code), Some(token)) = resume {
+                    let rejoin = ClientToServer::Rejoin { game_code, token };
+                    let json_msg = serde_json::to_string(&rejoin).unwrap();
+                    if tx.send(Message::Text(json_msg)).await.is_err() {
+                        error!("Failed to send rejoin request.");
+                    }
+                }
+
+                *app_ctx.ws_tx.write() = Some(tx);
+                *app_ctx.url.write() = url.to_owned();
+                // This loop listens for messages from the server
+                while let Some(Ok(Message::Text(text))) = rx.next().await {
+                    match serde_json::from_str::<ServerToClient>(&text) {
+                        Ok(msg) => {
+                            let is_housekeeping = matches!(
+                                msg,
+                                ServerToClient::ClockPing { .. }
+                                    | ServerToClient::ClockSync { .. }
+                                    | ServerToClient::Ack { .. }
+                            );
+                            if !is_housekeeping {
+                                info!("Received message: {:?}", msg);
+                                // Clear previous error on new message
+                                *app_ctx.error_message.write() = None;
                             }
+                            handle_s2c_message(msg, app_ctx, nav);
                         }
+                        Err(e) => error!("Failed to parse S2C message: {}", e),
                     }
-                    Err(e) => error!("Failed to parse S2C message: {}", e),
                 }
+                *app_ctx.ws_tx.write() = None;
+                // Reconnecting would only be refused again; wait for the user to reload
+                if *app_ctx.upgrade_required.peek() {
+                    info!("WebSocket connection closed, a reload is required");
+                    return;
+                }
+                info!("WebSocket connection closed, reconnecting...");
+                gloo_timers::future::TimeoutFuture::new(RECONNECT_DELAY_MS).await;
             }
-            info!("WebSocket connection closed!");
         });
     });
 
     rsx! {
         document::Link { rel: "icon", href: FAVICON }
         document::Stylesheet { href: CSS }
+        if (app_ctx.upgrade_required)() {
+            div {
+                class: "upgrade-notice",
+                p { "A new version of Buzzer is available. Reload the page to keep playing." }
+                button {
+                    class: "control-button",
+                    onclick: move |_| {
+                        if let Some(window) = web_sys::window() {
+                            let _ = window.location().reload();
+                        }
+                    },
+                    "Reload"
+                }
+            }
+        }
         div {
             class: "app-container",
             Outlet::<Route> {}
@@ -236,6 +354,275 @@ fn AppLayout() -> Element {
     }
 }
 
+fn handle_s2c_message(msg: ServerToClient, mut app_ctx: AppContext, nav: Navigator) {
+    match msg {
+        ServerToClient::Welcome {
+            protocol_version,
+            capabilities,
+        } => {
+            info!(
+                "Server speaks protocol {} with {:?}",
+                protocol_version, capabilities
+            );
+        }
+        ServerToClient::UpgradeRequired {
+            protocol_version,
+            min_protocol_version,
+        } => {
+            error!(
+                "Protocol {} is not supported, the server wants {}..={}",
+                PROTOCOL_VERSION, min_protocol_version, protocol_version
+            );
+            *app_ctx.upgrade_required.write() = true;
+        }
+        ServerToClient::GameCreated {
+            game_code: code,
+            player_id: id,
+            host_token,
+            game_state: state,
+        } => {
+            session::store_token(code, host_token);
+            *app_ctx.session_token.write() = Some(host_token);
+            *app_ctx.game_code.write() = Some(code);
+            *app_ctx.player_id.write() = Some(id);
+            *app_ctx.awaiting_snapshot.write() = false;
+            *app_ctx.game_state.write() = Some(state.into());
+
+            nav.push(Route::GameRoom { code });
+        }
+        ServerToClient::GameJoined {
+            player_id: id,
+            player_name,
+            session_token,
+            game_state: state,
+        } => {
+            info!("ServerToClient: game joined");
+            if let Some(code) = *app_ctx.game_code.read() {
+                session::store_token(code, session_token);
+            }
+            *app_ctx.session_token.write() = Some(session_token);
+            *app_ctx.player_id.write() = Some(id);
+            *app_ctx.player_name.write() = Some(player_name);
+            *app_ctx.awaiting_snapshot.write() = false;
+            *app_ctx.game_state.write() = Some(state.into());
+            if let Some(code) = *app_ctx.game_code.read() {
+                info!("Navigate to GameRoom");
+                nav.push(Route::GameRoom { code });
+            }
+        }
+        ServerToClient::GameStateUpdate { game_state: state } => {
+            let mut state: GameState = state.into();
+            // Snapshots leave out the ledger and the history; keep ours
+            if let Some(old) = app_ctx.game_state.write().as_mut() {
+                state.score_ledger = std::mem::take(&mut old.score_ledger);
+                state.progress.history = std::mem::take(&mut old.progress.history);
+            }
+            if state.globally_locked != *app_ctx.locally_locked.read() {
+                *app_ctx.locally_locked.write() = state.globally_locked;
+            }
+            *app_ctx.awaiting_snapshot.write() = false;
+            *app_ctx.game_state.write() = Some(state);
+        }
+        ServerToClient::Delta { seq, delta } => {
+            let Some(check) = app_ctx.game_state.peek().as_ref().map(|gs| gs.check_sequence(seq))
+            else {
+                return;
+            };
+            match check {
+                SequenceCheck::Next => {
+                    let mut game_state = app_ctx.game_state.write();
+                    if let Some(gs) = game_state.as_mut() {
+                        gs.apply_delta(seq, delta);
+                        let locked = gs.globally_locked;
+                        drop(game_state);
+                        if locked != *app_ctx.locally_locked.peek() {
+                            *app_ctx.locally_locked.write() = locked;
+                        }
+                    }
+                }
+                SequenceCheck::Stale => {}
+                SequenceCheck::Gap => {
+                    // Ask once; every delta until then is covered by the snapshot
+                    if *app_ctx.awaiting_snapshot.peek() {
+                        return;
+                    }
+                    let Some(game_code) = *app_ctx.game_code.peek() else {
+                        return;
+                    };
+                    info!("Missed updates before #{}, requesting a snapshot", seq);
+                    *app_ctx.awaiting_snapshot.write() = true;
+                    app_ctx.send(ClientToServer::RequestSnapshot { game_code });
+                }
+            }
+        }
+        ServerToClient::PlayerBuzzed {
+            player_id: _,
+            player_name,
+        } => {
+            if *app_ctx.is_host.read() {
+                let is_first_buzz = app_ctx.game_state.read()
+                    .as_ref()
+                    .is_some_and(|gs| gs.buzzer_order.is_empty());
+
+                if is_first_buzz {
+                    log::info!(
+                        "First player '{}' buzzed! Playing sound for host.",
+                        player_name
+                    );
+
+                    // Get the sound selected in the settings menu
+                    let sound_src = app_ctx.buzzer_sound.read().clone();
+                    if let Ok(audio) = HtmlAudioElement::new_with_src(&sound_src) {
+                        let _ = audio.play();
+                    }
+                } else {
+                     log::info!(
+                        "Player '{}' buzzed, but was not first. Sound suppressed.",
+                        player_name
+                    );
+                }
+            }
+        }
+        // The countdown itself lives in the game state, which the server
+        // broadcasts right after each of these events.
+        ServerToClient::CountdownStarted { time_limit, .. } => {
+            info!("Countdown of {} seconds started", time_limit);
+        }
+        ServerToClient::TimerPaused { .. } | ServerToClient::TimerResumed { .. } => {}
+        ServerToClient::TimerExpired => {
+            info!("Countdown expired");
+        }
+        ServerToClient::ClockPing { server_time_ms } => {
+            app_ctx.send(ClientToServer::ClockPong {
+                server_time_ms,
+                client_time_ms: timer::now_ms(),
+            });
+        }
+        ServerToClient::ClockSync { offset_ms, .. } => {
+            *app_ctx.clock_offset_ms.write() = offset_ms;
+        }
+        ServerToClient::HostDisconnected { grace_period_secs } => {
+            *app_ctx.host_grace_period.write() = Some(grace_period_secs);
+        }
+        ServerToClient::HostReconnected => {
+            *app_ctx.host_grace_period.write() = None;
+        }
+        ServerToClient::GameClosed => {
+            app_ctx.leave_game("The host left and the game was closed.", nav);
+        }
+        ServerToClient::QuestionBank { questions } => {
+            app_ctx.question_bank.set(questions);
+        }
+        ServerToClient::AnswerTexts { texts } => {
+            app_ctx.answer_texts.set(texts);
+        }
+        ServerToClient::WagerAmounts { amounts } => {
+            app_ctx.wager_amounts.set(amounts);
+        }
+        ServerToClient::GameRecords {
+            score_ledger,
+            history,
+        } => {
+            if let Some(gs) = app_ctx.game_state.write().as_mut() {
+                gs.score_ledger = score_ledger;
+                gs.progress.history = history;
+            }
+        }
+        ServerToClient::ScoreRecorded { entry } => {
+            if let Some(gs) = app_ctx.game_state.write().as_mut() {
+                gs.score_ledger.upsert(entry);
+            }
+        }
+        ServerToClient::QuestionArchived { record } => {
+            if let Some(gs) = app_ctx.game_state.write().as_mut() {
+                gs.add_archived_question(record);
+            }
+        }
+        ServerToClient::Ack { request_id } => {
+            app_ctx.pending_requests.write().remove(&request_id);
+        }
+        ServerToClient::Error {
+            request_id,
+            code,
+            message,
+        } => handle_error(request_id, code, message, app_ctx, nav),
+    }
+}
+
+fn handle_error(
+    request_id: Option<u64>,
+    code: ErrorCode,
+    message: String,
+    mut app_ctx: AppContext,
+    nav: Navigator,
+) {
+    // Undo our optimistic guess by fetching the server's version of the game
+    let was_applied = request_id.is_some_and(|id| app_ctx.pending_requests.write().remove(&id));
+    if was_applied {
+        if let Some(game_code) = *app_ctx.game_code.peek() {
+            app_ctx.send(ClientToServer::RequestSnapshot { game_code });
+        }
+    }
+
+    match code {
+        // A stale token from an earlier visit; joining again with a name still works
+        ErrorCode::SessionExpired if app_ctx.game_state.peek().is_none() => {
+            if let Some(code) = *app_ctx.game_code.peek() {
+                session::forget_token(code);
+            }
+            *app_ctx.session_token.write() = None;
+        }
+        ErrorCode::SessionExpired | ErrorCode::GameNotFound | ErrorCode::NotInGame => {
+            app_ctx.leave_game(&message, nav);
+        }
+        // The buzzer was locked before our press arrived; show it as locked
+        ErrorCode::Locked => {
+            *app_ctx.locally_locked.write() = true;
+        }
+        // Our first buzz already counts, we already had our turn, or we are
+        // still out for a false start; the buzzer shows which
+        ErrorCode::AlreadyBuzzed | ErrorCode::LockedOut | ErrorCode::FalseStart => {}
+        ErrorCode::RateLimited => {
+            *app_ctx.error_message.write() =
+                Some("Slow down! Some of your actions were ignored.".to_string());
+        }
+        // The buttons were out of date; the snapshot requested above fixes them
+        ErrorCode::NothingToUndo
+        | ErrorCode::NothingToRedo
+        | ErrorCode::NobodyBuzzed
+        | ErrorCode::NoChoices
+        | ErrorCode::NoAnswers
+        | ErrorCode::NoWagers
+        | ErrorCode::AlreadyAnswered => {}
+        ErrorCode::EmptyName
+        | ErrorCode::NameTaken
+        | ErrorCode::AlreadyJoined
+        | ErrorCode::AlreadyInGame
+        | ErrorCode::NotHost
+        | ErrorCode::NotPlayer
+        | ErrorCode::WrongGame
+        | ErrorCode::IdMismatch
+        | ErrorCode::QueueFull
+        | ErrorCode::InvalidRules
+        | ErrorCode::TeammateBuzzed
+        | ErrorCode::EmptyTeamName
+        | ErrorCode::TeamNameTaken
+        | ErrorCode::UnknownTeam
+        | ErrorCode::UnknownPlayer
+        | ErrorCode::InvalidQuestions
+        | ErrorCode::NoQuestion
+        | ErrorCode::InvalidBoard
+        | ErrorCode::InvalidChoices
+        | ErrorCode::InvalidAnswer
+        | ErrorCode::InvalidWager
+        | ErrorCode::ScoreOutOfRange
+        | ErrorCode::InvalidTimestamp
+        | ErrorCode::Unknown => {
+            *app_ctx.error_message.write() = Some(message);
+        }
+    }
+}
+
 #[derive(Routable, Clone, PartialEq)]
 #[rustfmt::skip]
 enum Route {
@@ -247,6 +634,8 @@ enum Route {
         JoinPage { code: usize },
         #[route("/gameroom/:code")]
         GameRoom { code: usize },
+        #[route("/replay/:code")]
+        Replay { code: usize },
     #[end_layout]
     // PageNotFound is a catch all route that will match any route
     #[route("/:..route")]
@@ -257,7 +646,14 @@ enum Route {
 pub fn JoinPage(code: usize) -> Element {
     let mut app_ctx = use_context::<AppContext>();
     let mut player_name = use_signal(String::new);
-    let nav = navigator();
+    let mut team_name = use_signal(String::new);
+
+    // Opening the invite link again resumes the previous seat instead of asking for a name
+    use_effect(move || {
+        if app_ctx.game_state.peek().is_none() {
+            app_ctx.rejoin(code);
+        }
+    });
 
     let on_join_submit = move |_| {
         let name = player_name.read().trim().to_string();
@@ -277,6 +673,7 @@ pub fn JoinPage(code: usize) -> Element {
         app_ctx.send(ClientToServer::JoinGame {
             game_code: code,
             player_name: name,
+            team_name: Some(team_name.read().trim().to_string()).filter(|t| !t.is_empty()),
         });
     };
 
@@ -300,6 +697,16 @@ pub fn JoinPage(code: usize) -> Element {
                         oninput: move |evt| player_name.set(evt.value()),
                     }
                 }
+                div { class: "form-field",
+                    label { r#for: "team_name", "Team (optional)" }
+                    input {
+                        id: "team_name",
+                        name: "team_name",
+                        maxlength: 24,
+                        value: "{team_name}",
+                        oninput: move |evt| team_name.set(evt.value()),
+                    }
+                }
                 div { class: "form-button-container",
                     button {
                         r#type: "submit",
@@ -326,7 +733,8 @@ pub fn NavBar() -> Element {
 fn Home() -> Element {
     let mut app_ctx = use_context::<AppContext>();
     let mut player_name = use_signal(String::new);
-    let mut join_code = use_signal(|| String::default());
+    let mut team_name = use_signal(String::new);
+    let mut join_code = use_signal(String::default);
 
     let on_create_game = move |_| {
         info!("Creating game");
@@ -352,6 +760,7 @@ fn Home() -> Element {
             app_ctx.send(ClientToServer::JoinGame {
                 game_code: code,
                 player_name: name,
+                team_name: Some(team_name.read().trim().to_string()).filter(|t| !t.is_empty()),
             });
         }
     };
@@ -400,6 +809,16 @@ fn Home() -> Element {
                             oninput: move |evt| player_name.set(evt.value()),
                         }
                     }
+                    div { class: "form-field",
+                        label { r#for: "team_name", "Team (optional)" }
+                        input {
+                            id: "team_name",
+                            name: "team_name",
+                            maxlength: 24,
+                            value: "{team_name}",
+                            oninput: move |evt| team_name.set(evt.value()),
+                        }
+                    }
                     div { class: "form-button-container",
                         button {
                             r#type: "submit",
@@ -422,9 +841,15 @@ pub fn GameRoom(code: usize) -> Element {
     // Effect to handle loading/redirect if game state isn't loaded promptly
     use_effect(move || {
         if app_ctx.game_state.read().is_none() {
+            // After a reload, give a stored session a chance to resume first
+            let resuming = app_ctx.rejoin(code);
             spawn(async move {
-                gloo_timers::future::TimeoutFuture::new(200).await;
+                gloo_timers::future::TimeoutFuture::new(if resuming { 2_000 } else { 200 }).await;
                 if app_ctx.game_state.read().is_none() {
+                    if resuming {
+                        session::forget_token(code);
+                        *app_ctx.session_token.write() = None;
+                    }
                     nav.push(Route::Home {});
                 }
             });
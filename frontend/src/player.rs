@@ -1,4 +1,4 @@
-use crate::{host::PlayerListItem, timer::Timer, AppContext, NavBar};
+use crate::{board::GameBoard, choices::{ChoiceResults, PlayerChoices}, questions::RevealedQuestionCard, rounds::{QuestionBanner, RoundScores}, teams::{group_by_team, TeamPicker}, timer::{now_ms, use_server_now, AnswerTimer, Timer}, wagers::PlayerWager, written::{PlayerAnswerForm, ShownAnswers}, AppContext};
 use common::*;
 use log::warn;
 use dioxus::{document::eval, prelude::*};
@@ -18,28 +18,15 @@ pub fn PlayerBuzzOrderList(children: Element) -> Element {
             div {
                 h3 { "Buzzed" }
             }
-            // div {
-            //     { children }
-            // }
-            // div {
-            //     button {
-            //         class: "control-button",
-            //         onclick: move |_| {
-            //             if let Some(code) = *app_ctx.game_code.read() {
-            //                 app_ctx.send(ClientToServer::StartCountdown {
-            //                     game_code: code,
-            //                     time_limit: 10,
-            //                 });
-            //             }
-            //         },
-            //         "Start Timer"
-            //     }
-            // }
+            div {
+                { children }
+            }
         }
         if !players_data.is_empty() {
             ol { class: "player-list buzzed-order-list",
-                for (_, player_name) in players_data {
+                for (i, (_, player_name)) in players_data.into_iter().enumerate() {
                     li {
+                        class: if i == 0 { "spotlight" },
                         "{player_name}"
                     }
                 }
@@ -52,6 +39,7 @@ pub fn PlayerBuzzOrderList(children: Element) -> Element {
 pub fn PlayerView() -> Element {
     let app_ctx = use_context::<AppContext>();
     let my_id = *app_ctx.player_id.read();
+    let server_now_ms = use_server_now();
 
     // --- Focus the main div on mount ---
     use_future(move || async move {
@@ -67,15 +55,24 @@ pub fn PlayerView() -> Element {
 
     // This is the core logic, now without any arguments.
     let on_buzz = move || {
+        // Take the press time first so the rest of the handler doesn't count against us
+        let client_time_ms = now_ms();
         if let Some(ref id) = my_id {
             if let Some(game_state) = app_ctx.game_state.read().as_ref() {
-                let i_have_buzzed = my_id.map_or(false, |id| {
+                let i_have_buzzed = my_id.is_some_and(|id| {
                     game_state.buzzer_order.iter().any(|(player_id, _)| *player_id == id)
                 });
-                if !game_state.globally_locked && !i_have_buzzed {
+                let i_am_locked_out = game_state.locked_out.contains(id);
+                let queue_full = !game_state.rules.queue_has_room(game_state.buzzer_order.len());
+                let server_time_ms = (client_time_ms as i64 - *app_ctx.clock_offset_ms.read()).max(0) as u64;
+                let penalized = game_state.false_start_remaining_ms(id, server_time_ms) > 0;
+                // With false starts on, an early press goes out so the server can penalize it
+                let may_press = !game_state.globally_locked || game_state.waiting_for_unlock();
+                if may_press && !i_have_buzzed && !i_am_locked_out && !queue_full && !penalized {
                     app_ctx.send(ClientToServer::Buzz {
-                        game_code: app_ctx.game_code.read().clone().unwrap(),
+                        game_code: app_ctx.game_code.read().unwrap(),
                         player_id: *id,
+                        client_time_ms: Some(client_time_ms),
                     });
                 }
             }
@@ -84,13 +81,28 @@ pub fn PlayerView() -> Element {
 
     let game_state_guard = app_ctx.game_state.read();
     if let Some(game) = game_state_guard.as_ref() {
-        let i_have_buzzed = my_id.map_or(false, |id| {
+        let i_have_buzzed = my_id.is_some_and(|id| {
             game.buzzer_order
                 .iter()
                 .any(|(player_id, _)| *player_id == id)
         });
-        let locked = game.globally_locked || i_have_buzzed;
-        let buzzer_text = if locked { "Locked" } else { "BUZZ!" };
+        let i_am_locked_out = my_id.is_some_and(|id| game.locked_out.contains(&id));
+        let queue_full = !game.rules.queue_has_room(game.buzzer_order.len());
+        let penalty_ms = my_id.map_or(0, |id| game.false_start_remaining_ms(&id, server_now_ms));
+        let locked = game.globally_locked || i_have_buzzed || i_am_locked_out || queue_full || penalty_ms > 0;
+        // Pressing too early is allowed, and penalized, when false starts are on
+        let disabled = penalty_ms > 0 || i_am_locked_out || (locked && !game.waiting_for_unlock());
+        let buzzer_text = if penalty_ms > 0 {
+            format!("Penalized: {}s", penalty_ms.div_ceil(1000))
+        } else if i_am_locked_out {
+            "Answered".to_string()
+        } else if game.globally_locked && game.buzzer_order.is_empty() {
+            "Waiting for host".to_string()
+        } else if locked {
+            "Locked".to_string()
+        } else {
+            "BUZZ!".to_string()
+        };
         let code_display = app_ctx.game_code.read().map_or_else(
             || "....".to_string(),
             |c| c.to_string()
@@ -100,6 +112,15 @@ pub fn PlayerView() -> Element {
         } else {
             "".to_string()
         };
+        let host_notice = (!game.host_connected).then(|| {
+            match *app_ctx.host_grace_period.read() {
+                Some(secs) => format!(
+                    "The host disconnected. Waiting up to {} seconds for them to return...",
+                    secs
+                ),
+                None => "The host disconnected. Waiting for them to return...".to_string(),
+            }
+        });
 
         let on_keydown = move |evt: KeyboardEvent| {
             if evt.key() == Key::Character(" ".to_owned()) {
@@ -114,6 +135,9 @@ pub fn PlayerView() -> Element {
                 id: "player_view_wrapper",
                 tabindex: "0",
                 onkeydown: on_keydown,
+                if let Some(notice) = host_notice {
+                    p { class: "host-notice", "{notice}" }
+                }
                 div {
                     class: "player-view-container",
                     div {
@@ -126,25 +150,37 @@ pub fn PlayerView() -> Element {
                             class: "game-info-container",
                             p { class: "game-info", "Your name: {my_name}" }
                         }
+                        TeamPicker {}
+                        QuestionBanner {}
                     }
-                    div {
-                        class: "buzzer-container",
-                        button {
-                            class: "buzzer",
-                            disabled: locked,
-                            onclick: move |_| on_buzz(), // Create a new closure for the event
-                            "{buzzer_text}"
+                    GameBoard {}
+                    RevealedQuestionCard {}
+                    if game.choices_open() {
+                        PlayerChoices {}
+                    } else {
+                        div {
+                            class: "buzzer-container",
+                            button {
+                                class: "buzzer",
+                                disabled: disabled,
+                                onclick: move |_| on_buzz(), // Create a new closure for the event
+                                "{buzzer_text}"
+                            }
                         }
                     }
+                    ChoiceResults {}
+                    PlayerAnswerForm {}
+                    ShownAnswers {}
+                    PlayerWager {}
                 }
                 div {
                     class: "player-lists-wrapper",
                     PlayerBuzzOrderList {
-                        if let Some(time_limit) = app_ctx.time_limit.read().clone() {
-                            Timer { time_limit: time_limit }
-                        }
+                        Timer {}
+                        AnswerTimer {}
                     },
                     PlayerList {}
+                    RoundScores {}
                 }
             }
         }
@@ -178,13 +214,19 @@ pub fn PlayerList() -> Element {
                 .filter(|(_, player)| player.name() != HOST)
                 .map(|(player_id, player)| {
                     (
+                        *player_id,
                         player.name().to_string(),
                         *game.scores.get(player_id).unwrap_or(&0),
                     )
                 })
                 .collect();
-            players.sort_by(|p1, p2| p2.1.cmp(&p1.1));
-            players
+            players.sort_by_key(|p| std::cmp::Reverse(p.2));
+            // Leading teams first, players without a team last
+            let mut groups = group_by_team(game, players, |p| p.0);
+            groups.sort_by_key(|group| {
+                std::cmp::Reverse(group.team.as_ref().map(|(_, _, score)| *score))
+            });
+            groups
         } else {
             vec![]
         }
@@ -193,11 +235,20 @@ pub fn PlayerList() -> Element {
     rsx! {
         h3 { "Players" }
         ul { class: "player-list",
-            for (player_name, score) in players_data.read().iter() {
-                li {
-                    class: "player-list-item",
-                    span { class: "player-name", "{player_name}" }
-                    span { class: "score-display", " {score}" }
+            for group in players_data.read().iter() {
+                if let Some((_, team_name, team_score)) = &group.team {
+                    li {
+                        class: "team-header",
+                        span { class: "player-name", "{team_name}" }
+                        span { class: "score-display", " {team_score}" }
+                    }
+                }
+                for (_, player_name, score) in group.players.iter() {
+                    li {
+                        class: "player-list-item",
+                        span { class: "player-name", "{player_name}" }
+                        span { class: "score-display", " {score}" }
+                    }
                 }
             }
         }
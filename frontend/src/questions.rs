@@ -0,0 +1,193 @@
+use crate::AppContext;
+use common::*;
+use dioxus::prelude::*;
+
+/// File input for the host's question set. The file is checked here first so
+/// every bad row can be listed at once.
+#[component]
+pub fn QuestionImport() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let mut errors = use_signal(Vec::<String>::new);
+
+    let on_file_change = move |event: Event<FormData>| {
+        spawn(async move {
+            let res = async {
+                let files = event.files().ok_or("No files provided")?;
+                let file = files.files().first().ok_or("No file selected")?.to_string();
+                let format = QuestionFormat::from_file_name(&file)
+                    .ok_or("Only .json and .csv files can be imported")?;
+                let content = files
+                    .read_file_to_string(&file)
+                    .await
+                    .ok_or("Failed to read file")?;
+                Ok::<_, &str>(parse_questions(format, &content))
+            };
+
+            match res.await {
+                Ok(Ok(questions)) => {
+                    errors.set(Vec::new());
+                    if let Some(code) = *app_ctx.game_code.read() {
+                        app_ctx.send(ClientToServer::ImportQuestions {
+                            game_code: code,
+                            questions,
+                        });
+                    }
+                }
+                Ok(Err(row_errors)) => {
+                    errors.set(row_errors.iter().map(|e| e.to_string()).collect());
+                }
+                Err(e) => errors.set(vec![e.to_string()]),
+            }
+        });
+    };
+
+    rsx! {
+        div {
+            class: "file-uploader-container",
+            input {
+                r#type: "file",
+                accept: ".json,.csv",
+                id: "questions-upload",
+                onchange: on_file_change,
+                class: "file-input"
+            }
+            if !errors.read().is_empty() {
+                ul {
+                    class: "error-message import-errors",
+                    for error in errors.read().iter() {
+                        li { "{error}" }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The host's view of the current question, answer key included
+#[component]
+pub fn QuestionPanel() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let question_bank = app_ctx.question_bank.read();
+    let game_state = app_ctx.game_state.read();
+    let Some(game) = game_state.as_ref() else {
+        return rsx! {};
+    };
+    let Some(index) = game.current_question else {
+        return rsx! {};
+    };
+    let Some(question) = question_bank.get(index) else {
+        return rsx! {};
+    };
+    let Question {
+        question: text,
+        answer,
+        ..
+    } = question;
+    let count = question_bank.len();
+    let number = index + 1;
+    let revealed = game.revealed.as_ref();
+    let question_shown = revealed.is_some();
+    let answer_shown = revealed.is_some_and(|r| r.answer.is_some());
+
+    let select = move |index: usize| {
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(ClientToServer::SelectQuestion {
+                game_code: code,
+                index,
+            });
+        }
+    };
+    let on_reveal_question = move |_| {
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(ClientToServer::RevealQuestion { game_code: code });
+        }
+    };
+    let on_reveal_answer = move |_| {
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(ClientToServer::RevealAnswer { game_code: code });
+        }
+    };
+
+    rsx! {
+        div {
+            class: "question-panel",
+            p {
+                class: "question-meta",
+                "Question {number} of {count}"
+                if let Some(category) = &question.category {
+                    " · {category}"
+                }
+                if let Some(points) = question.points {
+                    " · {points} points"
+                }
+            }
+            p { class: "question-text", "{text}" }
+            p { class: "answer-key", "Answer: {answer}" }
+            if let Some(media) = &question.media {
+                a { href: "{media}", target: "_blank", "Open media" }
+            }
+            div {
+                class: "host-controls",
+                button {
+                    class: "control-button",
+                    disabled: index == 0,
+                    onclick: move |_| select(index - 1),
+                    "Previous"
+                }
+                button {
+                    class: "control-button",
+                    disabled: number >= count,
+                    onclick: move |_| select(index + 1),
+                    "Next"
+                }
+                button {
+                    class: "control-button",
+                    disabled: question_shown,
+                    onclick: on_reveal_question,
+                    "Reveal Question"
+                }
+                button {
+                    class: "control-button",
+                    disabled: answer_shown,
+                    onclick: on_reveal_answer,
+                    "Reveal Answer"
+                }
+            }
+        }
+    }
+}
+
+/// The question as the host revealed it to the players
+#[component]
+pub fn RevealedQuestionCard() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let game_state = app_ctx.game_state.read();
+    let Some(revealed) = game_state.as_ref().and_then(|game| game.revealed.as_ref()) else {
+        return rsx! {};
+    };
+    let text = &revealed.question;
+
+    rsx! {
+        div {
+            class: "question-panel",
+            if revealed.category.is_some() || revealed.points.is_some() {
+                p {
+                    class: "question-meta",
+                    if let Some(category) = &revealed.category {
+                        "{category} "
+                    }
+                    if let Some(points) = revealed.points {
+                        "({points} points)"
+                    }
+                }
+            }
+            p { class: "question-text", "{text}" }
+            if let Some(media) = &revealed.media {
+                img { class: "question-media", src: "{media}", alt: "Question media" }
+            }
+            if let Some(answer) = &revealed.answer {
+                p { class: "answer-key", "Answer: {answer}" }
+            }
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use common::*;
+use dioxus::prelude::*;
+use gloo_net::http::Request;
+
+/// Absolute URL of a server endpoint; in dev the page and the server run on
+/// different ports.
+fn api_url(path: &str) -> String {
+    #[cfg(feature = "dev")]
+    {
+        format!("http://localhost:3001{}", path)
+    }
+    #[cfg(not(feature = "dev"))]
+    {
+        path.to_string()
+    }
+}
+
+/// Loads the log of game `code`. A running game only hands its log to the
+/// host, so the host's stored session token goes along in a header rather
+/// than in the URL.
+async fn fetch_log(code: usize) -> Result<Vec<LoggedEvent>, String> {
+    let mut request = Request::get(&api_url(&format!("/games/{}/log", code)));
+    if let Some(token) = crate::session::load_token(code) {
+        request = request.header("Authorization", &format!("Bearer {}", token));
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if response.status() == 404 {
+        return Err(format!("There is no log for game {}.", code));
+    }
+    if response.status() == 403 {
+        return Err(format!("Game {} is still running; only its host can replay it.", code));
+    }
+    if !response.ok() {
+        return Err(format!("Failed to load the log ({}).", response.status()));
+    }
+    response.json().await.map_err(|e| e.to_string())
+}
+
+fn player_name(game: &GameState, player_id: &uuid::Uuid) -> String {
+    game.players
+        .get(player_id)
+        .map_or_else(|| "Unknown player".to_string(), |p| p.name().to_string())
+}
+
+fn describe(event: &GameEvent, game: &GameState) -> String {
+    match event {
+        GameEvent::GameCreated { .. } => "Game created".to_string(),
+        GameEvent::QuestionAdvanced { new_round: true } => {
+            format!("Round {} started", game.progress.round)
+        }
+        GameEvent::QuestionAdvanced { new_round: false } => {
+            format!("Question {} started", game.progress.question)
+        }
+        GameEvent::QuestionsImported { count } => format!("The host imported {} questions", count),
+        GameEvent::QuestionSelected { index } => format!("The host picked question {}", index + 1),
+        GameEvent::QuestionRevealed { question } => match &question.answer {
+            Some(answer) => format!("Answer revealed: {}", answer),
+            None => format!("Question revealed: {}", question.question),
+        },
+        GameEvent::BoardOpened { board } => {
+            format!("The host put up a board with {} categories", board.categories.len())
+        }
+        GameEvent::BoardClosed => "The host closed the board".to_string(),
+        GameEvent::ClueSelected { index } => format!("The host picked clue {} from the board", index + 1),
+        GameEvent::ChoicesOpened { options, .. } => {
+            format!("Multiple-choice question opened with {} options", options.len())
+        }
+        GameEvent::ChoiceSubmitted { player_id } => format!("{} answered", player_name(game, player_id)),
+        GameEvent::ChoicesClosed { correct, picks } => format!(
+            "Multiple-choice question closed; {} was correct, {} of {} got it",
+            ChoiceQuestion::label(*correct),
+            picks.values().filter(|choice| **choice == *correct).count(),
+            picks.len()
+        ),
+        GameEvent::AnswersOpened => "The host started collecting answers".to_string(),
+        GameEvent::AnswerSubmitted { player_id } => {
+            format!("{} sent an answer", player_name(game, player_id))
+        }
+        GameEvent::AnswersClosed => "The host stopped taking answers".to_string(),
+        GameEvent::AnswersShown { texts } => format!("{} answers were shown", texts.len()),
+        GameEvent::AnswerGraded { player_id, grade } => {
+            let grade = match grade {
+                Grade::Correct => "correct",
+                Grade::Partial => "partly correct",
+                Grade::Incorrect => "wrong",
+            };
+            format!("{}'s answer was graded {}", player_name(game, player_id), grade)
+        }
+        GameEvent::WagersOpened => "The host started taking wagers".to_string(),
+        GameEvent::WagerPlaced { player_id } => format!("{} placed a wager", player_name(game, player_id)),
+        GameEvent::WagersLocked => "Wagers were locked".to_string(),
+        GameEvent::WagersRevealed { amounts } => format!("{} wagers were revealed", amounts.len()),
+        GameEvent::WagerResolved {
+            player_id,
+            amount,
+            won,
+            ..
+        } => format!(
+            "{} {} their wager of {}",
+            player_name(game, player_id),
+            if *won { "won" } else { "lost" },
+            amount
+        ),
+        GameEvent::PlayerJoined { player_name, .. } => format!("{} joined", player_name),
+        GameEvent::PlayerDisconnected { player_id } => {
+            format!("{} lost connection", player_name(game, player_id))
+        }
+        GameEvent::PlayerReconnected { player_id } => {
+            format!("{} reconnected", player_name(game, player_id))
+        }
+        GameEvent::TeamCreated { name, .. } => format!("Team {} created", name),
+        GameEvent::TeamRemoved { .. } => "A team was removed".to_string(),
+        GameEvent::TeamAssigned { player_id, .. } => match game.team_of(player_id) {
+            Some(team) => format!("{} joined team {}", player_name(game, player_id), team.name),
+            None => format!("{} left their team", player_name(game, player_id)),
+        },
+        GameEvent::HostDisconnected => "Host lost connection".to_string(),
+        GameEvent::HostReconnected => "Host reconnected".to_string(),
+        GameEvent::Buzzed {
+            player_name,
+            position,
+            ..
+        } => format!("{} buzzed in at position {}", player_name, position + 1),
+        GameEvent::Locked => "Buzzers locked".to_string(),
+        GameEvent::Unlocked => "Buzzers unlocked".to_string(),
+        GameEvent::QueueCleared => "Buzzers cleared".to_string(),
+        GameEvent::FalseStart { player_id, .. } => {
+            format!("{} buzzed before the unlock", player_name(game, player_id))
+        }
+        GameEvent::AnswerClockStarted { player_id, time_limit } => {
+            format!("{} has {} seconds to answer", player_name(game, player_id), time_limit)
+        }
+        GameEvent::AnswerClockStopped => "The answer clock stopped".to_string(),
+        GameEvent::AnswerClockExpired { player_id } => {
+            format!("{} ran out of time to answer", player_name(game, player_id))
+        }
+        GameEvent::AnswerJudged { player_id, correct } => format!(
+            "{} answered {}",
+            player_name(game, player_id),
+            if *correct { "correctly" } else { "wrong" }
+        ),
+        GameEvent::ScoreChanged {
+            player_id,
+            delta,
+            set_to,
+            reason,
+            ..
+        } => {
+            let change = match set_to {
+                Some(score) => format!("score set to {} ({:+})", score, delta),
+                None => format!("{:+} points", delta),
+            };
+            match reason {
+                Some(reason) => format!("{} {}: {}", player_name(game, player_id), change, reason),
+                None => format!("{} {}", player_name(game, player_id), change),
+            }
+        }
+        GameEvent::ScoreUndone { entry_id } => format!("Score change #{} undone", entry_id + 1),
+        GameEvent::ScoreRedone { entry_id } => format!("Score change #{} redone", entry_id + 1),
+        GameEvent::CountdownStarted { time_limit } => {
+            format!("Countdown of {} seconds started", time_limit)
+        }
+        GameEvent::CountdownPaused => "Countdown paused".to_string(),
+        GameEvent::CountdownResumed => "Countdown resumed".to_string(),
+        GameEvent::CountdownExpired { .. } => "Time is up".to_string(),
+        GameEvent::CountdownOptionsChanged {
+            auto_lock,
+            pause_on_buzz,
+        } => format!(
+            "Countdown options: lock when time is up {}, pause on first buzz {}",
+            if *auto_lock { "on" } else { "off" },
+            if *pause_on_buzz { "on" } else { "off" }
+        ),
+        GameEvent::RulesChanged { rules } => format!(
+            "Rules changed: {} points for a correct answer, {} for a wrong one",
+            rules.points_correct, -rules.penalty_wrong
+        ),
+    }
+}
+
+fn format_elapsed(elapsed_ms: u64) -> String {
+    let secs = elapsed_ms / 1000;
+    format!("{:02}:{:02}.{:03}", secs / 60, secs % 60, elapsed_ms % 1000)
+}
+
+/// Steps through the event log of a game, e.g. to review a contested ruling.
+#[component]
+pub fn Replay(code: usize) -> Element {
+    let log = use_resource(move || fetch_log(code));
+    let mut position = use_signal(|| 0usize);
+    // The download is built from the loaded log, so it needs no second request
+    let download_url = use_memo(move || match &*log.read() {
+        Some(Ok(events)) => {
+            let json = serde_json::to_string(events).unwrap_or_default();
+            let encoded: String = web_sys::js_sys::encode_uri_component(&json).into();
+            format!("data:application/json;charset=utf-8,{}", encoded)
+        }
+        _ => String::new(),
+    });
+
+    let log_guard = log.read();
+    let events = match &*log_guard {
+        None => return rsx! { p { class: "replay-status", "Loading game {code}..." } },
+        Some(Err(e)) => return rsx! { p { class: "error-message", "{e}" } },
+        Some(Ok(events)) if events.is_empty() => {
+            return rsx! { p { class: "replay-status", "Nothing happened in game {code}." } }
+        }
+        Some(Ok(events)) => events,
+    };
+
+    let count = events.len();
+    let last = count - 1;
+    let at = position().min(last);
+    let step = at + 1;
+    let game = GameState::replay(&events[..=at]);
+    let logged = &events[at];
+    let elapsed = format_elapsed(logged.at_ms.saturating_sub(events[0].at_ms));
+    let description = describe(&logged.event, &game);
+    let Progress {
+        round, question, ..
+    } = game.progress;
+
+    let first_press = game
+        .buzzer_order
+        .front()
+        .and_then(|(id, _)| game.buzz_timings.get(id))
+        .map(|t| t.press_time_ms);
+    let buzzes: Vec<(String, String)> = game
+        .buzzer_order
+        .iter()
+        .map(|(id, name)| {
+            let note = match (game.buzz_timings.get(id), first_press) {
+                (Some(timing), Some(first)) => format!(
+                    "+{:.3}s",
+                    timing.press_time_ms.saturating_sub(first) as f64 / 1000.0
+                ),
+                _ => String::new(),
+            };
+            (name.clone(), note)
+        })
+        .collect();
+    let scores: Vec<(String, i32)> = game
+        .player_join_order
+        .iter()
+        .filter(|id| **id != game.host_id)
+        .map(|id| {
+            let score = game.scores.get(id).copied().unwrap_or_default();
+            (player_name(&game, id), score)
+        })
+        .collect();
+
+    rsx! {
+        div {
+            class: "replay-container",
+            h2 { "Replay of game {code}" }
+            div {
+                class: "replay-controls",
+                button {
+                    class: "control-button",
+                    disabled: at == 0,
+                    onclick: move |_| position.set(at.saturating_sub(1)),
+                    "◀"
+                }
+                input {
+                    r#type: "range",
+                    class: "replay-slider",
+                    min: "0",
+                    max: "{last}",
+                    value: "{at}",
+                    oninput: move |evt| {
+                        if let Ok(value) = evt.value().parse::<usize>() {
+                            position.set(value);
+                        }
+                    }
+                }
+                button {
+                    class: "control-button",
+                    disabled: at == last,
+                    onclick: move |_| position.set((at + 1).min(last)),
+                    "▶"
+                }
+            }
+            p {
+                class: "replay-event",
+                span { class: "replay-time", "{elapsed}" }
+                "{description}"
+            }
+            p {
+                class: "replay-status",
+                "Event {step} of {count} · Round {round} · Question {question}"
+                if game.globally_locked {
+                    " · Buzzers locked"
+                }
+            }
+            div {
+                class: "replay-columns",
+                div {
+                    h3 { "Buzz order" }
+                    ol {
+                        class: "player-list",
+                        for (name, note) in buzzes {
+                            li {
+                                span { class: "player-name", "{name}" }
+                                span { class: "buzz-timing", "{note}" }
+                            }
+                        }
+                    }
+                }
+                div {
+                    h3 { "Scores" }
+                    ul {
+                        class: "player-list",
+                        for (name, score) in scores {
+                            li {
+                                span { class: "player-name", "{name}" }
+                                span { class: "score-display", "{score}" }
+                            }
+                        }
+                    }
+                }
+            }
+            a {
+                class: "control-button",
+                href: "{download_url}",
+                download: "game-{code}.json",
+                "Download log"
+            }
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use crate::AppContext;
+use common::*;
+use dioxus::prelude::*;
+use uuid::Uuid;
+
+/// Which question the game is on
+#[component]
+pub fn QuestionBanner() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let game_state = app_ctx.game_state.read();
+    let Some(game) = game_state.as_ref() else {
+        return rsx! {};
+    };
+    let Progress {
+        round, question, ..
+    } = game.progress;
+
+    rsx! {
+        div {
+            class: "game-info-container",
+            p { class: "game-info question-banner", "Round {round} · Question {question}" }
+        }
+    }
+}
+
+/// Points per round for every player, or every team if there are teams
+#[component]
+pub fn RoundScores() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let game_state = app_ctx.game_state.read();
+    let Some(game) = game_state.as_ref() else {
+        return rsx! {};
+    };
+    let rounds: Vec<u32> = (1..=game.progress.round).collect();
+    let rows: Vec<(String, Vec<i32>)> = if game.teams.is_empty() {
+        game.player_join_order
+            .iter()
+            .filter(|id| **id != game.host_id)
+            .filter_map(|id| {
+                let player = game.players.get(id)?;
+                Some((player.name().to_string(), game.round_scores(id)))
+            })
+            .collect()
+    } else {
+        game.teams
+            .iter()
+            .map(|team| (team.name.clone(), game.team_round_scores(&team.id)))
+            .collect()
+    };
+    if rows.is_empty() {
+        return rsx! {};
+    }
+    let rows = rows.into_iter().map(|(name, scores)| {
+        let total = scores.iter().sum::<i32>();
+        (name, scores, total)
+    });
+
+    rsx! {
+        div {
+            class: "round-scores-container",
+            h3 { "Scores by Round" }
+            table {
+                class: "round-scores",
+                thead {
+                    tr {
+                        th {}
+                        for round in rounds {
+                            th { "R{round}" }
+                        }
+                        th { "Total" }
+                    }
+                }
+                tbody {
+                    for (name, scores, total) in rows {
+                        tr {
+                            td { class: "player-name", "{name}" }
+                            for score in scores {
+                                td { "{score}" }
+                            }
+                            td { class: "score-display", "{total}" }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The questions that are over, newest first, with their buzzes and score changes
+#[component]
+pub fn QuestionHistory() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let game_state = app_ctx.game_state.read();
+    let Some(game) = game_state.as_ref() else {
+        return rsx! {};
+    };
+    if game.progress.history.is_empty() {
+        return rsx! {};
+    }
+    let name_of = |player_id: &Uuid| {
+        game.players
+            .get(player_id)
+            .map_or_else(|| "Unknown player".to_string(), |p| p.name().to_string())
+    };
+    let questions: Vec<(String, String, Vec<String>)> = game
+        .progress
+        .history
+        .iter()
+        .rev()
+        .map(|record| {
+            let title = format!("R{} Q{}", record.round, record.question);
+            let mut buzzes: Vec<&QuestionBuzz> = record.buzzes.iter().collect();
+            buzzes.sort_by_key(|buzz| buzz.timing.press_time_ms);
+            let first_press_ms = buzzes.first().map_or(0, |buzz| buzz.timing.press_time_ms);
+            let buzzes = if buzzes.is_empty() {
+                "No buzzes".to_string()
+            } else {
+                buzzes
+                    .iter()
+                    .map(|buzz| {
+                        let behind_ms = buzz.timing.press_time_ms - first_press_ms;
+                        format!("{} (+{} ms)", buzz.player_name, behind_ms)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            let changes = record
+                .score_entries
+                .iter()
+                .filter_map(|id| game.score_ledger.entries.get(*id as usize))
+                .filter(|entry| !entry.undone)
+                .map(|entry| format!("{} {:+}", name_of(&entry.player_id), entry.delta))
+                .collect();
+            (title, buzzes, changes)
+        })
+        .collect();
+
+    rsx! {
+        details {
+            class: "question-history",
+            summary { "Previous questions" }
+            ol {
+                for (title, buzzes, changes) in questions {
+                    li {
+                        strong { "{title}: " }
+                        "{buzzes}"
+                        if !changes.is_empty() {
+                            ul {
+                                for change in changes {
+                                    li { "{change}" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
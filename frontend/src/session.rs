@@ -0,0 +1,32 @@
+use uuid::Uuid;
+use web_sys::Storage;
+
+// Session tokens are kept in local storage so a player can resume their seat
+// after a dropped connection or a page reload.
+fn storage() -> Option<Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+fn storage_key(game_code: usize) -> String {
+    format!("buzzer-session-{}", game_code)
+}
+
+pub fn load_token(game_code: usize) -> Option<Uuid> {
+    storage()?
+        .get_item(&storage_key(game_code))
+        .ok()??
+        .parse()
+        .ok()
+}
+
+pub fn store_token(game_code: usize, token: Uuid) {
+    if let Some(storage) = storage() {
+        let _ = storage.set_item(&storage_key(game_code), &token.to_string());
+    }
+}
+
+pub fn forget_token(game_code: usize) {
+    if let Some(storage) = storage() {
+        let _ = storage.remove_item(&storage_key(game_code));
+    }
+}
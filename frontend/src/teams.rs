@@ -0,0 +1,188 @@
+use crate::AppContext;
+use common::*;
+use dioxus::prelude::*;
+use uuid::Uuid;
+
+/// The players of one team, or of no team, as shown in the player lists
+#[derive(Clone, PartialEq)]
+pub struct TeamGroup<T> {
+    // Id, name and total score of the team; `None` for players without one
+    pub team: Option<(Uuid, String, i32)>,
+    pub players: Vec<T>,
+}
+
+/// Splits `players` by team, keeping their order within each team. Teams come
+/// in the order they were created, players without a team last.
+pub fn group_by_team<T>(
+    game: &GameState,
+    players: Vec<T>,
+    id_of: impl Fn(&T) -> Uuid,
+) -> Vec<TeamGroup<T>> {
+    let mut groups: Vec<TeamGroup<T>> = game
+        .teams
+        .iter()
+        .map(|team| TeamGroup {
+            team: Some((team.id, team.name.clone(), game.team_score(&team.id))),
+            players: Vec::new(),
+        })
+        .collect();
+    let mut without_team = Vec::new();
+    for player in players {
+        let team_id = game.player_teams.get(&id_of(&player));
+        match groups
+            .iter_mut()
+            .find(|group| group.team.as_ref().map(|(id, ..)| id) == team_id)
+        {
+            Some(group) => group.players.push(player),
+            None => without_team.push(player),
+        }
+    }
+    if !without_team.is_empty() || groups.is_empty() {
+        groups.push(TeamGroup {
+            team: None,
+            players: without_team,
+        });
+    }
+    groups
+}
+
+/// Shows the player's team and lets them pick or start another one.
+#[component]
+pub fn TeamPicker() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let mut team_name = use_signal(String::new);
+    let game_state = app_ctx.game_state.read();
+    let Some(game) = game_state.as_ref() else {
+        return rsx! {};
+    };
+    let my_team = app_ctx
+        .player_id
+        .read()
+        .and_then(|id| game.team_of(&id).map(|team| team.name.clone()));
+    let team_names: Vec<String> = game.teams.iter().map(|team| team.name.clone()).collect();
+    let may_create = game.rules.players_create_teams;
+    if team_names.is_empty() && !may_create {
+        return rsx! {};
+    }
+
+    let on_submit = move |_| {
+        let name = team_name.read().trim().to_string();
+        if let (Some(code), false) = (*app_ctx.game_code.read(), name.is_empty()) {
+            app_ctx.send(ClientToServer::ChooseTeam {
+                game_code: code,
+                team_name: name,
+            });
+            team_name.set(String::new());
+        }
+    };
+    let placeholder = if may_create {
+        "Pick or name a team"
+    } else {
+        "Pick a team"
+    };
+
+    rsx! {
+        div {
+            class: "game-info-container team-picker",
+            p {
+                class: "game-info",
+                match my_team {
+                    Some(name) => rsx! { "Your team: {name}" },
+                    None => rsx! { "You are not on a team" },
+                }
+            }
+            form {
+                onsubmit: on_submit,
+                input {
+                    list: "team-names",
+                    placeholder: placeholder,
+                    maxlength: 24,
+                    value: "{team_name}",
+                    oninput: move |evt| team_name.set(evt.value()),
+                }
+                datalist {
+                    id: "team-names",
+                    for name in team_names {
+                        option { value: "{name}" }
+                    }
+                }
+                button { r#type: "submit", class: "control-button", "Join team" }
+            }
+        }
+    }
+}
+
+/// Lets the host move a player to another team.
+#[component]
+pub fn TeamSelect(player_id: Uuid) -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let game_state = app_ctx.game_state.read();
+    let Some(game) = game_state.as_ref() else {
+        return rsx! {};
+    };
+    if game.teams.is_empty() {
+        return rsx! {};
+    }
+    let current = game.player_teams.get(&player_id).copied();
+    let teams: Vec<(Uuid, String)> = game
+        .teams
+        .iter()
+        .map(|team| (team.id, team.name.clone()))
+        .collect();
+
+    rsx! {
+        select {
+            class: "team-select",
+            "aria-label": "Team",
+            onchange: move |evt| {
+                if let Some(code) = *app_ctx.game_code.read() {
+                    app_ctx.send(ClientToServer::AssignTeam {
+                        game_code: code,
+                        player_id,
+                        team_id: evt.value().parse::<Uuid>().ok(),
+                    });
+                }
+            },
+            option { value: "", selected: current.is_none(), "No team" }
+            for (team_id, name) in teams {
+                option {
+                    value: "{team_id}",
+                    selected: current == Some(team_id),
+                    "{name}"
+                }
+            }
+        }
+    }
+}
+
+/// Lets the host start a team.
+#[component]
+pub fn NewTeamForm() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let mut team_name = use_signal(String::new);
+
+    let on_submit = move |_| {
+        let name = team_name.read().trim().to_string();
+        if let (Some(code), false) = (*app_ctx.game_code.read(), name.is_empty()) {
+            app_ctx.send(ClientToServer::CreateTeam {
+                game_code: code,
+                name,
+            });
+            team_name.set(String::new());
+        }
+    };
+
+    rsx! {
+        form {
+            class: "new-team-form",
+            onsubmit: on_submit,
+            input {
+                placeholder: "New team",
+                maxlength: 24,
+                value: "{team_name}",
+                oninput: move |evt| team_name.set(evt.value()),
+            }
+            button { r#type: "submit", class: "control-button", "Add team" }
+        }
+    }
+}
@@ -1,28 +1,59 @@
+use crate::AppContext;
 use dioxus::prelude::*;
 use gloo_timers::future::TimeoutFuture;
+use uuid::Uuid;
 
-#[component]
-pub fn Timer(time_limit: u32) -> Element {
-    let mut remaining_time = use_signal(|| time_limit);
+pub fn now_ms() -> u64 {
+    web_sys::js_sys::Date::now() as u64
+}
+
+/// The server's current time, re-read a few times a second. Deadlines are on
+/// the server's clock, so our clock is shifted onto it first.
+pub fn use_server_now() -> u64 {
+    let app_ctx = use_context::<AppContext>();
+    let mut now = use_signal(now_ms);
 
     use_future(move || async move {
-        while *remaining_time.read() > 0 {
-            TimeoutFuture::new(1_000).await;
-            let current_time = *remaining_time.read();
-            remaining_time.set(current_time - 1);
+        loop {
+            TimeoutFuture::new(250).await;
+            now.set(now_ms());
         }
     });
 
-    let timer_display = if *remaining_time.read() > 0 {
-        format!("{}", remaining_time)
+    let offset_ms = *app_ctx.clock_offset_ms.read();
+    (now() as i64 - offset_ms).max(0) as u64
+}
+
+#[component]
+pub fn Timer() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    // The server owns the countdown; we only re-render against its deadline.
+    let server_now_ms = use_server_now();
+
+    let game_state_guard = app_ctx.game_state.read();
+    let Some(game) = game_state_guard.as_ref() else {
+        return rsx! {};
+    };
+    if game.time_limit.is_none() {
+        return rsx! {};
+    }
+
+    let remaining_ms = game.countdown_remaining_ms(server_now_ms);
+    let running = game.countdown.running;
+    let remaining_secs = remaining_ms.div_ceil(1000);
+
+    let timer_display = if remaining_ms > 0 {
+        format!("{}", remaining_secs)
     } else {
         "Time's up!".to_string()
     };
 
-    let timer_class = if *remaining_time.read() > 0 {
+    let timer_class = if remaining_ms == 0 {
+        "timer-finished"
+    } else if running {
         "timer-running"
     } else {
-        "timer-finished"
+        "timer-paused"
     };
 
     rsx! {
@@ -35,3 +66,60 @@ pub fn Timer(time_limit: u32) -> Element {
         }
     }
 }
+
+/// Time the player who was called on has left to answer
+#[component]
+pub fn AnswerTimer() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let server_now_ms = use_server_now();
+
+    let game_state_guard = app_ctx.game_state.read();
+    let Some(game) = game_state_guard.as_ref() else {
+        return rsx! {};
+    };
+    let Some(clock) = game.answer_clock.as_ref() else {
+        return rsx! {};
+    };
+    let name = game
+        .players
+        .get(&clock.player_id)
+        .map(|p| p.name().to_string())
+        .unwrap_or_default();
+
+    let remaining_ms = clock.remaining_ms(server_now_ms);
+    let (timer_display, timer_class) = if remaining_ms > 0 {
+        (format!("{}: {}s", name, remaining_ms.div_ceil(1000)), "timer-running")
+    } else {
+        (format!("{}: time's up!", name), "timer-finished")
+    };
+
+    rsx! {
+        div {
+            class: "timer-container answer-timer",
+            span {
+                class: "{timer_class}",
+                "{timer_display}"
+            }
+        }
+    }
+}
+
+/// Time left on a player's false start lockout, for the host's player list
+#[component]
+pub fn FalseStartBadge(player_id: Uuid) -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let server_now_ms = use_server_now();
+    let remaining_ms = app_ctx
+        .game_state
+        .read()
+        .as_ref()
+        .map_or(0, |game| game.false_start_remaining_ms(&player_id, server_now_ms));
+    if remaining_ms == 0 {
+        return rsx! {};
+    }
+    let remaining_secs = remaining_ms.div_ceil(1000);
+
+    rsx! {
+        span { class: "false-start-badge", "False start: {remaining_secs}s" }
+    }
+}
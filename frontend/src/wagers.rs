@@ -0,0 +1,211 @@
+use crate::AppContext;
+use common::*;
+use dioxus::prelude::*;
+use uuid::Uuid;
+
+fn outcome(wager: Option<&Wager>) -> (&'static str, &'static str) {
+    match wager.and_then(|w| w.won) {
+        Some(true) => ("Won", "graded correct"),
+        Some(false) => ("Lost", "graded wrong"),
+        None => ("", ""),
+    }
+}
+
+/// The host's controls for a wager question: who has bet, a timer to lock
+/// betting, and right/wrong buttons that settle each bet
+#[component]
+pub fn HostWagerPanel() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let mut lock_secs = use_signal(|| 30_u32);
+    let game_state = app_ctx.game_state.read();
+    let Some(game) = game_state.as_ref() else {
+        return rsx! {};
+    };
+    let round = game.wager.as_ref();
+    let open = round.is_some_and(|r| r.open);
+    let amounts = app_ctx.wager_amounts.read();
+    let rows: Vec<_> = game
+        .player_join_order
+        .iter()
+        .filter(|id| **id != game.host_id)
+        .filter_map(|id| {
+            let name = game.players.get(id)?.name().to_string();
+            let wager = round.and_then(|r| r.wager_of(id));
+            let status = match amounts.get(id) {
+                Some(amount) => format!("bet {}", amount),
+                None if wager.is_some() => "bet placed".to_string(),
+                None => "waiting...".to_string(),
+            };
+            let (result, class) = outcome(wager);
+            Some((*id, name, status, wager.is_some(), result, class))
+        })
+        .collect();
+
+    let send = move |msg: fn(usize) -> ClientToServer| {
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(msg(code));
+        }
+    };
+    let resolve = move |player_id: Uuid, correct: bool| {
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(ClientToServer::ResolveWager {
+                game_code: code,
+                player_id,
+                correct,
+            });
+        }
+    };
+    let on_timer = move |_| {
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(ClientToServer::StartCountdown {
+                game_code: code,
+                time_limit: lock_secs(),
+            });
+        }
+    };
+
+    rsx! {
+        div {
+            class: "wager-panel",
+            h3 { "Wagers" }
+            div {
+                class: "host-controls",
+                if open {
+                    input {
+                        r#type: "number",
+                        class: "timer-duration-input",
+                        min: "1",
+                        value: "{lock_secs}",
+                        oninput: move |evt| {
+                            if let Ok(secs) = evt.value().parse::<u32>() {
+                                lock_secs.set(secs.max(1));
+                            }
+                        }
+                    }
+                    button { class: "control-button", onclick: on_timer, "Lock on Timer" }
+                    button {
+                        class: "control-button",
+                        onclick: move |_| send(|game_code| ClientToServer::LockWagers { game_code }),
+                        "Lock Now"
+                    }
+                } else {
+                    button {
+                        class: "control-button",
+                        onclick: move |_| send(|game_code| ClientToServer::OpenWagers { game_code }),
+                        "Take Wagers"
+                    }
+                }
+                if round.is_some() {
+                    button {
+                        class: "control-button",
+                        onclick: move |_| send(|game_code| ClientToServer::RevealWagers { game_code }),
+                        "Reveal Wagers"
+                    }
+                }
+            }
+            if round.is_some() {
+                ul {
+                    class: "player-list",
+                    for (player_id, name, status, placed, result, class) in rows {
+                        li {
+                            class: "player-list-item {class}",
+                            span { class: "player-name", "{name}" }
+                            span { class: "wager-status", "{status} {result}" }
+                            if placed && !open {
+                                button {
+                                    class: "score-button",
+                                    "aria-label": "Right",
+                                    onclick: move |_| resolve(player_id, true),
+                                    "✓"
+                                }
+                                button {
+                                    class: "score-button",
+                                    "aria-label": "Wrong",
+                                    onclick: move |_| resolve(player_id, false),
+                                    "✗"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Where players place their bet, and everyone's bets once they are out
+#[component]
+pub fn PlayerWager() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let mut amount = use_signal(String::new);
+    let mut placed = use_signal(|| None::<i32>);
+    let game_state = app_ctx.game_state.read();
+    let Some(game) = game_state.as_ref() else {
+        return rsx! {};
+    };
+    let Some(round) = game.wager.as_ref() else {
+        return rsx! {};
+    };
+    let Some(my_id) = *app_ctx.player_id.read() else {
+        return rsx! {};
+    };
+    let max = game.max_wager(&my_id);
+    let mine = round.wager_of(&my_id);
+    // Our bet stays hidden in the state until it is revealed, so remember it
+    let my_bet = mine.and_then(|w| w.amount).or(mine.and(*placed.read()));
+    let (result, _) = outcome(mine);
+    let revealed: Vec<_> = round
+        .wagers
+        .iter()
+        .filter_map(|wager| {
+            let amount = wager.amount?;
+            let name = game.players.get(&wager.player_id)?.name().to_string();
+            let (result, class) = outcome(Some(wager));
+            Some((name, amount, result, class))
+        })
+        .collect();
+
+    rsx! {
+        div {
+            class: "wager-panel",
+            if round.open {
+                form {
+                    class: "answer-form",
+                    onsubmit: move |_| {
+                        if let (Some(code), Ok(bet)) = (*app_ctx.game_code.read(), amount.read().trim().parse::<i32>()) {
+                            app_ctx.send(ClientToServer::PlaceWager {
+                                game_code: code,
+                                amount: bet,
+                            });
+                            placed.set(Some(bet));
+                        }
+                    },
+                    input {
+                        r#type: "number",
+                        min: "0",
+                        max: "{max}",
+                        placeholder: "Your wager (0 to {max})",
+                        value: "{amount}",
+                        oninput: move |evt| amount.set(evt.value()),
+                    }
+                    button { class: "control-button", r#type: "submit", "Bet" }
+                }
+            }
+            if let Some(bet) = my_bet {
+                p { class: "answer-sent", "Your wager: {bet} {result}" }
+            }
+            if !revealed.is_empty() {
+                ul {
+                    class: "player-list",
+                    for (name, amount, result, class) in revealed {
+                        li {
+                            class: "player-list-item {class}",
+                            span { class: "player-name", "{name}" }
+                            span { "{amount} {result}" }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
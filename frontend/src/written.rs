@@ -0,0 +1,241 @@
+use crate::AppContext;
+use common::*;
+use dioxus::prelude::*;
+use uuid::Uuid;
+
+fn player_name(game: &GameState, player_id: &Uuid) -> String {
+    game.players
+        .get(player_id)
+        .map_or_else(|| "Unknown player".to_string(), |p| p.name().to_string())
+}
+
+fn grade_class(grade: Option<Grade>) -> &'static str {
+    match grade {
+        Some(Grade::Correct) => "graded correct",
+        Some(Grade::Partial) => "graded partial",
+        Some(Grade::Incorrect) => "graded wrong",
+        None => "",
+    }
+}
+
+/// The host's controls for written answers and the queue of answers to
+/// grade, with answers that read the same grouped together
+#[component]
+pub fn HostGradingQueue() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let mut anonymize = use_signal(|| false);
+    let game_state = app_ctx.game_state.read();
+    let Some(game) = game_state.as_ref() else {
+        return rsx! {};
+    };
+    let written = game.written.as_ref();
+    let open = written.is_some_and(|w| w.open);
+    let shown = written.is_some_and(|w| w.shown);
+    let texts = app_ctx.answer_texts.read();
+    let answers = written.map(|w| w.answers.as_slice()).unwrap_or_default();
+    let groups: Vec<_> = group_answers(answers.iter().filter_map(|answer| {
+        let text = texts.get(&answer.player_id)?;
+        Some((answer.player_id, text.as_str()))
+    }))
+    .into_iter()
+    .map(|group| {
+        let names: Vec<String> = group.player_ids.iter().map(|id| player_name(game, id)).collect();
+        // Mixed grades within a group show as ungraded
+        let grades: Vec<Option<Grade>> = group
+            .player_ids
+            .iter()
+            .map(|id| written.and_then(|w| w.answer_of(id)).and_then(|a| a.grade))
+            .collect();
+        let grade = grades[0].filter(|g| grades.iter().all(|other| *other == Some(*g)));
+        let count = group.player_ids.len();
+        (group.text, names.join(", "), count, grade_class(grade), group.player_ids)
+    })
+    .collect();
+    let count = answers.len();
+
+    let send = move |msg: fn(usize) -> ClientToServer| {
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(msg(code));
+        }
+    };
+    let grade = move |player_ids: Vec<Uuid>, grade: Grade| {
+        if let Some(code) = *app_ctx.game_code.read() {
+            app_ctx.send(ClientToServer::GradeAnswers {
+                game_code: code,
+                player_ids,
+                grade,
+            });
+        }
+    };
+
+    rsx! {
+        div {
+            class: "grading-queue",
+            div {
+                class: "player-list-header",
+                h3 { "Written Answers" }
+                label {
+                    input {
+                        r#type: "checkbox",
+                        checked: anonymize(),
+                        onchange: move |evt| anonymize.set(evt.checked()),
+                    }
+                    " Hide names"
+                }
+            }
+            div {
+                class: "host-controls",
+                if open {
+                    span { "{count} answers so far" }
+                    button {
+                        class: "control-button",
+                        onclick: move |_| send(|game_code| ClientToServer::CloseAnswers { game_code }),
+                        "Stop Answers"
+                    }
+                } else {
+                    button {
+                        class: "control-button",
+                        onclick: move |_| send(|game_code| ClientToServer::OpenAnswers { game_code }),
+                        "Collect Answers"
+                    }
+                }
+                if written.is_some() && !shown {
+                    button {
+                        class: "control-button",
+                        onclick: move |_| send(|game_code| ClientToServer::ShowAnswers { game_code }),
+                        "Show Answers"
+                    }
+                }
+            }
+            ul {
+                class: "player-list",
+                for (text, names, count, class, player_ids) in groups {
+                    li {
+                        class: "player-list-item answer-group {class}",
+                        span {
+                            class: "player-name",
+                            "{text}"
+                            if count > 1 {
+                                span { class: "answer-count", " ×{count}" }
+                            }
+                            if !anonymize() {
+                                span { class: "answer-names", "{names}" }
+                            }
+                        }
+                        button {
+                            class: "score-button",
+                            "aria-label": "Correct",
+                            onclick: {
+                                let player_ids = player_ids.clone();
+                                move |_| grade(player_ids.clone(), Grade::Correct)
+                            },
+                            "✓"
+                        }
+                        button {
+                            class: "score-button",
+                            "aria-label": "Partly correct",
+                            onclick: {
+                                let player_ids = player_ids.clone();
+                                move |_| grade(player_ids.clone(), Grade::Partial)
+                            },
+                            "½"
+                        }
+                        button {
+                            class: "score-button",
+                            "aria-label": "Wrong",
+                            onclick: {
+                                let player_ids = player_ids.clone();
+                                move |_| grade(player_ids.clone(), Grade::Incorrect)
+                            },
+                            "✗"
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Where players type their answer while answers are being collected
+#[component]
+pub fn PlayerAnswerForm() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let mut text = use_signal(String::new);
+    let game_state = app_ctx.game_state.read();
+    let Some(written) = game_state.as_ref().and_then(|game| game.written.as_ref()) else {
+        return rsx! {};
+    };
+    let answered = (*app_ctx.player_id.read()).is_some_and(|id| written.answer_of(&id).is_some());
+    if written.shown || !(written.open || answered) {
+        return rsx! {};
+    }
+
+    rsx! {
+        if answered {
+            p { class: "answer-sent", "Your answer was sent." }
+        } else {
+            form {
+                class: "answer-form",
+                onsubmit: move |_| {
+                    let answer = text.read().trim().to_string();
+                    if let (Some(code), false) = (*app_ctx.game_code.read(), answer.is_empty()) {
+                        app_ctx.send(ClientToServer::SubmitAnswer {
+                            game_code: code,
+                            text: answer,
+                        });
+                        text.set(String::new());
+                    }
+                },
+                input {
+                    r#type: "text",
+                    placeholder: "Your answer",
+                    maxlength: MAX_ANSWER_LENGTH as i64,
+                    value: "{text}",
+                    oninput: move |evt| text.set(evt.value()),
+                }
+                button { class: "control-button", r#type: "submit", "Send" }
+            }
+        }
+    }
+}
+
+/// Everyone's answers once the host showed them
+#[component]
+pub fn ShownAnswers() -> Element {
+    let app_ctx = use_context::<AppContext>();
+    let game_state = app_ctx.game_state.read();
+    let Some(game) = game_state.as_ref() else {
+        return rsx! {};
+    };
+    let Some(written) = game.written.as_ref().filter(|w| w.shown) else {
+        return rsx! {};
+    };
+    let rows: Vec<_> = written
+        .answers
+        .iter()
+        .map(|answer| {
+            (
+                player_name(game, &answer.player_id),
+                answer.text.clone().unwrap_or_default(),
+                grade_class(answer.grade),
+            )
+        })
+        .collect();
+
+    rsx! {
+        div {
+            class: "shown-answers",
+            h3 { "Answers" }
+            ul {
+                class: "player-list",
+                for (name, text, class) in rows {
+                    li {
+                        class: "player-list-item {class}",
+                        span { class: "player-name", "{name}" }
+                        span { "{text}" }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use std::fmt;
+
+use common::{ClientToServer, ErrorCode, Permission};
+use uuid::Uuid;
+
+use crate::SharedState;
+
+/// Why a connection may not send a particular message
+#[derive(Debug, PartialEq, Eq)]
+pub enum AccessError {
+    NotInGame,
+    AlreadyInGame { game_code: usize },
+    WrongGame { claimed: usize },
+    IdMismatch { claimed: Uuid },
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotInGame => write!(f, "You are not part of a game."),
+            Self::AlreadyInGame { game_code } => {
+                write!(f, "You are already part of game '{}'.", game_code)
+            }
+            Self::WrongGame { claimed } => write!(f, "You are not part of game '{}'.", claimed),
+            Self::IdMismatch { claimed } => {
+                write!(f, "You cannot act on behalf of player {}.", claimed)
+            }
+        }
+    }
+}
+
+impl AccessError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::NotInGame => ErrorCode::NotInGame,
+            Self::AlreadyInGame { .. } => ErrorCode::AlreadyInGame,
+            Self::WrongGame { .. } => ErrorCode::WrongGame,
+            Self::IdMismatch { .. } => ErrorCode::IdMismatch,
+        }
+    }
+}
+
+/// Binds every incoming message to the connection that sent it. The game and
+/// the acting player are derived from the connection, never from the payload;
+/// payload ids only have to agree with them. Whether that player's role may
+/// send the message is up to `GameState::apply`. A connection belongs to
+/// one game at a time, so it can't create or enter another one while in it.
+pub fn authorize(
+    msg: &ClientToServer,
+    sender_id: Uuid,
+    state: &SharedState,
+) -> Result<(), AccessError> {
+    if msg.permission() == Permission::Anyone {
+        let enters_game = matches!(
+            msg,
+            ClientToServer::CreateGame
+                | ClientToServer::JoinGame { .. }
+                | ClientToServer::Rejoin { .. }
+        );
+        if enters_game {
+            if let Some(game_code) = state.memberships.get(&sender_id).map(|m| *m) {
+                return Err(AccessError::AlreadyInGame { game_code });
+            }
+        }
+        return Ok(());
+    }
+
+    let game_code = state
+        .memberships
+        .get(&sender_id)
+        .map(|m| *m)
+        .ok_or(AccessError::NotInGame)?;
+    if let Some(claimed) = msg.game_code() {
+        if claimed != game_code {
+            return Err(AccessError::WrongGame { claimed });
+        }
+    }
+    if let ClientToServer::Buzz { player_id, .. } = msg {
+        if *player_id != sender_id {
+            return Err(AccessError::IdMismatch {
+                claimed: *player_id,
+            });
+        }
+    }
+    Ok(())
+}
@@ -1,10 +1,40 @@
 use serde::Deserialize;
 
+use crate::outbox::OverflowPolicy;
+
 #[derive(Deserialize)]
 pub struct Settings {
     pub host: String,
     pub application_port: u16,
     pub frontend_path: String,
+    /// How long a game is kept alive after the host's connection drops.
+    pub host_grace_period_secs: u64,
+    /// How far apart two buzzes may arrive and still be ordered by press time.
+    pub buzz_fairness_window_ms: u64,
+    /// How many messages a single connection may send per second; 0 disables the limit.
+    pub max_messages_per_sec: u32,
+    /// How many messages may wait for a slow connection before the policy kicks in.
+    pub outbound_queue_capacity: usize,
+    /// What happens to a connection whose outbound queue is full.
+    #[serde(default)]
+    pub slow_consumer_policy: OverflowPolicy,
+    pub storage: StorageSettings,
+}
+
+#[derive(Deserialize)]
+pub struct StorageSettings {
+    pub backend: StorageBackend,
+    /// File the `file` backend appends game snapshots to.
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// Games are lost when the server stops
+    Memory,
+    /// Games are snapshotted to `path` and reloaded on startup
+    File,
 }
 
 pub fn get_configuration() -> Result<Settings, config::ConfigError> {
@@ -0,0 +1,299 @@
+use std::time::Duration;
+
+use common::*;
+use tokio::{
+    sync::{mpsc, oneshot, watch},
+    time::Instant,
+};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    kind_of, now_ms, outbox::Kind, rebind_connection, send_serialized, send_to_player, serialize,
+    store::StoredGame, supports, SharedState,
+};
+
+// Commands queued per game before senders have to wait
+const COMMAND_BUFFER: usize = 64;
+
+pub enum GameCommand {
+    Apply {
+        sender_id: Uuid,
+        msg: ClientToServer,
+        ctx: ApplyContext,
+        // Lets the game re-bind the sending connection to a player
+        identity: watch::Sender<Uuid>,
+        done: oneshot::Sender<Result<(), GameError>>,
+    },
+    Disconnect {
+        player_id: Uuid,
+    },
+    Log {
+        token: Uuid,
+        reply: oneshot::Sender<Option<Vec<LoggedEvent>>>,
+    },
+}
+
+/// Cheap, cloneable handle to a running game task.
+#[derive(Clone)]
+pub struct GameHandle {
+    commands: mpsc::Sender<GameCommand>,
+}
+
+impl GameHandle {
+    /// Hands a message to the game and waits until it has been applied, so a
+    /// connection's messages are processed strictly in order.
+    /// Returns `None` if the game has already ended.
+    pub async fn apply(
+        &self,
+        sender_id: Uuid,
+        msg: ClientToServer,
+        ctx: ApplyContext,
+        identity: watch::Sender<Uuid>,
+    ) -> Option<Result<(), GameError>> {
+        let (done, done_rx) = oneshot::channel();
+        let command = GameCommand::Apply {
+            sender_id,
+            msg,
+            ctx,
+            identity,
+            done,
+        };
+        self.commands.send(command).await.ok()?;
+        done_rx.await.ok()
+    }
+
+    pub async fn disconnect(&self, player_id: Uuid) {
+        let _ = self
+            .commands
+            .send(GameCommand::Disconnect { player_id })
+            .await;
+    }
+
+    /// The game's event log so far, or `None` if the game has already ended.
+    /// The inner `None` means `token` is not the host's.
+    pub async fn log(&self, token: Uuid) -> Option<Option<Vec<LoggedEvent>>> {
+        let (reply, reply_rx) = oneshot::channel();
+        self.commands
+            .send(GameCommand::Log { token, reply })
+            .await
+            .ok()?;
+        reply_rx.await.ok()
+    }
+}
+
+/// Starts the task that owns `game` from now on and registers it under `game_code`.
+/// `initial` is carried out first, e.g. to re-arm timers of a restored game,
+/// and the first `stored_events` of its log are already in the store.
+pub fn spawn_game(
+    game_code: usize,
+    game: GameState,
+    initial: Vec<Outbound>,
+    stored_events: usize,
+    state: &SharedState,
+) -> GameHandle {
+    let (commands, rx) = mpsc::channel(COMMAND_BUFFER);
+    let handle = GameHandle { commands };
+    state.games.insert(game_code, handle.clone());
+    tokio::spawn(run_game(
+        game_code,
+        game,
+        initial,
+        stored_events,
+        rx,
+        state.clone(),
+    ));
+    handle
+}
+
+/// Timers a game is waiting on, besides its command queue
+#[derive(Default)]
+struct Deadlines {
+    countdown: Option<(Instant, u64)>,
+    answer: Option<(Instant, u64)>,
+    host_grace: Option<Instant>,
+}
+
+/// Owns a running game. Dropping it takes the game out of service, which
+/// also happens when the game's task panics, so the code doesn't keep
+/// pointing at a dead task and the log still gets archived.
+struct RunningGame {
+    game_code: usize,
+    game: GameState,
+    state: SharedState,
+}
+
+impl Drop for RunningGame {
+    fn drop(&mut self) {
+        let game_code = self.game_code;
+        if std::thread::panicking() {
+            error!("Game {} crashed, closing it", game_code);
+        }
+        let state = &self.state;
+        state.games.remove(&game_code);
+        if let Err(e) = state.store.finish(game_code, &self.game.events) {
+            warn!("Failed to archive game {}: {}", game_code, e);
+        }
+        broadcast_message(&self.game, &ServerToClient::GameClosed, state);
+        for player in self.game.players.iter() {
+            state
+                .memberships
+                .remove_if(&player.id(), |_, code| *code == game_code);
+        }
+    }
+}
+
+async fn run_game(
+    game_code: usize,
+    game: GameState,
+    initial: Vec<Outbound>,
+    mut stored_events: usize,
+    mut commands: mpsc::Receiver<GameCommand>,
+    state: SharedState,
+) {
+    let mut running = RunningGame {
+        game_code,
+        game,
+        state: state.clone(),
+    };
+    let game = &mut running.game;
+    let mut deadlines = Deadlines::default();
+    execute(initial, game_code, game, None, &mut deadlines, &state).await;
+    save(game_code, game, stored_events, &state);
+    stored_events = game.events.len();
+    let mut saved_seq = game.seq;
+    loop {
+        // Every change to the game bumps its sequence number
+        if game.seq != saved_seq {
+            save(game_code, game, stored_events, &state);
+            stored_events = game.events.len();
+            saved_seq = game.seq;
+        }
+
+        tokio::select! {
+            command = commands.recv() => {
+                let Some(command) = command else {
+                    break;
+                };
+                match command {
+                    GameCommand::Apply { sender_id, msg, ctx, identity, done } => {
+                        info!("Game {}: {} sent {}", game_code, sender_id, msg.name());
+                        let result = match game.apply(sender_id, msg, &ctx) {
+                            Ok(outbound) => {
+                                let connection = Some((sender_id, &identity));
+                                execute(outbound, game_code, game, connection, &mut deadlines, &state).await;
+                                Ok(())
+                            }
+                            Err(e) => Err(e),
+                        };
+                        let _ = done.send(result);
+                    }
+                    GameCommand::Disconnect { player_id } => {
+                        info!("Player {} disconnected from game {}", player_id, game_code);
+                        let grace_period_secs = state.host_grace_period.as_secs();
+                        let outbound = game.disconnect(player_id, grace_period_secs, now_ms());
+                        execute(outbound, game_code, game, None, &mut deadlines, &state).await;
+                    }
+                    GameCommand::Log { token, reply } => {
+                        let events = game.is_host_token(&token).then(|| game.events.clone());
+                        let _ = reply.send(events);
+                    }
+                }
+            }
+            _ = sleep_until(deadlines.countdown.map(|(at, _)| at)) => {
+                let (_, deadline_ms) = deadlines.countdown.take().expect("countdown deadline was set");
+                let outbound = game.countdown_elapsed(deadline_ms);
+                execute(outbound, game_code, game, None, &mut deadlines, &state).await;
+            }
+            _ = sleep_until(deadlines.answer.map(|(at, _)| at)) => {
+                let (_, deadline_ms) = deadlines.answer.take().expect("answer deadline was set");
+                let outbound = game.answer_clock_elapsed(deadline_ms);
+                execute(outbound, game_code, game, None, &mut deadlines, &state).await;
+            }
+            _ = sleep_until(deadlines.host_grace) => {
+                deadlines.host_grace = None;
+                if !game.host_connected {
+                    info!("Host did not return, removing game {}", game_code);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn save(game_code: usize, game: &GameState, stored_events: usize, state: &SharedState) {
+    let new_events = &game.events[stored_events..];
+    if let Err(e) = state
+        .store
+        .save(game_code, &StoredGame::snapshot(game), new_events)
+    {
+        warn!("Failed to store game {}: {}", game_code, e);
+    }
+}
+
+/// Waits for `deadline`, or forever if there is none
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Carries out what the game's state machine asked for. `connection` is the
+/// connection whose message caused it, if any, so it can be bound to a player.
+async fn execute(
+    outbound: Vec<Outbound>,
+    game_code: usize,
+    game: &GameState,
+    connection: Option<(Uuid, &watch::Sender<Uuid>)>,
+    deadlines: &mut Deadlines,
+    state: &SharedState,
+) {
+    for item in outbound {
+        match item {
+            Outbound::Send(player_id, message) => send_to_player(player_id, &message, state).await,
+            Outbound::Broadcast(message) => broadcast_message(game, &message, state),
+            Outbound::Bind(player_id) => {
+                state.memberships.insert(player_id, game_code);
+                if let Some((connection_id, identity)) = connection {
+                    rebind_connection(connection_id, player_id, identity, state);
+                }
+            }
+            Outbound::ScheduleCountdownExpiry(deadline_ms) => {
+                let delay = Duration::from_millis(deadline_ms.saturating_sub(now_ms()));
+                deadlines.countdown = Some((Instant::now() + delay, deadline_ms));
+            }
+            Outbound::CancelCountdownExpiry => deadlines.countdown = None,
+            Outbound::ScheduleAnswerExpiry(deadline_ms) => {
+                let delay = Duration::from_millis(deadline_ms.saturating_sub(now_ms()));
+                deadlines.answer = Some((Instant::now() + delay, deadline_ms));
+            }
+            Outbound::CancelAnswerExpiry => deadlines.answer = None,
+            Outbound::StartHostGrace => {
+                deadlines.host_grace = Some(Instant::now() + state.host_grace_period);
+            }
+            Outbound::CancelHostGrace => deadlines.host_grace = None,
+        }
+    }
+}
+
+/// Helper to send the same message to every player in a game
+fn broadcast_message(game: &GameState, message: &ServerToClient, state: &SharedState) {
+    let kind = kind_of(message);
+    let text = serialize(message);
+    // Clients that can't apply deltas get the whole state instead
+    let mut snapshot = None;
+    for player_ref in game.players.iter() {
+        let player_id = player_ref.id();
+        if kind == Kind::Delta && !supports(player_id, Capability::Deltas, state) {
+            let snapshot = snapshot.get_or_insert_with(|| {
+                serialize(&ServerToClient::GameStateUpdate {
+                    game_state: game.to_json(),
+                })
+            });
+            send_serialized(player_id, Kind::Snapshot, snapshot, state);
+        } else {
+            send_serialized(player_id, kind, &text, state);
+        }
+    }
+}
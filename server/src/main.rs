@@ -1,40 +1,64 @@
 use axum::{
     extract::{
-        ws::{Message, WebSocket},
-        State, WebSocketUpgrade,
+        ws::{Message, Utf8Bytes, WebSocket},
+        Path, State, WebSocketUpgrade,
     },
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
     response::Response,
     routing::get,
-    Router,
+    Json, Router,
 };
 use common::*;
-use configuration::get_configuration;
+use configuration::{get_configuration, StorageBackend};
 use dashmap::DashMap;
 use futures_util::{SinkExt, StreamExt};
 use rand::Rng;
 use std::{
-    collections::{HashMap, VecDeque},
     net::SocketAddr,
     sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use tokio::sync::mpsc;
+use game::{spawn_game, GameHandle};
+use access::AccessError;
+use outbox::{Kind, Metrics, OverflowPolicy, Outbox};
+use rate_limit::RateLimiter;
+use store::{FileStore, GameStore, MemoryStore};
+use tokio::sync::watch;
 use tower_http::services::ServeDir;
 use tower_http::trace::TraceLayer;
 use tower_http::{
     cors::{Any, CorsLayer},
     trace::DefaultMakeSpan,
 };
-use tracing::{info, warn};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
+mod access;
 mod configuration;
+mod game;
+mod outbox;
+mod rate_limit;
+mod store;
 
 // Holds all game states and player connections
-#[derive(Default)]
 struct AppState {
-    games: DashMap<usize, GameState>,
-    // Maps a player's unique ID to their WebSocket sender
-    connections: DashMap<Uuid, mpsc::UnboundedSender<Message>>,
+    // Each game runs in its own task; this only holds the handles to reach them
+    games: DashMap<usize, GameHandle>,
+    // Maps a player's unique ID to the outbound queue of their WebSocket
+    connections: DashMap<Uuid, Arc<Outbox>>,
+    outbound_queue_capacity: usize,
+    slow_consumer_policy: OverflowPolicy,
+    metrics: Metrics,
+    // How long a game survives without its host
+    host_grace_period: Duration,
+    // Which game each connected or resumable player belongs to
+    memberships: DashMap<Uuid, usize>,
+    // Recent clock offset samples per player, used to correct buzz times
+    clock_samples: DashMap<Uuid, ClockSamples>,
+    buzz_fairness_window_ms: u64,
+    max_messages_per_sec: u32,
+    // Snapshots of every game, so they survive a restart
+    store: Box<dyn GameStore>,
 }
 
 type SharedState = Arc<AppState>;
@@ -43,14 +67,39 @@ type SharedState = Arc<AppState>;
 async fn main() {
     tracing_subscriber::fmt::init();
 
-    let state = SharedState::new(AppState::default());
+    let configuration = get_configuration().expect("Failed to read configuration.");
 
-    let cors = CorsLayer::new().allow_origin(Any).allow_methods(Any);
+    let store: Box<dyn GameStore> = match configuration.storage.backend {
+        StorageBackend::Memory => Box::new(MemoryStore::default()),
+        StorageBackend::File => Box::new(
+            FileStore::open(&configuration.storage.path).expect("Failed to open the game store."),
+        ),
+    };
 
-    let configuration = get_configuration().expect("Failed to read configuration.");
+    let state = SharedState::new(AppState {
+        games: DashMap::new(),
+        connections: DashMap::new(),
+        outbound_queue_capacity: configuration.outbound_queue_capacity,
+        slow_consumer_policy: configuration.slow_consumer_policy,
+        metrics: Metrics::default(),
+        host_grace_period: Duration::from_secs(configuration.host_grace_period_secs),
+        memberships: DashMap::new(),
+        clock_samples: DashMap::new(),
+        buzz_fairness_window_ms: configuration.buzz_fairness_window_ms,
+        max_messages_per_sec: configuration.max_messages_per_sec,
+        store,
+    });
+    restore_games(&state);
+
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods(Any)
+        .allow_headers(Any);
 
     let app = Router::new()
         .route("/ws", get(ws_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/games/{game_code}/log", get(game_log_handler))
         .fallback_service(ServeDir::new(configuration.frontend_path))
         .with_state(state)
         .layer(
@@ -70,20 +119,87 @@ async fn main() {
         .unwrap();
 }
 
+/// Brings back the games that were running when the server last stopped.
+/// Their members reconnect with `Rejoin` like after any dropped connection.
+fn restore_games(state: &SharedState) {
+    let stored = match state.store.load_all() {
+        Ok(stored) => stored,
+        Err(e) => {
+            error!("Failed to load stored games: {}", e);
+            return;
+        }
+    };
+    info!("Restoring {} games", stored.len());
+    for (game_code, stored, events) in stored {
+        let stored_events = events.len();
+        let mut game = stored.restore(events);
+        let outbound = game.connections_lost(now_ms());
+        spawn_game(game_code, game, outbound, stored_events, state);
+    }
+}
+
+/// The session token sent as `Authorization: Bearer <token>`, if any
+fn bearer_token(headers: &HeaderMap) -> Option<Uuid> {
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?;
+    value.strip_prefix("Bearer ")?.trim().parse().ok()
+}
+
+/// The event log of a running or finished game, for reviewing it afterwards.
+/// While the game is running only its host may read it, with their session
+/// token in an `Authorization: Bearer` header. The token is kept out of the
+/// URL so it doesn't end up in access logs or the browser history.
+async fn game_log_handler(
+    Path(game_code): Path<usize>,
+    headers: HeaderMap,
+    State(state): State<SharedState>,
+) -> Result<Json<Vec<LoggedEvent>>, StatusCode> {
+    let running = state.games.get(&game_code).map(|g| g.clone());
+    if let Some(game) = running {
+        let token = bearer_token(&headers).ok_or(StatusCode::FORBIDDEN)?;
+        match game.log(token).await {
+            Some(Some(events)) => return Ok(Json(events)),
+            Some(None) => return Err(StatusCode::FORBIDDEN),
+            None => {}
+        }
+    }
+    let finished_log = tokio::task::spawn_blocking(move || state.store.finished_log(game_code))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    match finished_log {
+        Ok(Some(events)) => Ok(Json(events)),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            error!("Failed to read the log of game {}: {}", game_code, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
 async fn ws_handler(ws: WebSocketUpgrade, State(state): State<SharedState>) -> Response {
     ws.on_upgrade(|socket| handle_socket(socket, state))
 }
 
+async fn metrics_handler(State(state): State<SharedState>) -> String {
+    let depths = state.connections.iter().map(|c| c.depth()).collect::<Vec<_>>();
+    state.metrics.render(depths.into_iter())
+}
+
 async fn handle_socket(socket: WebSocket, state: SharedState) {
-    let player_id = Uuid::new_v4();
+    // The identity starts out as a fresh id, but a `Rejoin` can re-bind this
+    // connection to an existing player, so it is shared with the receive task.
+    let (identity, identity_rx) = watch::channel(Uuid::new_v4());
     let (mut ws_sender, mut ws_receiver) = socket.split();
 
-    let (tx, mut rx) = mpsc::unbounded_channel();
-    state.connections.insert(player_id, tx);
+    let outbox = Arc::new(Outbox::new(
+        state.outbound_queue_capacity,
+        state.slow_consumer_policy,
+    ));
+    state.connections.insert(*identity.borrow(), outbox.clone());
 
     // This task forwards messages from our application logic to the actual WebSocket
+    let send_outbox = outbox.clone();
     let mut send_task = tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
+        while let Some(msg) = send_outbox.pop().await {
             if ws_sender.send(msg).await.is_err() {
                 break;
             }
@@ -94,12 +210,32 @@ async fn handle_socket(socket: WebSocket, state: SharedState) {
     let recv_state = state.clone();
     let mut recv_task = tokio::spawn(async move {
         let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
+        let mut clock_interval = tokio::time::interval(std::time::Duration::from_secs(5));
+        let mut greeted = false;
+        let mut limiter = RateLimiter::new(recv_state.max_messages_per_sec);
         loop {
+            let player_id = *identity.borrow();
             tokio::select! {
                 Some(Ok(msg)) = ws_receiver.next() => {
                     if let Message::Text(text) = msg {
-                        match serde_json::from_str::<ClientToServer>(&text) {
-                            Ok(c2s_msg) => handle_c2s_message(c2s_msg, player_id, recv_state.clone()).await,
+                        match serde_json::from_str::<ClientRequest>(&text) {
+                            Ok(ClientRequest { msg: ClientToServer::Hello { protocol_version, capabilities }, .. }) => {
+                                greeted = greet(player_id, protocol_version, &capabilities, &recv_state).await;
+                            }
+                            Ok(request) if greeted => {
+                                let result = if limiter.allow() {
+                                    handle_c2s_message(request.msg, player_id, &identity, recv_state.clone()).await
+                                } else {
+                                    Err(Rejection::rate_limited())
+                                };
+                                // A `Rejoin` may have re-bound the connection in the meantime
+                                let reply_to = *identity.borrow();
+                                reply(reply_to, request.request_id, result, &recv_state).await;
+                            }
+                            // Bundles built before the handshake existed never say hello
+                            Ok(_) => {
+                                greet(player_id, 0, &[], &recv_state).await;
+                            }
                             Err(e) => warn!("Failed to parse C2S message: {}", e),
                         }
                     } else if let Message::Close(_) = msg {
@@ -107,16 +243,19 @@ async fn handle_socket(socket: WebSocket, state: SharedState) {
                     }
                 },
                 _ = interval.tick() => {
-                    let sender = recv_state.connections.get(&player_id);
-                    if let Some(sender) = sender {
-                        if sender.send(Message::Ping(vec![].into())).is_err() {
-                            // If sending fails, the connection is likely closed
-                            break;
-                        }
-                    } else {
+                    let sender = recv_state.connections.get(&player_id).map(|c| c.clone());
+                    let ping = Message::Ping(vec![].into());
+                    if !sender.is_some_and(|s| s.push(Kind::Event, ping, &recv_state.metrics)) {
+                        // The connection was closed or handed over to a newer socket
                         break;
                     }
                 }
+                _ = clock_interval.tick() => {
+                    if supports(player_id, Capability::ClockSync, &recv_state) {
+                        let ping = ServerToClient::ClockPing { server_time_ms: now_ms() };
+                        send_to_player(player_id, &ping, &recv_state).await;
+                    }
+                }
             }
         }
     });
@@ -125,245 +264,268 @@ async fn handle_socket(socket: WebSocket, state: SharedState) {
         _ = (&mut send_task) => recv_task.abort(),
         _ = (&mut recv_task) => send_task.abort(),
     };
+    outbox.close();
 
+    let player_id = *identity_rx.borrow();
     info!("Player {} disconnected", player_id);
-    state.connections.remove(&player_id);
 
-    let game_code_to_process: Option<usize> = state
-        .games
-        .iter()
-        .find(|g| g.players.contains_key(&player_id))
-        .map(|g| *g.key());
+    // If the player already resumed on a newer connection, this socket no longer
+    // owns the entry and must not mark them as disconnected.
+    let owned = state
+        .connections
+        .remove_if(&player_id, |_, other| Arc::ptr_eq(other, &outbox))
+        .is_some();
+    if !owned {
+        return;
+    }
+    state.clock_samples.remove(&player_id);
+
+    let game_code_to_process: Option<usize> = state.memberships.get(&player_id).map(|m| *m);
 
     if let Some(game_code) = game_code_to_process {
-        let mut game = state.games.get_mut(&game_code).unwrap();
-
-        // If the host disconnects, remove the game entirely
-        if game.host_id == player_id {
-            info!("Host disconnected, removing game {}", game_code);
-            state.games.remove(&game_code);
+        let Some(game) = state.games.get(&game_code).map(|g| g.clone()) else {
+            state.memberships.remove(&player_id);
             return;
+        };
+        game.disconnect(player_id).await;
+    }
+}
+
+/// Answers a `Hello`. An incompatible client is told to upgrade and is
+/// disconnected once that reply is out. Returns whether the client may go on.
+async fn greet(
+    connection_id: Uuid,
+    protocol_version: u32,
+    capabilities: &[Capability],
+    state: &SharedState,
+) -> bool {
+    if !is_compatible(protocol_version) {
+        info!(
+            "Connection {} speaks protocol {}, asking it to upgrade",
+            connection_id, protocol_version
+        );
+        let upgrade = ServerToClient::UpgradeRequired {
+            protocol_version: PROTOCOL_VERSION,
+            min_protocol_version: MIN_PROTOCOL_VERSION,
+        };
+        send_to_player(connection_id, &upgrade, state).await;
+        if let Some(outbox) = state.connections.get(&connection_id) {
+            outbox.close();
         }
+        return false;
+    }
 
-        // If a player disconnects, update their state
-        if let Some(mut actor) = game.players.get_mut(&player_id) {
-            let player_name = actor.name().to_string();
-            *actor = Actor::Disconnected {
-                id: player_id,
-                name: player_name,
-            };
-            info!("Player {} disconnected from game {}", player_id, game_code);
+    let capabilities = negotiate(capabilities);
+    if let Some(outbox) = state.connections.get(&connection_id) {
+        outbox.set_capabilities(capabilities.clone());
+    }
+    let welcome = ServerToClient::Welcome {
+        protocol_version: PROTOCOL_VERSION,
+        capabilities,
+    };
+    send_to_player(connection_id, &welcome, state).await;
+    true
+}
+
+/// Whether the player's connection agreed on `capability` in its handshake
+fn supports(player_id: Uuid, capability: Capability, state: &SharedState) -> bool {
+    state
+        .connections
+        .get(&player_id)
+        .is_some_and(|outbox| outbox.supports(capability))
+}
+
+/// Why a request failed, as reported back to its sender
+#[derive(Debug)]
+struct Rejection {
+    code: ErrorCode,
+    message: String,
+}
+
+impl Rejection {
+    fn game_not_found(game_code: usize) -> Self {
+        Self {
+            code: ErrorCode::GameNotFound,
+            message: format!("Game '{}' not found.", game_code),
         }
+    }
 
-        let game_clone = game.clone();
-        let state_clone = state.clone();
-        tokio::spawn(async move {
-            broadcast_state_update(&game_clone, &state_clone).await;
-        });
+    fn rate_limited() -> Self {
+        Self {
+            code: ErrorCode::RateLimited,
+            message: "You are sending too fast, slow down.".to_string(),
+        }
     }
 }
 
-async fn handle_c2s_message(msg: ClientToServer, sender_id: Uuid, state: SharedState) {
+impl From<AccessError> for Rejection {
+    fn from(e: AccessError) -> Self {
+        Self {
+            code: e.code(),
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<GameError> for Rejection {
+    fn from(e: GameError) -> Self {
+        Self {
+            code: e.code(),
+            message: e.to_string(),
+        }
+    }
+}
+
+/// Acknowledges a request that carried an id, and reports every failure.
+async fn reply(
+    connection_id: Uuid,
+    request_id: Option<u64>,
+    result: Result<(), Rejection>,
+    state: &SharedState,
+) {
+    let response = match result {
+        Ok(()) => match request_id {
+            Some(request_id) => ServerToClient::Ack { request_id },
+            None => return,
+        },
+        Err(rejection) => ServerToClient::Error {
+            request_id,
+            code: rejection.code,
+            message: rejection.message,
+        },
+    };
+    send_to_player(connection_id, &response, state).await;
+}
+
+async fn handle_c2s_message(
+    msg: ClientToServer,
+    sender_id: Uuid,
+    identity: &watch::Sender<Uuid>,
+    state: SharedState,
+) -> Result<(), Rejection> {
+    // Host-only and player-only messages are checked here once, so the arms
+    // below can trust the game code and player id they carry.
+    if let Err(e) = access::authorize(&msg, sender_id, &state) {
+        warn!("Rejected {} from {}: {:?}", msg.name(), sender_id, e);
+        return Err(e.into());
+    }
+
     match msg {
         ClientToServer::CreateGame => {
             let game_code = generate_game_code(&state);
-
-            // Create a map and add the host to it immediately
-            let players = DashMap::new();
-            players.insert(sender_id, Actor::Host { id: sender_id });
-
-            let game_state = GameState {
-                host_id: sender_id,
-                globally_locked: false,
-                buzzer_order: VecDeque::new(),
-                players,
-                scores: HashMap::new(),
-                player_join_order: vec![sender_id],
-                time_limit: None,
-            };
+            let mut game_state = GameState::new(sender_id, now_ms());
+            let host_token = game_state.issue_session_token(sender_id);
 
             info!("Game created: {} by player {}", game_code, sender_id);
 
             let response = ServerToClient::GameCreated {
-                game_code: game_code.clone(),
+                game_code,
                 player_id: sender_id,
+                host_token,
                 game_state: game_state.to_json(),
             };
-            state.games.insert(game_code, game_state.clone());
+            state.memberships.insert(sender_id, game_code);
+            spawn_game(game_code, game_state, Vec::new(), 0, &state);
             send_to_player(sender_id, &response, &state).await;
         }
-        ClientToServer::JoinGame {
-            game_code,
-            mut player_name,
+        ClientToServer::ClockPong {
+            server_time_ms,
+            client_time_ms,
         } => {
-            player_name = player_name.trim().to_string();
-
-            if player_name.is_empty() {
-                let error_msg = ServerToClient::Error {
-                    message: "Player name cannot be empty.".to_string(),
-                };
-                send_to_player(sender_id, &error_msg, &state).await;
-                return;
-            }
-
-            if let Some(mut game) = state.games.get_mut(&game_code) {
-                let name_exists = game.players.iter().any(|p| p.name() == player_name);
-                if name_exists {
-                    let error_msg = ServerToClient::Error {
-                        message: format!("Player name '{}' is already taken.", player_name),
-                    };
-                    send_to_player(sender_id, &error_msg, &state).await;
-                    return;
-                }
-
-                game.players.insert(
-                    sender_id,
-                    Actor::Player {
-                        id: sender_id,
-                        name: player_name.clone(),
-                    },
-                );
-                game.scores.insert(sender_id, 0);
-                game.player_join_order.push(sender_id);
-                let response = ServerToClient::GameJoined {
-                    player_id: sender_id,
-                    player_name,
-                    game_state: game.to_json(),
-                };
-                send_to_player(sender_id, &response, &state).await;
-                broadcast_state_update(&game, &state).await;
-            } else {
-                let err = ServerToClient::Error {
-                    message: format!("Game '{}' not found.", game_code),
+            let sample = ClockEstimate::from_round_trip(server_time_ms, client_time_ms, now_ms());
+            let best = {
+                let mut samples = state.clock_samples.entry(sender_id).or_default();
+                samples.push(sample);
+                samples.best()
+            };
+            if let Some(best) = best {
+                let sync = ServerToClient::ClockSync {
+                    offset_ms: best.offset_ms,
+                    rtt_ms: best.rtt_ms,
                 };
-                send_to_player(sender_id, &err, &state).await;
-            }
-        }
-        ClientToServer::Buzz {
-            game_code,
-            player_id,
-        } => {
-            if let Some(mut game) = state.games.get_mut(&game_code) {
-                if !game.globally_locked {
-                    let Some(player_name) =
-                        game.players.get(&player_id).map(|p| p.name().to_owned())
-                    else {
-                        return;
-                    };
-                    info!("Player {} buzzed in game {}", player_name, game_code);
-                    game.buzzer_order
-                        .push_back((player_id, player_name.clone()));
-                    let buzz_msg = ServerToClient::PlayerBuzzed {
-                        player_id,
-                        player_name,
-                    };
-                    // Use your existing `send_to_player` helper to target the host
-                    send_to_player(game.host_id, &buzz_msg, &state).await;
-                    broadcast_state_update(&game, &state).await;
-                }
-            }
-        }
-        ClientToServer::Lock { ref game_code } | ClientToServer::Unlock { ref game_code } => {
-            if let Some(mut game) = state.games.get_mut(game_code) {
-                if game.host_id == sender_id {
-                    // Only host can lock/unlock
-                    game.globally_locked = matches!(msg, ClientToServer::Lock { .. });
-                    broadcast_state_update(&game, &state).await;
-                }
-            }
-        }
-        ClientToServer::Clear { game_code } => {
-            if let Some(mut game) = state.games.get_mut(&game_code) {
-                if game.host_id == sender_id {
-                    // Only host can clear
-                    game.buzzer_order = VecDeque::new();
-                    broadcast_state_update(&game, &state).await;
-                }
-            }
-        }
-        ClientToServer::UpdateScore {
-            game_code,
-            player_id,
-            delta,
-        } => {
-            if let Some(mut game) = state.games.get_mut(&game_code) {
-                if game.host_id == sender_id {
-                    let score = game.scores.entry(player_id).or_insert(0);
-                    *score += delta;
-                    info!(
-                        "Host {} updated score for player {} to {}",
-                        sender_id, player_id, *score
-                    );
-                    broadcast_state_update(&game, &state).await;
-                }
+                send_to_player(sender_id, &sync, &state).await;
             }
         }
-        ClientToServer::StartCountdown {
-            game_code,
-            time_limit,
-        } => {
-            if let Some(mut game) = state.games.get_mut(&game_code) {
-                if game.host_id == sender_id {
-                    game.time_limit = Some(time_limit);
-                    broadcast_start_countdown(&game, &state, time_limit).await;
-                    broadcast_timer_paused(&game, &state, false).await;
-                }
-            }
-        }
-        ClientToServer::PauseTimer { game_code } => {
-            if let Some(game) = state.games.get(&game_code) {
-                if game.host_id == sender_id {
-                    broadcast_timer_paused(&game, &state, true).await;
-                }
-            }
+        msg => {
+            let Some(game_code) = msg.game_code() else {
+                return Ok(());
+            };
+            // Clone the handle so no map guard is held while the game works
+            let Some(game) = state.games.get(&game_code).map(|g| g.clone()) else {
+                return Err(Rejection::game_not_found(game_code));
+            };
+
+            let ctx = ApplyContext {
+                now_ms: now_ms(),
+                clock: state.clock_samples.get(&sender_id).and_then(|s| s.best()),
+                buzz_fairness_window_ms: state.buzz_fairness_window_ms,
+            };
+            game.apply(sender_id, msg, ctx, identity.clone())
+                .await
+                .ok_or_else(|| Rejection::game_not_found(game_code))??;
         }
-        ClientToServer::ResumeTimer { game_code } => {
-            if let Some(game) = state.games.get(&game_code) {
-                if game.host_id == sender_id {
-                    broadcast_timer_paused(&game, &state, false).await;
-                }
-            }
+    }
+    Ok(())
+}
+
+/// Moves a connection's sender over to `player_id` and makes the socket speak for
+/// that player from now on. Any older connection of the same player is dropped.
+fn rebind_connection(
+    connection_id: Uuid,
+    player_id: Uuid,
+    identity: &watch::Sender<Uuid>,
+    state: &SharedState,
+) {
+    if connection_id == player_id {
+        return;
+    }
+    if let Some((_, outbox)) = state.connections.remove(&connection_id) {
+        if let Some(stale) = state.connections.insert(player_id, outbox) {
+            stale.close();
         }
     }
+    if let Some((_, samples)) = state.clock_samples.remove(&connection_id) {
+        state.clock_samples.insert(player_id, samples);
+    }
+    state.memberships.remove(&connection_id);
+    identity.send_replace(player_id);
 }
 
 /// Helper to serialize a message and send it to a single player
 async fn send_to_player(player_id: Uuid, message: &ServerToClient, state: &SharedState) {
-    if let Some(tx) = state.connections.get(&player_id) {
-        let json_msg = serde_json::to_string(message).unwrap();
-        if tx.send(Message::Text(json_msg.into())).is_err() {
-            warn!("Failed to send message to player {}", player_id);
-        }
-    }
+    send_serialized(player_id, kind_of(message), &serialize(message), state);
 }
 
-/// Helper to broadcast the current game state to all players in a game
-async fn broadcast_state_update(game: &GameState, state: &SharedState) {
-    let update_msg = ServerToClient::GameStateUpdate {
-        game_state: game.to_json(),
-    };
-    for player_ref in game.players.iter() {
-        let player_id = player_ref.id();
-        send_to_player(player_id, &update_msg, state).await;
-    }
+fn serialize(message: &ServerToClient) -> Utf8Bytes {
+    serde_json::to_string(message).unwrap().into()
 }
 
-async fn broadcast_start_countdown(game: &GameState, state: &SharedState, time_limit: u32) {
-    let update_msg = ServerToClient::CountdownStarted { time_limit };
-    for player_ref in game.players.iter() {
-        let player_id = player_ref.id();
-        send_to_player(player_id, &update_msg, state).await;
+fn kind_of(message: &ServerToClient) -> Kind {
+    match message {
+        ServerToClient::GameStateUpdate { .. } => Kind::Snapshot,
+        ServerToClient::Delta { .. } => Kind::Delta,
+        _ => Kind::Event,
     }
 }
 
-async fn broadcast_timer_paused(game: &GameState, state: &SharedState, paused: bool) {
-    let update_msg = ServerToClient::TimerPaused { paused };
-    for player_ref in game.players.iter() {
-        let player_id = player_ref.id();
-        send_to_player(player_id, &update_msg, state).await;
+/// Queues an already serialized message; the bytes are shared, not copied,
+/// so a broadcast only serializes once.
+fn send_serialized(player_id: Uuid, kind: Kind, text: &Utf8Bytes, state: &SharedState) {
+    let Some(outbox) = state.connections.get(&player_id).map(|c| c.clone()) else {
+        return;
+    };
+    if !outbox.push(kind, Message::Text(text.clone()), &state.metrics) {
+        warn!("Outbound queue of player {} is closed", player_id);
     }
 }
 
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("System clock is before the unix epoch")
+        .as_millis() as u64
+}
+
 fn generate_game_code(state: &SharedState) -> usize {
     loop {
         let mut rng = rand::rng();
@@ -0,0 +1,280 @@
+use std::{
+    collections::VecDeque,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use axum::extract::ws::Message;
+use common::Capability;
+use serde::Deserialize;
+use tokio::sync::Notify;
+
+/// What to do when a connection's outbound queue is full
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued snapshot or delta to make room; the client
+    /// notices the gap in the sequence and asks for a snapshot. When only
+    /// one-off events are queued, which can't be sent again, the connection
+    /// is closed instead.
+    #[default]
+    DropOldest,
+    /// Close the connection; the client reconnects and resumes its session
+    Disconnect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// A full game state snapshot; a newer one makes a queued one obsolete
+    Snapshot,
+    /// A sequenced change that is already contained in any later snapshot
+    Delta,
+    Event,
+}
+
+/// Counters for all outbound queues, exposed on `/metrics`
+#[derive(Default)]
+pub struct Metrics {
+    pub queued_total: AtomicU64,
+    pub coalesced_total: AtomicU64,
+    pub dropped_total: AtomicU64,
+    pub slow_consumer_disconnects_total: AtomicU64,
+    pub max_queue_depth: AtomicU64,
+}
+
+impl Metrics {
+    /// Renders the counters plus the current queue depths in the Prometheus text format
+    pub fn render(&self, depths: impl Iterator<Item = usize>) -> String {
+        let (connections, depth_total, depth_max) =
+            depths.fold((0, 0, 0), |(n, sum, max), d| (n + 1, sum + d, max.max(d)));
+        let mut out = String::new();
+        let mut line = |name: &str, value: u64| {
+            let _ = writeln!(out, "buzzer_{} {}", name, value);
+        };
+        line("connections", connections);
+        line("outbound_queue_depth_total", depth_total as u64);
+        line("outbound_queue_depth_max", depth_max as u64);
+        line(
+            "outbound_queue_depth_high_water",
+            self.max_queue_depth.load(Ordering::Relaxed),
+        );
+        line(
+            "outbound_messages_total",
+            self.queued_total.load(Ordering::Relaxed),
+        );
+        line(
+            "outbound_coalesced_total",
+            self.coalesced_total.load(Ordering::Relaxed),
+        );
+        line(
+            "outbound_dropped_total",
+            self.dropped_total.load(Ordering::Relaxed),
+        );
+        line(
+            "slow_consumer_disconnects_total",
+            self.slow_consumer_disconnects_total.load(Ordering::Relaxed),
+        );
+        out
+    }
+}
+
+/// Bounded outbound queue of a single connection. A queued snapshot replaces
+/// any snapshots and deltas queued before it, so a slow client skips straight
+/// to the latest state. Also remembers what the client said it supports, as
+/// that decides what may be queued for it.
+pub struct Outbox {
+    queue: Mutex<Queue>,
+    ready: Notify,
+    capacity: usize,
+    policy: OverflowPolicy,
+    capabilities: Mutex<Vec<Capability>>,
+}
+
+#[derive(Default)]
+struct Queue {
+    items: VecDeque<(Kind, Message)>,
+    closed: bool,
+}
+
+impl Outbox {
+    pub fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        Self {
+            queue: Mutex::new(Queue::default()),
+            ready: Notify::new(),
+            capacity: capacity.max(1),
+            policy,
+            capabilities: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Records the capabilities agreed on in the handshake
+    pub fn set_capabilities(&self, capabilities: Vec<Capability>) {
+        *self.capabilities.lock().unwrap() = capabilities;
+    }
+
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities.lock().unwrap().contains(&capability)
+    }
+
+    /// Queues a message. Returns false once the connection is closed,
+    /// including when it was just closed for falling too far behind.
+    pub fn push(&self, kind: Kind, message: Message, metrics: &Metrics) -> bool {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.closed {
+            return false;
+        }
+
+        if kind == Kind::Snapshot {
+            let before = queue.items.len();
+            queue
+                .items
+                .retain(|(k, _)| !matches!(k, Kind::Snapshot | Kind::Delta));
+            let coalesced = (before - queue.items.len()) as u64;
+            metrics
+                .coalesced_total
+                .fetch_add(coalesced, Ordering::Relaxed);
+        }
+        if queue.items.len() >= self.capacity {
+            let droppable = match self.policy {
+                OverflowPolicy::DropOldest => {
+                    queue.items.iter().position(|(k, _)| *k != Kind::Event)
+                }
+                OverflowPolicy::Disconnect => None,
+            };
+            match droppable {
+                Some(oldest) => {
+                    queue.items.remove(oldest);
+                    metrics.dropped_total.fetch_add(1, Ordering::Relaxed);
+                }
+                None => {
+                    queue.closed = true;
+                    queue.items.clear();
+                    drop(queue);
+                    metrics
+                        .slow_consumer_disconnects_total
+                        .fetch_add(1, Ordering::Relaxed);
+                    self.ready.notify_one();
+                    return false;
+                }
+            }
+        }
+
+        queue.items.push_back((kind, message));
+        let depth = queue.items.len() as u64;
+        drop(queue);
+        metrics.queued_total.fetch_add(1, Ordering::Relaxed);
+        metrics.max_queue_depth.fetch_max(depth, Ordering::Relaxed);
+        self.ready.notify_one();
+        true
+    }
+
+    /// Waits for the next message; `None` once the outbox is closed and drained.
+    pub async fn pop(&self) -> Option<Message> {
+        loop {
+            {
+                let mut queue = self.queue.lock().unwrap();
+                if let Some((_, message)) = queue.items.pop_front() {
+                    return Some(message);
+                }
+                if queue.closed {
+                    return None;
+                }
+            }
+            self.ready.notified().await;
+        }
+    }
+
+    pub fn close(&self) {
+        self.queue.lock().unwrap().closed = true;
+        self.ready.notify_one();
+    }
+
+    pub fn depth(&self) -> usize {
+        self.queue.lock().unwrap().items.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(body: &str) -> Message {
+        Message::Text(body.into())
+    }
+
+    /// Takes everything queued right now
+    async fn drain(outbox: &Outbox) -> Vec<String> {
+        let mut bodies = Vec::new();
+        while outbox.depth() > 0 {
+            match outbox.pop().await {
+                Some(Message::Text(body)) => bodies.push(body.to_string()),
+                other => panic!("unexpected message {:?}", other),
+            }
+        }
+        bodies
+    }
+
+    #[tokio::test]
+    async fn a_snapshot_replaces_queued_snapshots_and_deltas() {
+        let (outbox, metrics) = (
+            Outbox::new(10, OverflowPolicy::DropOldest),
+            Metrics::default(),
+        );
+        outbox.push(Kind::Snapshot, text("snapshot 1"), &metrics);
+        outbox.push(Kind::Delta, text("delta 2"), &metrics);
+        outbox.push(Kind::Event, text("buzz"), &metrics);
+        outbox.push(Kind::Delta, text("delta 3"), &metrics);
+        outbox.push(Kind::Snapshot, text("snapshot 3"), &metrics);
+
+        assert_eq!(drain(&outbox).await, ["buzz", "snapshot 3"]);
+        assert_eq!(metrics.coalesced_total.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn overflowing_drops_the_oldest_delta_but_never_an_event() {
+        let (outbox, metrics) = (
+            Outbox::new(3, OverflowPolicy::DropOldest),
+            Metrics::default(),
+        );
+        outbox.push(Kind::Event, text("buzz"), &metrics);
+        outbox.push(Kind::Delta, text("delta 1"), &metrics);
+        outbox.push(Kind::Delta, text("delta 2"), &metrics);
+        assert!(outbox.push(Kind::Event, text("timer"), &metrics));
+
+        assert_eq!(drain(&outbox).await, ["buzz", "delta 2", "timer"]);
+        assert_eq!(metrics.dropped_total.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn overflowing_with_only_events_queued_disconnects() {
+        let (outbox, metrics) = (
+            Outbox::new(2, OverflowPolicy::DropOldest),
+            Metrics::default(),
+        );
+        outbox.push(Kind::Event, text("buzz"), &metrics);
+        outbox.push(Kind::Event, text("timer"), &metrics);
+        assert!(!outbox.push(Kind::Delta, text("delta 1"), &metrics));
+
+        assert_eq!(outbox.pop().await, None);
+        assert!(!outbox.push(Kind::Event, text("late"), &metrics));
+        assert_eq!(metrics.dropped_total.load(Ordering::Relaxed), 0);
+        let disconnects = metrics
+            .slow_consumer_disconnects_total
+            .load(Ordering::Relaxed);
+        assert_eq!(disconnects, 1);
+    }
+
+    #[tokio::test]
+    async fn the_disconnect_policy_closes_a_full_queue() {
+        let (outbox, metrics) = (
+            Outbox::new(1, OverflowPolicy::Disconnect),
+            Metrics::default(),
+        );
+        assert!(outbox.push(Kind::Delta, text("delta 1"), &metrics));
+        assert!(!outbox.push(Kind::Delta, text("delta 2"), &metrics));
+        assert_eq!(outbox.pop().await, None);
+    }
+}
@@ -0,0 +1,36 @@
+use std::time::Instant;
+
+/// Token bucket that limits how many messages a single connection may send.
+/// Allows bursts of up to one second's worth of messages.
+pub struct RateLimiter {
+    per_sec: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// A limit of 0 lets everything through.
+    pub fn new(per_sec: u32) -> Self {
+        Self {
+            per_sec: f64::from(per_sec),
+            tokens: f64::from(per_sec),
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Takes a token; false if the connection is sending too fast.
+    pub fn allow(&mut self) -> bool {
+        if self.per_sec == 0.0 {
+            return true;
+        }
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_sec).min(self.per_sec);
+        self.last_refill = now;
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
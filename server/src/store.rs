@@ -0,0 +1,571 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{mpsc, Mutex},
+    thread,
+};
+
+use common::{GameState, GameStateJson, LoggedEvent, Question, QuestionRecord, ScoreLedger};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use uuid::Uuid;
+
+/// Everything needed to bring a game back after a restart, including the
+/// secrets and records that `GameStateJson` leaves out on purpose.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredGame {
+    pub game_state: GameStateJson,
+    pub session_tokens: HashMap<Uuid, Uuid>,
+    #[serde(default)]
+    pub question_bank: Vec<Question>,
+    #[serde(default)]
+    pub choice_key: Option<usize>,
+    #[serde(default)]
+    pub choice_picks: HashMap<Uuid, usize>,
+    #[serde(default)]
+    pub answer_texts: HashMap<Uuid, String>,
+    #[serde(default)]
+    pub wager_amounts: HashMap<Uuid, i32>,
+    #[serde(default)]
+    pub score_ledger: ScoreLedger,
+    #[serde(default)]
+    pub history: Vec<QuestionRecord>,
+}
+
+impl StoredGame {
+    pub fn snapshot(game: &GameState) -> Self {
+        Self {
+            game_state: game.to_json(),
+            session_tokens: game.session_tokens.clone(),
+            question_bank: game.question_bank.clone(),
+            choice_key: game.choice_key,
+            choice_picks: game.choice_picks.clone(),
+            answer_texts: game.answer_texts.clone(),
+            wager_amounts: game.wager_amounts.clone(),
+            score_ledger: game.score_ledger.clone(),
+            history: game.progress.history.clone(),
+        }
+    }
+
+    pub fn restore(self, events: Vec<LoggedEvent>) -> GameState {
+        let mut game: GameState = self.game_state.into();
+        game.session_tokens = self.session_tokens;
+        game.question_bank = self.question_bank;
+        game.choice_key = self.choice_key;
+        game.choice_picks = self.choice_picks;
+        game.answer_texts = self.answer_texts;
+        game.wager_amounts = self.wager_amounts;
+        game.score_ledger = self.score_ledger;
+        game.progress.history = self.history;
+        game.events = events;
+        game
+    }
+}
+
+/// A stored game together with its full event log
+pub type StoredEntry = (usize, StoredGame, Vec<LoggedEvent>);
+
+/// Where games are kept between mutations so they survive a restart.
+pub trait GameStore: Send + Sync {
+    /// Stores the latest snapshot along with the events logged since the last save.
+    fn save(
+        &self,
+        game_code: usize,
+        game: &StoredGame,
+        new_events: &[LoggedEvent],
+    ) -> io::Result<()>;
+    /// The game has ended; only its log is kept, for `finished_log`.
+    fn finish(&self, game_code: usize, events: &[LoggedEvent]) -> io::Result<()>;
+    fn load_all(&self) -> io::Result<Vec<StoredEntry>>;
+    fn finished_log(&self, game_code: usize) -> io::Result<Option<Vec<LoggedEvent>>>;
+}
+
+/// Keeps snapshots for the lifetime of the process only
+#[derive(Default)]
+pub struct MemoryStore {
+    games: Mutex<HashMap<usize, (StoredGame, Vec<LoggedEvent>)>>,
+    finished: Mutex<HashMap<usize, Vec<LoggedEvent>>>,
+}
+
+impl GameStore for MemoryStore {
+    fn save(
+        &self,
+        game_code: usize,
+        game: &StoredGame,
+        new_events: &[LoggedEvent],
+    ) -> io::Result<()> {
+        let mut games = self.games.lock().unwrap();
+        let (stored, events) = games
+            .entry(game_code)
+            .or_insert_with(|| (game.clone(), Vec::new()));
+        *stored = game.clone();
+        events.extend_from_slice(new_events);
+        Ok(())
+    }
+
+    fn finish(&self, game_code: usize, events: &[LoggedEvent]) -> io::Result<()> {
+        self.games.lock().unwrap().remove(&game_code);
+        self.finished
+            .lock()
+            .unwrap()
+            .insert(game_code, events.to_vec());
+        Ok(())
+    }
+
+    fn load_all(&self) -> io::Result<Vec<StoredEntry>> {
+        let games = self.games.lock().unwrap();
+        Ok(games
+            .iter()
+            .map(|(code, (game, events))| (*code, game.clone(), events.clone()))
+            .collect())
+    }
+
+    fn finished_log(&self, game_code: usize) -> io::Result<Option<Vec<LoggedEvent>>> {
+        Ok(self.finished.lock().unwrap().get(&game_code).cloned())
+    }
+}
+
+/// One line of the append-only file. The last snapshot of a game wins, while
+/// its events add up across records.
+#[derive(Serialize, Deserialize)]
+struct Record {
+    game_code: usize,
+    // `None` marks a game that has ended
+    game: Option<StoredGame>,
+    #[serde(default)]
+    events: Vec<LoggedEvent>,
+}
+
+// The file is only compacted once it grew by at least this much
+const MIN_COMPACTION_BYTES: u64 = 1 << 20;
+
+/// Appends snapshots as JSON lines. The file is compacted to one line per
+/// live game when it is loaded, and again whenever it has doubled since, so
+/// the snapshots of a long game don't pile up. Logs of finished games are
+/// moved to their own file in a `logs` directory next to it.
+///
+/// The file is only touched by a writer thread, so games never wait for the
+/// disk. Saves that queue up while it is busy are merged into one record.
+pub struct FileStore {
+    writer: mpsc::Sender<WriterCommand>,
+}
+
+enum WriterCommand {
+    Save {
+        game_code: usize,
+        game: Box<StoredGame>,
+        events: Vec<LoggedEvent>,
+    },
+    Finish {
+        game_code: usize,
+        events: Vec<LoggedEvent>,
+    },
+    LoadAll {
+        reply: mpsc::Sender<io::Result<Vec<StoredEntry>>>,
+    },
+    FinishedLog {
+        game_code: usize,
+        reply: mpsc::Sender<io::Result<Option<Vec<LoggedEvent>>>>,
+    },
+}
+
+impl FileStore {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let writer = FileWriter {
+            compacted_len: file.metadata()?.len(),
+            appended_len: 0,
+            path,
+            file,
+        };
+        let (commands, rx) = mpsc::channel();
+        thread::Builder::new()
+            .name("game-store".to_string())
+            .spawn(move || writer.run(rx))?;
+        Ok(Self { writer: commands })
+    }
+
+    fn send(&self, command: WriterCommand) -> io::Result<()> {
+        self.writer.send(command).map_err(|_| writer_gone())
+    }
+
+    /// Sends a command that answers, and waits for the answer
+    fn request<T>(
+        &self,
+        command: impl FnOnce(mpsc::Sender<io::Result<T>>) -> WriterCommand,
+    ) -> io::Result<T> {
+        let (reply, reply_rx) = mpsc::channel();
+        self.send(command(reply))?;
+        reply_rx.recv().map_err(|_| writer_gone())?
+    }
+}
+
+fn writer_gone() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "The store's writer thread has stopped",
+    )
+}
+
+impl GameStore for FileStore {
+    fn save(
+        &self,
+        game_code: usize,
+        game: &StoredGame,
+        new_events: &[LoggedEvent],
+    ) -> io::Result<()> {
+        self.send(WriterCommand::Save {
+            game_code,
+            game: Box::new(game.clone()),
+            events: new_events.to_vec(),
+        })
+    }
+
+    fn finish(&self, game_code: usize, events: &[LoggedEvent]) -> io::Result<()> {
+        self.send(WriterCommand::Finish {
+            game_code,
+            events: events.to_vec(),
+        })
+    }
+
+    fn load_all(&self) -> io::Result<Vec<StoredEntry>> {
+        self.request(|reply| WriterCommand::LoadAll { reply })
+    }
+
+    fn finished_log(&self, game_code: usize) -> io::Result<Option<Vec<LoggedEvent>>> {
+        self.request(|reply| WriterCommand::FinishedLog { game_code, reply })
+    }
+}
+
+/// The writer thread's side of a `FileStore`
+struct FileWriter {
+    path: PathBuf,
+    file: File,
+    // Size of the file right after it was last compacted
+    compacted_len: u64,
+    // Bytes appended since
+    appended_len: u64,
+}
+
+impl FileWriter {
+    fn run(mut self, commands: mpsc::Receiver<WriterCommand>) {
+        // Merged saves, one per game
+        let mut pending: Vec<Record> = Vec::new();
+        while let Ok(command) = commands.recv() {
+            let mut next = Some(command);
+            while let Some(command) = next {
+                match command {
+                    WriterCommand::Save {
+                        game_code,
+                        game,
+                        events,
+                    } => match pending.iter_mut().find(|r| r.game_code == game_code) {
+                        Some(record) => {
+                            record.game = Some(*game);
+                            record.events.extend(events);
+                        }
+                        None => pending.push(Record {
+                            game_code,
+                            game: Some(*game),
+                            events,
+                        }),
+                    },
+                    // Anything else has to see the saves that came before it
+                    command => {
+                        self.flush(&mut pending);
+                        self.handle(command);
+                    }
+                }
+                next = commands.try_recv().ok();
+            }
+            self.flush(&mut pending);
+        }
+    }
+
+    fn flush(&mut self, pending: &mut Vec<Record>) {
+        for record in pending.drain(..) {
+            if let Err(e) = self.append(&record) {
+                warn!("Failed to store game {}: {}", record.game_code, e);
+            }
+        }
+        if self.appended_len > self.compacted_len.max(MIN_COMPACTION_BYTES) {
+            if let Err(e) = self.compact() {
+                warn!("Failed to compact the game store: {}", e);
+            }
+        }
+    }
+
+    fn handle(&mut self, command: WriterCommand) {
+        match command {
+            WriterCommand::Save { .. } => unreachable!("saves are merged before they are written"),
+            WriterCommand::Finish { game_code, events } => {
+                if let Err(e) = self.finish(game_code, &events) {
+                    warn!("Failed to archive game {}: {}", game_code, e);
+                }
+            }
+            WriterCommand::LoadAll { reply } => {
+                let games = self.compact().map(|games| {
+                    games
+                        .into_iter()
+                        .map(|(code, (game, events))| (code, game, events))
+                        .collect()
+                });
+                let _ = reply.send(games);
+            }
+            WriterCommand::FinishedLog { game_code, reply } => {
+                let _ = reply.send(self.finished_log(game_code));
+            }
+        }
+    }
+
+    fn logs_dir(&self) -> PathBuf {
+        self.path.parent().unwrap_or(Path::new(".")).join("logs")
+    }
+
+    /// Codes are reused once a game has ended, so a log is named after the
+    /// code and the time its game started.
+    fn finished_log_path(&self, game_code: usize, started_at_ms: u64) -> PathBuf {
+        self.logs_dir()
+            .join(format!("{}-{}.json", game_code, started_at_ms))
+    }
+
+    fn append(&mut self, record: &Record) -> io::Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        self.file.flush()?;
+        self.appended_len += line.len() as u64;
+        Ok(())
+    }
+
+    fn finish(&mut self, game_code: usize, events: &[LoggedEvent]) -> io::Result<()> {
+        let started_at_ms = events.first().map_or(0, |e| e.at_ms);
+        fs::create_dir_all(self.logs_dir())?;
+        fs::write(
+            self.finished_log_path(game_code, started_at_ms),
+            serde_json::to_vec(events)?,
+        )?;
+        self.append(&Record {
+            game_code,
+            game: None,
+            events: Vec::new(),
+        })
+    }
+
+    /// Rewrites the file with one record per live game and returns those games
+    fn compact(&mut self) -> io::Result<HashMap<usize, (StoredGame, Vec<LoggedEvent>)>> {
+        let mut games: HashMap<usize, (StoredGame, Vec<LoggedEvent>)> = HashMap::new();
+        for line in BufReader::new(File::open(&self.path)?).lines() {
+            let line = line?;
+            // A crash mid-write can leave a torn last line behind
+            let Ok(record) = serde_json::from_str::<Record>(&line) else {
+                continue;
+            };
+            match record.game {
+                Some(game) => {
+                    let entry = games
+                        .entry(record.game_code)
+                        .or_insert_with(|| (game.clone(), Vec::new()));
+                    entry.0 = game;
+                    entry.1.extend(record.events);
+                }
+                None => {
+                    games.remove(&record.game_code);
+                }
+            }
+        }
+
+        let compacted = self.path.with_extension("compacting");
+        let mut out = File::create(&compacted)?;
+        for (game_code, (game, events)) in &games {
+            let record = Record {
+                game_code: *game_code,
+                game: Some(game.clone()),
+                events: events.clone(),
+            };
+            writeln!(out, "{}", serde_json::to_string(&record)?)?;
+        }
+        out.sync_all()?;
+        fs::rename(&compacted, &self.path)?;
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        self.compacted_len = self.file.metadata()?.len();
+        self.appended_len = 0;
+
+        Ok(games)
+    }
+
+    /// The log of the latest game that had this code
+    fn finished_log(&self, game_code: usize) -> io::Result<Option<Vec<LoggedEvent>>> {
+        let entries = match fs::read_dir(self.logs_dir()) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let prefix = format!("{}-", game_code);
+        let mut latest = None;
+        for entry in entries {
+            let name = entry?.file_name();
+            let started_at_ms = name
+                .to_str()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|name| name.strip_suffix(".json"))
+                .and_then(|started| started.parse::<u64>().ok());
+            latest = latest.max(started_at_ms);
+        }
+        let Some(started_at_ms) = latest else {
+            return Ok(None);
+        };
+        let bytes = fs::read(self.finished_log_path(game_code, started_at_ms))?;
+        Ok(Some(serde_json::from_slice(&bytes)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::GameEvent;
+
+    /// A store file in a fresh directory of its own
+    fn temp_store_path() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("buzzer-store-{}", Uuid::new_v4()))
+            .join("games.jsonl")
+    }
+
+    fn log(started_at_ms: u64, event: GameEvent) -> Vec<LoggedEvent> {
+        vec![LoggedEvent {
+            at_ms: started_at_ms,
+            event,
+        }]
+    }
+
+    /// A game with a host and a session token, locked at `at_ms`
+    fn stored_game(at_ms: u64) -> StoredGame {
+        let host_id = Uuid::new_v4();
+        let mut game = GameState::new(host_id, 0);
+        game.issue_session_token(host_id);
+        game.record(at_ms, GameEvent::Locked);
+        StoredGame::snapshot(&game)
+    }
+
+    fn record_line(game_code: usize, game: Option<StoredGame>, events: Vec<LoggedEvent>) -> String {
+        let record = Record {
+            game_code,
+            game,
+            events,
+        };
+        serde_json::to_string(&record).unwrap() + "\n"
+    }
+
+    fn as_json(game: &StoredGame) -> serde_json::Value {
+        serde_json::to_value(game).unwrap()
+    }
+
+    #[test]
+    fn saved_games_load_again() {
+        let path = temp_store_path();
+        let game = stored_game(1_000);
+        let store = FileStore::open(&path).unwrap();
+        store
+            .save(1234, &game, &log(1_000, GameEvent::Locked))
+            .unwrap();
+        store
+            .save(1234, &game, &log(2_000, GameEvent::Unlocked))
+            .unwrap();
+        // Answers once everything sent before it has been written
+        store.load_all().unwrap();
+        drop(store);
+
+        let loaded = FileStore::open(&path).unwrap().load_all().unwrap();
+        assert_eq!(loaded.len(), 1);
+        let (game_code, loaded_game, events) = &loaded[0];
+        assert_eq!(*game_code, 1234);
+        assert_eq!(as_json(loaded_game), as_json(&game));
+        let times: Vec<u64> = events.iter().map(|e| e.at_ms).collect();
+        assert_eq!(times, [1_000, 2_000]);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn a_torn_last_line_is_skipped() {
+        let path = temp_store_path();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let game = stored_game(1_000);
+        let complete = record_line(1234, Some(game.clone()), log(1_000, GameEvent::Locked));
+        let torn = record_line(
+            1234,
+            Some(stored_game(2_000)),
+            log(2_000, GameEvent::Unlocked),
+        );
+        fs::write(&path, format!("{}{}", complete, &torn[..torn.len() / 2])).unwrap();
+
+        let loaded = FileStore::open(&path).unwrap().load_all().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(as_json(&loaded[0].1), as_json(&game));
+        assert_eq!(loaded[0].2, log(1_000, GameEvent::Locked));
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn compaction_keeps_the_latest_snapshot_of_live_games() {
+        let path = temp_store_path();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let latest = stored_game(2_000);
+        let lines = [
+            record_line(
+                1234,
+                Some(stored_game(1_000)),
+                log(1_000, GameEvent::Locked),
+            ),
+            record_line(
+                5678,
+                Some(stored_game(1_500)),
+                log(1_500, GameEvent::Locked),
+            ),
+            record_line(1234, Some(latest.clone()), log(2_000, GameEvent::Unlocked)),
+            record_line(5678, None, Vec::new()),
+        ];
+        fs::write(&path, lines.concat()).unwrap();
+
+        let loaded = FileStore::open(&path).unwrap().load_all().unwrap();
+        assert_eq!(loaded.len(), 1);
+        let (game_code, game, events) = &loaded[0];
+        assert_eq!(*game_code, 1234);
+        assert_eq!(as_json(game), as_json(&latest));
+        assert_eq!(events.len(), 2);
+
+        let compacted = fs::read_to_string(&path).unwrap();
+        assert_eq!(compacted.lines().count(), 1);
+        let record: Record = serde_json::from_str(compacted.trim_end()).unwrap();
+        assert_eq!(record.game_code, 1234);
+        assert_eq!(record.events, events.clone());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn a_reused_code_keeps_the_earlier_log() {
+        let path = temp_store_path();
+        let store = FileStore::open(&path).unwrap();
+        assert!(store.finished_log(1234).unwrap().is_none());
+
+        store.finish(1234, &log(1_000, GameEvent::Locked)).unwrap();
+        store
+            .finish(1234, &log(2_000, GameEvent::Unlocked))
+            .unwrap();
+        store.finish(5678, &log(3_000, GameEvent::Locked)).unwrap();
+
+        let latest = store.finished_log(1234).unwrap().unwrap();
+        assert_eq!(latest[0].at_ms, 2_000);
+        let logs = fs::read_dir(path.parent().unwrap().join("logs"))
+            .unwrap()
+            .count();
+        assert_eq!(logs, 3);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}